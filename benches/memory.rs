@@ -0,0 +1,113 @@
+// Compares `PagedMemory` with `SimpleMemory` on a deep callstack: every frame writes into
+// its stack and heap, reads calldata of the caller, and returns its heap to the caller.
+// Run with `cargo bench --bench memory`.
+
+use std::time::{Duration, Instant};
+
+use zk_evm::abstractions::{Memory, MemoryType};
+use zk_evm::aux_structures::{MemoryIndex, MemoryLocation, MemoryPage, MemoryQuery, Timestamp};
+use zk_evm::ethereum_types::U256;
+use zk_evm::reference_impls::memory::SimpleMemory;
+use zk_evm::reference_impls::paged_memory::PagedMemory;
+use zk_evm::vm_state::CallStackEntry;
+use zk_evm::zkevm_opcode_defs::decoding::EncodingModeProduction;
+use zk_evm::zkevm_opcode_defs::{FatPointer, BOOTLOADER_BASE_PAGE, NEW_MEMORY_PAGES_PER_FAR_CALL};
+
+const DEPTH: u32 = 1000;
+const WORDS_PER_FRAME: u32 = 64;
+const ITERATIONS: u32 = 5;
+
+fn query(memory_type: MemoryType, page: u32, index: u32, value: Option<U256>) -> MemoryQuery {
+    MemoryQuery {
+        timestamp: Timestamp(0),
+        location: MemoryLocation {
+            memory_type,
+            page: MemoryPage(page),
+            index: MemoryIndex(index),
+        },
+        value: value.unwrap_or(U256::zero()),
+        rw_flag: value.is_some(),
+        value_is_pointer: false,
+    }
+}
+
+fn base_page(depth: u32) -> MemoryPage {
+    MemoryPage(BOOTLOADER_BASE_PAGE + depth * NEW_MEMORY_PAGES_PER_FAR_CALL)
+}
+
+fn heap_slice(depth: u32) -> FatPointer {
+    FatPointer {
+        offset: 0,
+        memory_page: CallStackEntry::<8, EncodingModeProduction>::heap_page_from_base(base_page(
+            depth,
+        ))
+        .0,
+        start: 0,
+        length: WORDS_PER_FRAME * 32,
+    }
+}
+
+fn run_deep_callstack<M: Memory>(memory: &mut M) {
+    let mut current_base = MemoryPage(0);
+    for depth in 0..DEPTH {
+        let calldata = if depth == 0 {
+            FatPointer::empty()
+        } else {
+            heap_slice(depth - 1)
+        };
+        memory.start_global_frame(current_base, base_page(depth), calldata, Timestamp(0));
+        current_base = base_page(depth);
+
+        let stack_page =
+            CallStackEntry::<8, EncodingModeProduction>::stack_page_from_base(current_base).0;
+        let heap_page =
+            CallStackEntry::<8, EncodingModeProduction>::heap_page_from_base(current_base).0;
+        for idx in 0..WORDS_PER_FRAME {
+            let value = Some(U256::from(depth * WORDS_PER_FRAME + idx));
+            memory.execute_partial_query(0, query(MemoryType::Stack, stack_page, idx, value));
+            memory.execute_partial_query(0, query(MemoryType::Heap, heap_page, idx, value));
+            if depth > 0 {
+                memory.execute_partial_query(
+                    0,
+                    query(MemoryType::FatPointer, calldata.memory_page, idx, None),
+                );
+            }
+        }
+    }
+    for depth in (0..DEPTH).rev() {
+        memory.finish_global_frame(base_page(depth), heap_slice(depth), Timestamp(0));
+    }
+}
+
+fn measure(name: &str, mut f: impl FnMut()) {
+    let mut total = Duration::ZERO;
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        f();
+        total += start.elapsed();
+    }
+    println!("{:<48} {:?} per iteration", name, total / ITERATIONS);
+}
+
+fn main() {
+    measure("SimpleMemory::new", || {
+        let mut memory = SimpleMemory::new();
+        run_deep_callstack(&mut memory);
+    });
+    measure("SimpleMemory::new_without_preallocations", || {
+        let mut memory = SimpleMemory::new_without_preallocations();
+        run_deep_callstack(&mut memory);
+    });
+    measure("PagedMemory::new", || {
+        let mut memory = PagedMemory::new();
+        run_deep_callstack(&mut memory);
+    });
+
+    let mut memory = PagedMemory::new();
+    run_deep_callstack(&mut memory);
+    measure("PagedMemory::snapshot x 1000", || {
+        for _ in 0..1000 {
+            std::hint::black_box(memory.snapshot());
+        }
+    });
+}
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// Random well-formed programs, registers and callees, invariants are checked after every cycle
+fuzz_target!(|data: &[u8]| {
+    zk_evm::testing::fuzzing::fuzz_one(data);
+});
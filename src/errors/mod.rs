@@ -11,3 +11,213 @@ impl std::fmt::Display for OpcodeDecodingError {
 }
 
 impl std::error::Error for OpcodeDecodingError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BytecodeHashingError {
+    LengthIsNotDivisibleBy32,
+    EvenNumberOfWords,
+    BytecodeIsTooLong,
+}
+
+impl std::fmt::Display for BytecodeHashingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for BytecodeHashingError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecommittmentError {
+    UnknownCodeHash(crate::ethereum_types::U256),
+    CodeLengthMismatch {
+        hash: crate::ethereum_types::U256,
+        declared_length_in_words: u16,
+        actual_length_in_words: u16,
+    },
+}
+
+impl std::fmt::Display for DecommittmentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for DecommittmentError {}
+
+/// Oracle response (or request from VM to the oracle) that contradicts the semantics
+/// VM relies on. Reported by checking wrappers from `reference_impls::checked`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OracleViolation {
+    MemoryReadMismatch {
+        page: u32,
+        index: u32,
+        timestamp: u32,
+        written_at: u32,
+        expected: crate::vm_state::PrimitiveValue,
+        actual: crate::vm_state::PrimitiveValue,
+    },
+    CodeReadMismatch {
+        page: u32,
+        index: u32,
+        expected: crate::ethereum_types::U256,
+        actual: crate::ethereum_types::U256,
+    },
+    MemoryQueryIsModified {
+        page: u32,
+        index: u32,
+        timestamp: u32,
+    },
+    StackAccessOutsideOfCurrentFrame {
+        page: u32,
+        current_stack_page: Option<u32>,
+        rw_flag: bool,
+    },
+    GlobalFrameMismatch {
+        expected_stack_page: Option<u32>,
+        actual_stack_page: u32,
+    },
+    StorageReadMismatch {
+        shard_id: u8,
+        address: crate::ethereum_types::Address,
+        key: crate::ethereum_types::U256,
+        timestamp: u32,
+        expected: crate::ethereum_types::U256,
+        actual: crate::ethereum_types::U256,
+    },
+    StorageRollbackMismatch {
+        shard_id: u8,
+        address: crate::ethereum_types::Address,
+        key: crate::ethereum_types::U256,
+        expected: crate::ethereum_types::U256,
+        actual: crate::ethereum_types::U256,
+    },
+    StorageQueryIsModified {
+        shard_id: u8,
+        address: crate::ethereum_types::Address,
+        key: crate::ethereum_types::U256,
+        timestamp: u32,
+    },
+    UnbalancedFrame {
+        oracle: &'static str,
+    },
+    DecommittedLengthMismatch {
+        hash: crate::ethereum_types::U256,
+        declared_length_in_words: u16,
+        decommitted_length_in_words: u16,
+    },
+    DecommittedPageMismatch {
+        hash: crate::ethereum_types::U256,
+        previous_page: Option<u32>,
+        page: u32,
+    },
+    // message with this timestamp is missing after the frame has finished, or is present
+    // while it must have been rolled back
+    EventRollbackMismatch {
+        timestamp: u32,
+        is_missing: bool,
+        panicked: bool,
+    },
+}
+
+impl std::fmt::Display for OracleViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for OracleViolation {}
+
+/// Memory queries that can not be explained by memory that starts zeroed out. Reported for
+/// the first inconsistent query in (page, index, timestamp) order, same as the RAM permutation
+/// circuit would fail on it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemoryWitnessError {
+    ReadValueMismatch {
+        page: u32,
+        index: u32,
+        timestamp: u32,
+        // `None` if the cell was never written before
+        written_at: Option<u32>,
+        expected: crate::ethereum_types::U256,
+        actual: crate::ethereum_types::U256,
+    },
+    PointerFlagMismatch {
+        page: u32,
+        index: u32,
+        timestamp: u32,
+        written_at: Option<u32>,
+        expected: bool,
+        actual: bool,
+    },
+}
+
+impl std::fmt::Display for MemoryWitnessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for MemoryWitnessError {}
+
+/// Storage log queries that can not be explained by sequential application to the storage
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorageLogError {
+    ReadValueMismatch {
+        shard_id: u8,
+        address: crate::ethereum_types::Address,
+        key: crate::ethereum_types::U256,
+        timestamp: u32,
+        expected: crate::ethereum_types::U256,
+        actual: crate::ethereum_types::U256,
+    },
+    RollbackWithoutWrite {
+        shard_id: u8,
+        address: crate::ethereum_types::Address,
+        key: crate::ethereum_types::U256,
+        timestamp: u32,
+    },
+    FinalValueMismatch {
+        shard_id: u8,
+        address: crate::ethereum_types::Address,
+        key: crate::ethereum_types::U256,
+        expected: crate::ethereum_types::U256,
+        actual: crate::ethereum_types::U256,
+    },
+}
+
+impl std::fmt::Display for StorageLogError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for StorageLogError {}
+
+/// Limit from `ExecutionLimits` that has stopped the VM
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExecutionLimitExceeded {
+    Cycles {
+        limit: u64,
+    },
+    WallTime {
+        limit: std::time::Duration,
+        elapsed: std::time::Duration,
+    },
+    MemoryPages {
+        limit: u32,
+        reserved: u32,
+    },
+    CallstackDepth {
+        limit: usize,
+        depth: usize,
+    },
+}
+
+impl std::fmt::Display for ExecutionLimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for ExecutionLimitExceeded {}
@@ -1,5 +1,6 @@
 use super::*;
 
+use crate::witness_trace::{FatPointerEvent, HeapGrowthEvent, HeapGrowthSource, PointerTransfer};
 use zk_evm_abstractions::aux::*;
 use zk_evm_abstractions::queries::LogQuery;
 use zkevm_opcode_defs::definitions::far_call::*;
@@ -85,6 +86,8 @@ impl<const N: usize, E: VmEncodingMode<N>> DecodedOpcode<N, E> {
         far_call_abi.constructor_call = far_call_abi.constructor_call & is_kernel_mode;
         far_call_abi.to_system = far_call_abi.to_system & dst_is_kernel;
 
+        let pointer_event_location = vm_state.pointer_event_location();
+
         let current_stack = vm_state.local_state.callstack.get_current_stack();
 
         // read for case of delegatecall
@@ -327,7 +330,9 @@ impl<const N: usize, E: VmEncodingMode<N>> DecodedOpcode<N, E> {
             let current_stack_mut = vm_state.local_state.callstack.get_current_stack_mut();
 
             // potentially pay for memory growth
-            let memory_growth_in_bytes = match far_call_abi.forwarding_mode {
+            let (memory_growth_in_bytes, bound_before_growth, penalized_growth) = match far_call_abi
+                .forwarding_mode
+            {
                 a @ FarCallForwardPageType::UseHeap | a @ FarCallForwardPageType::UseAuxHeap => {
                     // pointer is already validated, so we do not need to check that start + length do not overflow
                     let mut upper_bound = far_call_abi.memory_quasi_fat_pointer.start
@@ -363,17 +368,41 @@ impl<const N: usize, E: VmEncodingMode<N>> DecodedOpcode<N, E> {
                         }
                     }
 
-                    diff
+                    (diff, current_bound, penalize_out_of_bounds_growth)
                 }
-                FarCallForwardPageType::ForwardFatPointer => 0u32,
+                FarCallForwardPageType::ForwardFatPointer => (0u32, 0u32, false),
             };
 
-            #[allow(dropping_references)]
-            drop(current_stack_mut);
-
             // MEMORY_GROWTH_ERGS_PER_BYTE is always 1
             let cost_of_memory_growth =
                 memory_growth_in_bytes.wrapping_mul(zkevm_opcode_defs::MEMORY_GROWTH_ERGS_PER_BYTE);
+
+            let heap_growth = if memory_growth_in_bytes > 0 || penalized_growth {
+                Some(HeapGrowthEvent {
+                    source: HeapGrowthSource::FarCall,
+                    base_memory_page: current_stack_mut.base_memory_page.0,
+                    code_address: current_stack_mut.code_address,
+                    // pc is not yet updated
+                    pc: current_stack_mut.pc.as_u64() as u32,
+                    is_aux_heap: far_call_abi.forwarding_mode == FarCallForwardPageType::UseAuxHeap,
+                    old_bound: bound_before_growth,
+                    new_bound: bound_before_growth.wrapping_add(memory_growth_in_bytes),
+                    ergs_charged: cost_of_memory_growth,
+                    out_of_range_penalty: penalized_growth,
+                })
+            } else {
+                None
+            };
+
+            #[allow(dropping_references)]
+            drop(current_stack_mut);
+
+            if let Some(event) = heap_growth {
+                vm_state
+                    .witness_tracer
+                    .add_heap_growth(vm_state.local_state.monotonic_cycle_counter, &event);
+            }
+
             let remaining_ergs_after_growth = if remaining_ergs >= cost_of_memory_growth {
                 remaining_ergs - cost_of_memory_growth
             } else {
@@ -568,6 +597,29 @@ impl<const N: usize, E: VmEncodingMode<N>> DecodedOpcode<N, E> {
             Timestamp(vm_state.local_state.timestamp),
         );
 
+        // masked pointer is not passed anywhere
+        if far_call_abi.memory_quasi_fat_pointer.memory_page != 0 {
+            let event = match far_call_abi.forwarding_mode {
+                FarCallForwardPageType::ForwardFatPointer => FatPointerEvent::Forwarded {
+                    location: pointer_event_location,
+                    transfer: PointerTransfer::Calldata,
+                    original: FarCallABI::from_u256(abi_src).memory_quasi_fat_pointer,
+                    forwarded: far_call_abi.memory_quasi_fat_pointer,
+                    receiver_base_page: new_base_memory_page.0,
+                },
+                a => FatPointerEvent::Created {
+                    location: pointer_event_location,
+                    transfer: PointerTransfer::Calldata,
+                    is_aux_heap: a == FarCallForwardPageType::UseAuxHeap,
+                    pointer: far_call_abi.memory_quasi_fat_pointer,
+                    receiver_base_page: new_base_memory_page.0,
+                },
+            };
+            vm_state
+                .witness_tracer
+                .add_fat_pointer_event(vm_state.local_state.monotonic_cycle_counter, &event);
+        }
+
         // write down calldata information
 
         let r1_value = PrimitiveValue {
@@ -1,5 +1,6 @@
 use super::*;
 
+use crate::witness_trace::PrecompileCallEvent;
 use zk_evm_abstractions::queries::LogQuery;
 use zkevm_opcode_defs::{LogOpcode, Opcode, PrecompileCallABI, FIRST_MESSAGE_FLAG_IDX};
 
@@ -314,6 +315,20 @@ impl<const N: usize, E: VmEncodingMode<N>> DecodedOpcode<N, E> {
                     is_service: is_first_message,
                 };
 
+                vm_state.witness_tracer.add_precompile_call(
+                    vm_state.local_state.monotonic_cycle_counter,
+                    &PrecompileCallEvent {
+                        address,
+                        input_memory_offset: precompile_abi.input_memory_offset,
+                        input_memory_length: precompile_abi.input_memory_length,
+                        output_memory_offset: precompile_abi.output_memory_offset,
+                        output_memory_length: precompile_abi.output_memory_length,
+                        memory_page_to_read: precompile_abi.memory_page_to_read,
+                        memory_page_to_write: precompile_abi.memory_page_to_write,
+                        precompile_interpreted_data: precompile_abi.precompile_interpreted_data,
+                        extra_ergs_cost: extra_cost,
+                    },
+                );
                 vm_state.call_precompile(vm_state.local_state.monotonic_cycle_counter, query);
                 let result = PrimitiveValue {
                     value: U256::from(1u64),
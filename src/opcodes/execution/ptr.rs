@@ -1,5 +1,6 @@
 use super::*;
 
+use crate::witness_trace::FatPointerEvent;
 use zkevm_opcode_defs::{FatPointer, Opcode, PtrOpcode};
 
 impl<const N: usize, E: VmEncodingMode<N>> DecodedOpcode<N, E> {
@@ -27,6 +28,8 @@ impl<const N: usize, E: VmEncodingMode<N>> DecodedOpcode<N, E> {
             _ => unreachable!(),
         };
 
+        let pointer_event_location = vm_state.pointer_event_location();
+
         vm_state.local_state.callstack.get_current_stack_mut().pc = new_pc;
 
         match inner_variant {
@@ -86,6 +89,16 @@ impl<const N: usize, E: VmEncodingMode<N>> DecodedOpcode<N, E> {
                     is_pointer: true,
                 };
 
+                vm_state.witness_tracer.add_fat_pointer_event(
+                    vm_state.local_state.monotonic_cycle_counter,
+                    &FatPointerEvent::Narrowed {
+                        location: pointer_event_location,
+                        opcode: inner_variant,
+                        input: FatPointer::from_u256(src0),
+                        output: FatPointer::from_u256(result.value),
+                    },
+                );
+
                 vm_state.perform_dst0_update(
                     vm_state.local_state.monotonic_cycle_counter,
                     result,
@@ -130,6 +143,16 @@ impl<const N: usize, E: VmEncodingMode<N>> DecodedOpcode<N, E> {
                     is_pointer: true,
                 };
 
+                vm_state.witness_tracer.add_fat_pointer_event(
+                    vm_state.local_state.monotonic_cycle_counter,
+                    &FatPointerEvent::Narrowed {
+                        location: pointer_event_location,
+                        opcode: inner_variant,
+                        input: FatPointer::from_u256(src0),
+                        output: FatPointer::from_u256(result.value),
+                    },
+                );
+
                 vm_state.perform_dst0_update(
                     vm_state.local_state.monotonic_cycle_counter,
                     result,
@@ -183,6 +206,16 @@ impl<const N: usize, E: VmEncodingMode<N>> DecodedOpcode<N, E> {
                     is_pointer: true,
                 };
 
+                vm_state.witness_tracer.add_fat_pointer_event(
+                    vm_state.local_state.monotonic_cycle_counter,
+                    &FatPointerEvent::Narrowed {
+                        location: pointer_event_location,
+                        opcode: inner_variant,
+                        input: FatPointer::from_u256(src0),
+                        output: FatPointer::from_u256(result.value),
+                    },
+                );
+
                 vm_state.perform_dst0_update(
                     vm_state.local_state.monotonic_cycle_counter,
                     result,
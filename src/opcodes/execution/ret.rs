@@ -1,5 +1,8 @@
 use super::*;
 
+use crate::witness_trace::{
+    CallstackTransition, FatPointerEvent, HeapGrowthEvent, HeapGrowthSource, PointerTransfer,
+};
 use zk_evm_abstractions::aux::Timestamp;
 use zkevm_opcode_defs::definitions::ret::*;
 use zkevm_opcode_defs::FatPointerValidationException;
@@ -51,6 +54,8 @@ impl<const N: usize, E: VmEncodingMode<N>> DecodedOpcode<N, E> {
         let is_to_label = self.variant.flags[RET_TO_LABEL_BIT_IDX];
         let label_pc = self.imm_0;
 
+        let pointer_event_location = vm_state.pointer_event_location();
+
         let current_callstack = vm_state.local_state.callstack.get_current_stack();
 
         let mut pointer_validation_exceptions = FatPointerValidationException::empty();
@@ -96,6 +101,7 @@ impl<const N: usize, E: VmEncodingMode<N>> DecodedOpcode<N, E> {
         }
 
         let mut ergs_remaining = current_callstack.ergs_remaining;
+        let mut heap_growth = None;
 
         // now we are all good to form a new fat pointer for
         let fat_ptr_for_returndata = if current_callstack.is_local_frame == true {
@@ -142,41 +148,56 @@ impl<const N: usize, E: VmEncodingMode<N>> DecodedOpcode<N, E> {
             }
 
             // potentially pay for memory growth
-            let memory_growth_in_bytes = match page_forwarding_mode {
-                a @ RetForwardPageType::UseHeap | a @ RetForwardPageType::UseAuxHeap => {
-                    // pointer is already validated, so we do not need to check that start + length do not overflow
-                    let mut upper_bound =
-                        memory_quasi_fat_pointer.start + memory_quasi_fat_pointer.length;
-
-                    let penalize_out_of_bounds_growth = pointer_validation_exceptions
-                        .contains(FatPointerValidationException::DEREF_BEYOND_HEAP_RANGE);
-                    if penalize_out_of_bounds_growth {
-                        upper_bound = u32::MAX;
-                    }
+            let (memory_growth_in_bytes, bound_before_growth, penalized_growth) =
+                match page_forwarding_mode {
+                    a @ RetForwardPageType::UseHeap | a @ RetForwardPageType::UseAuxHeap => {
+                        // pointer is already validated, so we do not need to check that start + length do not overflow
+                        let mut upper_bound =
+                            memory_quasi_fat_pointer.start + memory_quasi_fat_pointer.length;
+
+                        let penalize_out_of_bounds_growth = pointer_validation_exceptions
+                            .contains(FatPointerValidationException::DEREF_BEYOND_HEAP_RANGE);
+                        if penalize_out_of_bounds_growth {
+                            upper_bound = u32::MAX;
+                        }
 
-                    let current_bound = if a == RetForwardPageType::UseHeap {
-                        current_callstack.heap_bound
-                    } else if a == RetForwardPageType::UseAuxHeap {
-                        current_callstack.aux_heap_bound
-                    } else {
-                        unreachable!();
-                    };
-                    let (mut diff, uf) = upper_bound.overflowing_sub(current_bound);
-                    if uf {
-                        // heap bound is already beyond what we pass
-                        diff = 0u32;
-                    } else {
-                        // we do not need to do anything with the frame that goes out of scope
-                    };
-
-                    diff
-                }
-                RetForwardPageType::ForwardFatPointer => 0u32,
-            };
+                        let current_bound = if a == RetForwardPageType::UseHeap {
+                            current_callstack.heap_bound
+                        } else if a == RetForwardPageType::UseAuxHeap {
+                            current_callstack.aux_heap_bound
+                        } else {
+                            unreachable!();
+                        };
+                        let (mut diff, uf) = upper_bound.overflowing_sub(current_bound);
+                        if uf {
+                            // heap bound is already beyond what we pass
+                            diff = 0u32;
+                        } else {
+                            // we do not need to do anything with the frame that goes out of scope
+                        };
+
+                        (diff, current_bound, penalize_out_of_bounds_growth)
+                    }
+                    RetForwardPageType::ForwardFatPointer => (0u32, 0u32, false),
+                };
 
             // MEMORY_GROWTH_ERGS_PER_BYTE is always 1
             let cost_of_memory_growth =
                 memory_growth_in_bytes.wrapping_mul(zkevm_opcode_defs::MEMORY_GROWTH_ERGS_PER_BYTE);
+            if memory_growth_in_bytes > 0 || penalized_growth {
+                heap_growth = Some(HeapGrowthEvent {
+                    source: HeapGrowthSource::Ret,
+                    base_memory_page: current_callstack.base_memory_page.0,
+                    code_address: current_callstack.code_address,
+                    pc: current_callstack.pc.as_u64() as u32,
+                    is_aux_heap: page_forwarding_mode == RetForwardPageType::UseAuxHeap,
+                    old_bound: bound_before_growth,
+                    new_bound: bound_before_growth.wrapping_add(memory_growth_in_bytes),
+                    ergs_charged: cost_of_memory_growth,
+                    out_of_range_penalty: penalized_growth,
+                });
+            }
+
             if ergs_remaining >= cost_of_memory_growth {
                 ergs_remaining -= cost_of_memory_growth;
             } else {
@@ -192,6 +213,12 @@ impl<const N: usize, E: VmEncodingMode<N>> DecodedOpcode<N, E> {
         #[allow(dropping_references)]
         drop(current_callstack);
 
+        if let Some(event) = heap_growth {
+            vm_state
+                .witness_tracer
+                .add_heap_growth(vm_state.local_state.monotonic_cycle_counter, &event);
+        }
+
         // done with exceptions, so we can pop the callstack entry
         let panicked = inner_variant == RetOpcode::Revert || inner_variant == RetOpcode::Panic;
 
@@ -210,6 +237,35 @@ impl<const N: usize, E: VmEncodingMode<N>> DecodedOpcode<N, E> {
                 Timestamp(vm_state.local_state.timestamp),
             );
 
+            // pointer is masked on panic
+            if returndata_fat_pointer.memory_page != 0 {
+                let receiver_base_page = vm_state
+                    .local_state
+                    .callstack
+                    .get_current_stack()
+                    .base_memory_page
+                    .0;
+                let event = match page_forwarding_mode {
+                    RetForwardPageType::ForwardFatPointer => FatPointerEvent::Forwarded {
+                        location: pointer_event_location,
+                        transfer: PointerTransfer::Returndata,
+                        original: RetABI::from_u256(src0).memory_quasi_fat_pointer,
+                        forwarded: returndata_fat_pointer,
+                        receiver_base_page,
+                    },
+                    a => FatPointerEvent::Created {
+                        location: pointer_event_location,
+                        transfer: PointerTransfer::Returndata,
+                        is_aux_heap: a == RetForwardPageType::UseAuxHeap,
+                        pointer: returndata_fat_pointer,
+                        receiver_base_page,
+                    },
+                };
+                vm_state
+                    .witness_tracer
+                    .add_fat_pointer_event(vm_state.local_state.monotonic_cycle_counter, &event);
+            }
+
             vm_state.local_state.registers[RET_IMPLICIT_RETURNDATA_PARAMS_REGISTER as usize] =
                 PrimitiveValue {
                     value: returndata_fat_pointer.to_u256(),
@@ -262,5 +318,17 @@ impl<const N: usize, E: VmEncodingMode<N>> DecodedOpcode<N, E> {
         if inner_variant == RetOpcode::Panic {
             vm_state.local_state.flags.overflow_or_less_than_flag = true;
         }
+
+        let restored_callstack = *vm_state.local_state.callstack.get_current_stack();
+        vm_state.witness_tracer.add_callstack_transition(
+            vm_state.local_state.monotonic_cycle_counter,
+            &CallstackTransition::Pop {
+                popped: finished_callstack,
+                restored: restored_callstack,
+                panicked,
+                ergs_returned: ergs_remaining,
+                jumped_to_exception_handler: panicked && is_to_label == false,
+            },
+        );
     }
 }
@@ -2,6 +2,7 @@ use zk_evm_abstractions::aux::*;
 use zk_evm_abstractions::vm::MemoryType;
 
 use super::*;
+use crate::witness_trace::{HeapGrowthEvent, HeapGrowthSource};
 use zkevm_opcode_defs::{FatPointer, Opcode, UMAOpcode, UMA_INCREMENT_FLAG_IDX};
 
 const U64_TOP_32_BITS_MASK: u64 = 0xffff_ffff_0000_0000;
@@ -50,6 +51,7 @@ impl<const N: usize, E: VmEncodingMode<N>> DecodedOpcode<N, E> {
             Opcode::UMA(inner) => inner,
             _ => unreachable!(),
         };
+        let pc = vm_state.local_state.callstack.get_current_stack().pc;
         vm_state.local_state.callstack.get_current_stack_mut().pc = new_pc;
 
         let increment_offset = self.variant.flags[UMA_INCREMENT_FLAG_IDX];
@@ -149,7 +151,7 @@ impl<const N: usize, E: VmEncodingMode<N>> DecodedOpcode<N, E> {
         let current_callstack_mut = vm_state.local_state.callstack.get_current_stack_mut();
 
         // potentially pay for memory growth
-        let memory_growth_in_bytes = match inner_variant {
+        let (memory_growth_in_bytes, bound_before_growth) = match inner_variant {
             UMAOpcode::HeapRead
             | UMAOpcode::HeapWrite
             | UMAOpcode::AuxHeapRead
@@ -185,11 +187,11 @@ impl<const N: usize, E: VmEncodingMode<N>> DecodedOpcode<N, E> {
                     };
                 };
 
-                diff
+                (diff, current_bound)
             }
             UMAOpcode::FatPointerRead => {
                 // cost was paid somewhere, and we if try to go out of bound we will just not read
-                0u32
+                (0u32, 0u32)
             }
         };
 
@@ -216,9 +218,31 @@ impl<const N: usize, E: VmEncodingMode<N>> DecodedOpcode<N, E> {
         }
         current_callstack_mut.ergs_remaining = ergs_after_memory_growth;
 
+        let heap_growth = if memory_growth_in_bytes > 0 || penalize_for_out_of_bounds {
+            Some(HeapGrowthEvent {
+                source: HeapGrowthSource::Uma,
+                base_memory_page: current_callstack_mut.base_memory_page.0,
+                code_address: current_callstack_mut.code_address,
+                pc: pc.as_u64() as u32,
+                is_aux_heap: memory_type == MemoryType::AuxHeap,
+                old_bound: bound_before_growth,
+                new_bound: bound_before_growth.wrapping_add(memory_growth_in_bytes),
+                ergs_charged: cost_of_memory_growth,
+                out_of_range_penalty: penalize_for_out_of_bounds,
+            })
+        } else {
+            None
+        };
+
         #[allow(dropping_references)]
         drop(current_callstack_mut);
 
+        if let Some(event) = heap_growth {
+            vm_state
+                .witness_tracer
+                .add_heap_growth(vm_state.local_state.monotonic_cycle_counter, &event);
+        }
+
         // we will set panic if any exception was triggered
         let set_panic = exceptions.is_empty() == false;
         let legitimate_skip_memory_access = skip_memory_access_flags.is_empty() == false;
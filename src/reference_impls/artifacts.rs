@@ -0,0 +1,37 @@
+use std::path::Path;
+
+// JSON artifacts keep hex encoded bytecode in `bytecode` field: plain string as in hardhat
+// artifacts, or an object with `object` field as in foundry ones
+
+/// Decodes hex string with optional `0x` prefix
+pub fn decode_hex(input: &str) -> anyhow::Result<Vec<u8>> {
+    let input = input.trim();
+    let input = input.strip_prefix("0x").unwrap_or(input);
+
+    Ok(hex::decode(input)?)
+}
+
+fn bytecode_from_json_artifact(content: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let artifact: serde_json::Value = serde_json::from_slice(content)?;
+    let bytecode = match artifact.get("bytecode") {
+        Some(serde_json::Value::String(bytecode)) => bytecode,
+        Some(serde_json::Value::Object(inner)) => inner
+            .get("object")
+            .and_then(|el| el.as_str())
+            .ok_or_else(|| anyhow::anyhow!("artifact's `bytecode.object` field is not a string"))?,
+        _ => anyhow::bail!("artifact has no `bytecode` field"),
+    };
+
+    decode_hex(bytecode)
+}
+
+/// Reads bytecode from raw (`.bin`), hex encoded (`.hex`) or JSON (`.json`) artifact
+pub fn read_artifact(path: &Path) -> anyhow::Result<Vec<u8>> {
+    let content = std::fs::read(path)
+        .map_err(|e| anyhow::anyhow!("failed to read artifact {:?}: {}", path, e))?;
+    match path.extension().and_then(|el| el.to_str()) {
+        Some("json") => bytecode_from_json_artifact(&content),
+        Some("hex") => decode_hex(std::str::from_utf8(&content)?),
+        _ => Ok(content),
+    }
+}
@@ -0,0 +1,193 @@
+use zk_evm_abstractions::aux::*;
+use zk_evm_abstractions::queries::*;
+use zk_evm_abstractions::vm::*;
+
+use super::*;
+
+use crate::errors::DecommittmentError;
+use crate::reference_impls::artifacts::{decode_hex, read_artifact};
+use crate::reference_impls::decommitter::{hash_bytecode, DecommittmentHistory};
+use std::path::{Path, PathBuf};
+
+// Artifacts can be stored in a directory, where every file is either raw bytecode (`.bin`)
+// or a JSON artifact with a hex encoded `bytecode` field (plain string as in hardhat artifacts,
+// or an object with `object` field as in foundry ones). If file name is a code hash
+// (e.g. `0x0100...ab.bin`) we can find it without reading other files, otherwise we index
+// the full directory on the first miss.
+// Bundle is a single JSON file that maps code hashes to hex encoded bytecode.
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ArtifactsSource {
+    Directory(PathBuf),
+    Bundle(PathBuf),
+}
+
+#[derive(Debug)]
+pub struct ArtifactsDecommitter<const B: bool> {
+    source: ArtifactsSource,
+    // hash -> where we can get it from, filled lazily
+    index: HashMap<U256, PathBuf>,
+    index_is_complete: bool,
+    bundle: Option<HashMap<U256, String>>,
+    cache: HashMap<U256, Vec<U256>>,
+    history: DecommittmentHistory,
+}
+
+fn parse_hash(input: &str) -> Option<U256> {
+    let input = input.strip_prefix("0x").unwrap_or(input);
+    if input.len() != 64 {
+        return None;
+    }
+
+    U256::from_str_radix(input, 16).ok()
+}
+
+impl<const B: bool> ArtifactsDecommitter<B> {
+    pub fn new(source: ArtifactsSource) -> Self {
+        Self {
+            source,
+            index: HashMap::default(),
+            index_is_complete: false,
+            bundle: None,
+            cache: HashMap::default(),
+            history: DecommittmentHistory::default(),
+        }
+    }
+
+    pub fn from_directory(path: impl Into<PathBuf>) -> Self {
+        Self::new(ArtifactsSource::Directory(path.into()))
+    }
+
+    pub fn from_bundle(path: impl Into<PathBuf>) -> Self {
+        Self::new(ArtifactsSource::Bundle(path.into()))
+    }
+
+    pub fn source(&self) -> &ArtifactsSource {
+        &self.source
+    }
+
+    pub fn cached_hashes(&self) -> impl Iterator<Item = &U256> {
+        self.cache.keys()
+    }
+
+    fn load_bundle(&mut self, path: &Path) -> anyhow::Result<()> {
+        let content = std::fs::read(path)
+            .map_err(|e| anyhow::anyhow!("failed to read bundle {:?}: {}", path, e))?;
+        let raw: HashMap<String, String> = serde_json::from_slice(&content)?;
+        let mut bundle = HashMap::with_capacity(raw.len());
+        for (hash, bytecode) in raw.into_iter() {
+            let hash = parse_hash(&hash)
+                .ok_or_else(|| anyhow::anyhow!("invalid code hash {} in bundle", hash))?;
+            bundle.insert(hash, bytecode);
+        }
+        self.bundle = Some(bundle);
+
+        Ok(())
+    }
+
+    fn index_directory(&mut self, path: &Path) -> anyhow::Result<()> {
+        for entry in std::fs::read_dir(path)? {
+            let entry_path = entry?.path();
+            if entry_path.is_file() == false {
+                continue;
+            }
+            // we are not interested in files that are not artifacts
+            let Ok(bytecode) = read_artifact(&entry_path) else {
+                continue;
+            };
+            let Ok(hash) = hash_bytecode(&bytecode) else {
+                continue;
+            };
+            self.index.insert(hash, entry_path);
+        }
+        self.index_is_complete = true;
+
+        Ok(())
+    }
+
+    fn find_in_directory(&mut self, dir: &Path, hash: &U256) -> anyhow::Result<Option<Vec<u8>>> {
+        if let Some(path) = self.index.get(hash) {
+            return read_artifact(path).map(Some);
+        }
+
+        let mut buffer = [0u8; 32];
+        hash.to_big_endian(&mut buffer);
+        let hash_hex = hex::encode(buffer);
+        for name in [&hash_hex, &format!("0x{}", hash_hex)] {
+            for extension in ["bin", "hex", "json"] {
+                let candidate = dir.join(format!("{}.{}", name, extension));
+                if candidate.is_file() {
+                    return read_artifact(&candidate).map(Some);
+                }
+            }
+        }
+
+        if self.index_is_complete == false {
+            self.index_directory(dir)?;
+            if let Some(path) = self.index.get(hash) {
+                return read_artifact(path).map(Some);
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn load_bytecode(&mut self, hash: &U256) -> anyhow::Result<Option<Vec<u8>>> {
+        match self.source.clone() {
+            ArtifactsSource::Directory(dir) => self.find_in_directory(&dir, hash),
+            ArtifactsSource::Bundle(path) => {
+                if self.bundle.is_none() {
+                    self.load_bundle(&path)?;
+                }
+                match self.bundle.as_ref().unwrap().get(hash) {
+                    Some(bytecode) => decode_hex(bytecode).map(Some),
+                    None => Ok(None),
+                }
+            }
+        }
+    }
+
+    /// Returns bytecode words for the hash, loading and validating it if it's not cached yet
+    pub fn get_or_load(&mut self, hash: &U256) -> anyhow::Result<&Vec<U256>> {
+        if self.cache.contains_key(hash) == false {
+            let bytecode = self
+                .load_bytecode(hash)?
+                .ok_or(DecommittmentError::UnknownCodeHash(*hash))?;
+            let actual_hash = hash_bytecode(&bytecode)?;
+            if actual_hash != *hash {
+                anyhow::bail!(
+                    "artifact for code hash {:#066x} actually has hash {:#066x}",
+                    hash,
+                    actual_hash
+                );
+            }
+            let words = bytecode
+                .chunks(32)
+                .map(|el| U256::from_big_endian(el))
+                .collect();
+            self.cache.insert(*hash, words);
+        }
+
+        Ok(self.cache.get(hash).unwrap())
+    }
+}
+
+impl<const B: bool> DecommittmentProcessor for ArtifactsDecommitter<B> {
+    fn decommit_into_memory<M: Memory>(
+        &mut self,
+        monotonic_cycle_counter: u32,
+        partial_query: DecommittmentQuery,
+        memory: &mut M,
+    ) -> anyhow::Result<(DecommittmentQuery, Option<Vec<U256>>)> {
+        if let Some(query) = self.history.repeated(partial_query) {
+            return Ok((query, B.then(Vec::new))); // empty extra data
+        }
+
+        let values = self.get_or_load(&partial_query.hash)?.clone();
+        let query =
+            self.history
+                .decommit_fresh(monotonic_cycle_counter, partial_query, &values, memory);
+
+        Ok((query, B.then_some(values)))
+    }
+}
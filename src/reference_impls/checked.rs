@@ -0,0 +1,604 @@
+use super::*;
+
+use crate::errors::OracleViolation;
+use crate::reference_impls::decommitter::code_length_in_words_from_hash;
+use crate::reference_impls::event_sink::InMemoryEventSink;
+use crate::vm_state::{CallStackEntry, PrimitiveValue};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use zk_evm_abstractions::aux::{MemoryPage, Timestamp};
+use zk_evm_abstractions::queries::{DecommittmentQuery, LogQuery, MemoryQuery};
+use zk_evm_abstractions::vm::{
+    DecommittmentProcessor, EventSink, Memory, MemoryType, RefundType, Storage,
+};
+use zkevm_opcode_defs::decoding::EncodingModeProduction;
+use zkevm_opcode_defs::FatPointer;
+
+// Opt-in wrappers that validate oracle responses against the semantics VM relies on,
+// so a buggy custom oracle is caught at the query where it misbehaves instead of
+// many cycles later. Wrappers keep a shadow model that is built only from what VM has
+// seen, so oracles can be pre-populated in any way before wrapping.
+
+// Either panics right away, so the backtrace points to the misbehaving query,
+// or collects violations for later inspection
+#[derive(Debug)]
+struct ViolationsLog {
+    panic_on_violation: bool,
+    // interior mutability as some oracle methods take `&self`
+    violations: RefCell<Vec<OracleViolation>>,
+}
+
+impl ViolationsLog {
+    fn new(panic_on_violation: bool) -> Self {
+        Self {
+            panic_on_violation,
+            violations: RefCell::new(vec![]),
+        }
+    }
+
+    fn report(&self, violation: OracleViolation) {
+        if self.panic_on_violation {
+            panic!("oracle violation: {}", violation);
+        }
+        self.violations.borrow_mut().push(violation);
+    }
+
+    fn to_vec(&self) -> Vec<OracleViolation> {
+        self.violations.borrow().clone()
+    }
+}
+
+/// Checks that reads return the last write made at an earlier timestamp, that stack is only
+/// accessed in the current frame and that global frames are finished in order. Memory that is
+/// wrapped after global frames were started (e.g. after `push_bootloader_context`) must get
+/// them through `with_running_frames`
+#[derive(Debug)]
+pub struct CheckedMemory<M: Memory> {
+    inner: M,
+    // (page, index) -> timestamp and value of the last write
+    last_writes: HashMap<(u32, u32), (u32, PrimitiveValue)>,
+    code_writes: HashMap<(u32, u32), U256>,
+    stack_pages: Vec<u32>,
+    log: ViolationsLog,
+}
+
+impl<M: Memory> CheckedMemory<M> {
+    /// Panics on the first violation
+    pub fn new(inner: M) -> Self {
+        Self::with_log(inner, ViolationsLog::new(true))
+    }
+
+    /// Collects violations, see `violations`
+    pub fn recording(inner: M) -> Self {
+        Self::with_log(inner, ViolationsLog::new(false))
+    }
+
+    fn with_log(inner: M, log: ViolationsLog) -> Self {
+        Self {
+            inner,
+            last_writes: HashMap::new(),
+            code_writes: HashMap::new(),
+            stack_pages: vec![],
+            log,
+        }
+    }
+
+    /// Global frames that were started before wrapping, outermost first
+    pub fn with_running_frames(mut self, base_pages: impl IntoIterator<Item = MemoryPage>) -> Self {
+        assert!(self.stack_pages.is_empty(), "frames are already known");
+        self.stack_pages = base_pages
+            .into_iter()
+            .map(|el| CallStackEntry::<8, EncodingModeProduction>::stack_page_from_base(el).0)
+            .collect();
+
+        self
+    }
+
+    pub fn violations(&self) -> Vec<OracleViolation> {
+        self.log.to_vec()
+    }
+
+    pub fn inner(&self) -> &M {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> M {
+        self.inner
+    }
+}
+
+impl<M: Memory> Memory for CheckedMemory<M> {
+    fn execute_partial_query(
+        &mut self,
+        monotonic_cycle_counter: u32,
+        query: MemoryQuery,
+    ) -> MemoryQuery {
+        let page = query.location.page.0;
+        let index = query.location.index.0;
+        if query.location.memory_type == MemoryType::Stack {
+            let current_stack_page = self.stack_pages.last().copied();
+            if current_stack_page != Some(page) {
+                self.log
+                    .report(OracleViolation::StackAccessOutsideOfCurrentFrame {
+                        page,
+                        current_stack_page,
+                        rw_flag: query.rw_flag,
+                    });
+            }
+        }
+
+        let response = self
+            .inner
+            .execute_partial_query(monotonic_cycle_counter, query);
+
+        let write_is_modified = query.rw_flag
+            && (response.value != query.value
+                || response.value_is_pointer != query.value_is_pointer);
+        if response.location.memory_type != query.location.memory_type
+            || response.location.page.0 != page
+            || response.location.index.0 != index
+            || response.timestamp.0 != query.timestamp.0
+            || response.rw_flag != query.rw_flag
+            || write_is_modified
+        {
+            self.log.report(OracleViolation::MemoryQueryIsModified {
+                page,
+                index,
+                timestamp: query.timestamp.0,
+            });
+        }
+
+        if query.rw_flag {
+            let written = PrimitiveValue {
+                value: query.value,
+                is_pointer: query.value_is_pointer,
+            };
+            self.last_writes
+                .insert((page, index), (query.timestamp.0, written));
+        } else if let Some((written_at, expected)) = self.last_writes.get(&(page, index)).copied() {
+            let actual = PrimitiveValue {
+                value: response.value,
+                is_pointer: response.value_is_pointer,
+            };
+            if written_at < query.timestamp.0 && actual != expected {
+                self.log.report(OracleViolation::MemoryReadMismatch {
+                    page,
+                    index,
+                    timestamp: query.timestamp.0,
+                    written_at,
+                    expected,
+                    actual,
+                });
+            }
+        }
+
+        response
+    }
+
+    fn specialized_code_query(
+        &mut self,
+        monotonic_cycle_counter: u32,
+        query: MemoryQuery,
+    ) -> MemoryQuery {
+        let response = self
+            .inner
+            .specialized_code_query(monotonic_cycle_counter, query);
+        let location = (query.location.page.0, query.location.index.0);
+        if query.rw_flag {
+            self.code_writes.insert(location, query.value);
+        } else {
+            self.check_code_read(location, response.value);
+        }
+
+        response
+    }
+
+    fn read_code_query(&self, monotonic_cycle_counter: u32, query: MemoryQuery) -> MemoryQuery {
+        let response = self.inner.read_code_query(monotonic_cycle_counter, query);
+        self.check_code_read(
+            (query.location.page.0, query.location.index.0),
+            response.value,
+        );
+
+        response
+    }
+
+    fn start_global_frame(
+        &mut self,
+        current_base_page: MemoryPage,
+        new_base_page: MemoryPage,
+        calldata_fat_pointer: FatPointer,
+        timestamp: Timestamp,
+    ) {
+        let stack_page =
+            CallStackEntry::<8, EncodingModeProduction>::stack_page_from_base(new_base_page);
+        self.stack_pages.push(stack_page.0);
+
+        self.inner.start_global_frame(
+            current_base_page,
+            new_base_page,
+            calldata_fat_pointer,
+            timestamp,
+        );
+    }
+
+    fn finish_global_frame(
+        &mut self,
+        base_page: MemoryPage,
+        returndata_fat_pointer: FatPointer,
+        timestamp: Timestamp,
+    ) {
+        let stack_page =
+            CallStackEntry::<8, EncodingModeProduction>::stack_page_from_base(base_page);
+        let expected_stack_page = self.stack_pages.pop();
+        if expected_stack_page != Some(stack_page.0) {
+            self.log.report(OracleViolation::GlobalFrameMismatch {
+                expected_stack_page,
+                actual_stack_page: stack_page.0,
+            });
+        }
+
+        self.inner
+            .finish_global_frame(base_page, returndata_fat_pointer, timestamp);
+    }
+}
+
+impl<M: Memory> CheckedMemory<M> {
+    // code pages that were populated before wrapping are unknown and not checked
+    fn check_code_read(&self, location: (u32, u32), actual: U256) {
+        if let Some(expected) = self.code_writes.get(&location).copied() {
+            if actual != expected {
+                self.log.report(OracleViolation::CodeReadMismatch {
+                    page: location.0,
+                    index: location.1,
+                    expected,
+                    actual,
+                });
+            }
+        }
+    }
+}
+
+type StorageSlot = (u8, Address, U256);
+
+/// Checks that reads see the latest write, including writes of the current frame, and that
+/// rollback of a panicked frame restores exactly the values that were there before its writes.
+/// Rollbacks are validated on the next access to the rolled back slot
+#[derive(Debug)]
+pub struct CheckedStorage<S: Storage> {
+    inner: S,
+    // value as VM should see it, and whether it was restored by rollback
+    values: HashMap<StorageSlot, (U256, bool)>,
+    // per frame: slot and its value before the write, in order of writes
+    frames: Vec<Vec<(StorageSlot, U256)>>,
+    log: ViolationsLog,
+}
+
+impl<S: Storage> CheckedStorage<S> {
+    /// Panics on the first violation
+    pub fn new(inner: S) -> Self {
+        Self::with_log(inner, ViolationsLog::new(true))
+    }
+
+    /// Collects violations, see `violations`
+    pub fn recording(inner: S) -> Self {
+        Self::with_log(inner, ViolationsLog::new(false))
+    }
+
+    fn with_log(inner: S, log: ViolationsLog) -> Self {
+        Self {
+            inner,
+            values: HashMap::new(),
+            // same as oracles do, we keep a frame that is never finished
+            frames: vec![vec![]],
+            log,
+        }
+    }
+
+    pub fn violations(&self) -> Vec<OracleViolation> {
+        self.log.to_vec()
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Storage> Storage for CheckedStorage<S> {
+    fn estimate_refunds_for_write(
+        &mut self,
+        monotonic_cycle_counter: u32,
+        partial_query: &LogQuery,
+    ) -> RefundType {
+        self.inner
+            .estimate_refunds_for_write(monotonic_cycle_counter, partial_query)
+    }
+
+    fn execute_partial_query(&mut self, monotonic_cycle_counter: u32, query: LogQuery) -> LogQuery {
+        let response = self
+            .inner
+            .execute_partial_query(monotonic_cycle_counter, query);
+        let slot = (query.shard_id, query.address, query.key);
+
+        if response.shard_id != query.shard_id
+            || response.address != query.address
+            || response.key != query.key
+            || response.timestamp.0 != query.timestamp.0
+            || response.rw_flag != query.rw_flag
+            || (query.rw_flag && response.written_value != query.written_value)
+        {
+            self.log.report(OracleViolation::StorageQueryIsModified {
+                shard_id: query.shard_id,
+                address: query.address,
+                key: query.key,
+                timestamp: query.timestamp.0,
+            });
+        }
+
+        if let Some((expected, restored_by_rollback)) = self.values.get(&slot).copied() {
+            if response.read_value != expected {
+                let violation = if restored_by_rollback {
+                    OracleViolation::StorageRollbackMismatch {
+                        shard_id: query.shard_id,
+                        address: query.address,
+                        key: query.key,
+                        expected,
+                        actual: response.read_value,
+                    }
+                } else {
+                    OracleViolation::StorageReadMismatch {
+                        shard_id: query.shard_id,
+                        address: query.address,
+                        key: query.key,
+                        timestamp: query.timestamp.0,
+                        expected,
+                        actual: response.read_value,
+                    }
+                };
+                self.log.report(violation);
+            }
+        }
+
+        // we follow the oracle after reporting, so one bug is reported once
+        if query.rw_flag {
+            match self.frames.last_mut() {
+                Some(frame) => frame.push((slot, response.read_value)),
+                None => self
+                    .log
+                    .report(OracleViolation::UnbalancedFrame { oracle: "storage" }),
+            }
+            self.values.insert(slot, (query.written_value, false));
+        } else {
+            self.values.insert(slot, (response.read_value, false));
+        }
+
+        response
+    }
+
+    fn start_frame(&mut self, timestamp: Timestamp) {
+        self.frames.push(vec![]);
+        self.inner.start_frame(timestamp);
+    }
+
+    fn finish_frame(&mut self, timestamp: Timestamp, panicked: bool) {
+        self.inner.finish_frame(timestamp, panicked);
+
+        if self.frames.len() < 2 {
+            self.log
+                .report(OracleViolation::UnbalancedFrame { oracle: "storage" });
+            return;
+        }
+        let frame = self.frames.pop().unwrap();
+        if panicked {
+            for (slot, previous_value) in frame.into_iter().rev() {
+                self.values.insert(slot, (previous_value, true));
+            }
+        } else {
+            // parent's rollback will also revert writes of the child
+            self.frames.last_mut().unwrap().extend(frame);
+        }
+    }
+}
+
+/// Event sinks that can tell which messages are alive, i.e. would be committed if all the
+/// running frames finish successfully
+pub trait InspectableEventSink: EventSink {
+    /// Timestamps of alive events and L1 messages, in any order
+    fn alive_message_timestamps(&self) -> Vec<u32>;
+}
+
+impl InspectableEventSink for InMemoryEventSink {
+    fn alive_message_timestamps(&self) -> Vec<u32> {
+        // rollbacks of panicked frames follow their messages in the forward part
+        let mut alive = HashSet::new();
+        for query in self.frames_stack.iter().flat_map(|el| el.forward.iter()) {
+            if query.rollback {
+                alive.remove(&query.timestamp.0);
+            } else {
+                alive.insert(query.timestamp.0);
+            }
+        }
+
+        alive.into_iter().collect()
+    }
+}
+
+/// Checks that finishing a panicked frame rolls back exactly the events and L1 messages
+/// emitted in it (including the ones of its finished children), and that a successful
+/// frame keeps them. The sink is compared with the model after every finished frame
+#[derive(Debug)]
+pub struct CheckedEventSink<EV: InspectableEventSink> {
+    inner: EV,
+    // per frame: timestamps of messages that are alive in it
+    frames: Vec<Vec<u32>>,
+    log: ViolationsLog,
+}
+
+impl<EV: InspectableEventSink> CheckedEventSink<EV> {
+    /// Panics on the first violation
+    pub fn new(inner: EV) -> Self {
+        Self::with_log(inner, ViolationsLog::new(true))
+    }
+
+    /// Collects violations, see `violations`
+    pub fn recording(inner: EV) -> Self {
+        Self::with_log(inner, ViolationsLog::new(false))
+    }
+
+    fn with_log(inner: EV, log: ViolationsLog) -> Self {
+        // messages emitted before wrapping are never rolled back by frames we know about
+        let alive = inner.alive_message_timestamps();
+        Self {
+            inner,
+            // same as oracles do, we keep a frame that is never finished
+            frames: vec![alive],
+            log,
+        }
+    }
+
+    pub fn violations(&self) -> Vec<OracleViolation> {
+        self.log.to_vec()
+    }
+
+    pub fn inner(&self) -> &EV {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> EV {
+        self.inner
+    }
+
+    fn check_alive_messages(&self, panicked: bool) {
+        let mut expected: Vec<u32> = self.frames.iter().flatten().copied().collect();
+        expected.sort();
+        let mut actual = self.inner.alive_message_timestamps();
+        actual.sort();
+
+        let missing = expected.iter().find(|el| actual.binary_search(el).is_err());
+        let extra = actual.iter().find(|el| expected.binary_search(el).is_err());
+        let mismatch = match (missing, extra) {
+            (Some(a), Some(b)) if b < a => Some((*b, false)),
+            (Some(a), _) => Some((*a, true)),
+            (None, Some(b)) => Some((*b, false)),
+            (None, None) => None,
+        };
+        if let Some((timestamp, is_missing)) = mismatch {
+            self.log.report(OracleViolation::EventRollbackMismatch {
+                timestamp,
+                is_missing,
+                panicked,
+            });
+        }
+    }
+}
+
+impl<EV: InspectableEventSink> EventSink for CheckedEventSink<EV> {
+    fn add_partial_query(&mut self, monotonic_cycle_counter: u32, query: LogQuery) {
+        self.frames
+            .last_mut()
+            .expect("frame is never finished")
+            .push(query.timestamp.0);
+        self.inner.add_partial_query(monotonic_cycle_counter, query);
+    }
+
+    fn start_frame(&mut self, timestamp: Timestamp) {
+        self.frames.push(vec![]);
+        self.inner.start_frame(timestamp);
+    }
+
+    fn finish_frame(&mut self, panicked: bool, timestamp: Timestamp) {
+        self.inner.finish_frame(panicked, timestamp);
+
+        if self.frames.len() < 2 {
+            self.log.report(OracleViolation::UnbalancedFrame {
+                oracle: "event sink",
+            });
+            return;
+        }
+        let frame = self.frames.pop().unwrap();
+        if panicked == false {
+            self.frames.last_mut().unwrap().extend(frame);
+        }
+        self.check_alive_messages(panicked);
+    }
+}
+
+/// Checks that decommitted length matches the length declared in the versioned hash, and that
+/// repeated decommittment points to the page where the code was decommitted before
+#[derive(Debug)]
+pub struct CheckedDecommitter<D: DecommittmentProcessor> {
+    inner: D,
+    pages: HashMap<U256, u32>,
+    log: ViolationsLog,
+}
+
+impl<D: DecommittmentProcessor> CheckedDecommitter<D> {
+    /// Panics on the first violation
+    pub fn new(inner: D) -> Self {
+        Self::with_log(inner, ViolationsLog::new(true))
+    }
+
+    /// Collects violations, see `violations`
+    pub fn recording(inner: D) -> Self {
+        Self::with_log(inner, ViolationsLog::new(false))
+    }
+
+    fn with_log(inner: D, log: ViolationsLog) -> Self {
+        Self {
+            inner,
+            pages: HashMap::new(),
+            log,
+        }
+    }
+
+    pub fn violations(&self) -> Vec<OracleViolation> {
+        self.log.to_vec()
+    }
+
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+}
+
+impl<D: DecommittmentProcessor> DecommittmentProcessor for CheckedDecommitter<D> {
+    fn decommit_into_memory<M: Memory>(
+        &mut self,
+        monotonic_cycle_counter: u32,
+        partial_query: DecommittmentQuery,
+        memory: &mut M,
+    ) -> anyhow::Result<(DecommittmentQuery, Option<Vec<U256>>)> {
+        let (query, witness) =
+            self.inner
+                .decommit_into_memory(monotonic_cycle_counter, partial_query, memory)?;
+
+        let declared_length_in_words = code_length_in_words_from_hash(&query.hash);
+        if query.decommitted_length != declared_length_in_words {
+            self.log.report(OracleViolation::DecommittedLengthMismatch {
+                hash: query.hash,
+                declared_length_in_words,
+                decommitted_length_in_words: query.decommitted_length,
+            });
+        }
+
+        let previous_page = self.pages.get(&query.hash).copied();
+        if query.is_fresh {
+            self.pages.insert(query.hash, query.memory_page.0);
+        } else if previous_page != Some(query.memory_page.0) {
+            self.log.report(OracleViolation::DecommittedPageMismatch {
+                hash: query.hash,
+                previous_page,
+                page: query.memory_page.0,
+            });
+        }
+
+        Ok((query, witness))
+    }
+}
@@ -4,19 +4,82 @@ use zk_evm_abstractions::vm::*;
 
 use super::*;
 
+use crate::errors::{BytecodeHashingError, DecommittmentError};
+use zkevm_opcode_defs::sha2::{Digest, Sha256};
+use zkevm_opcode_defs::{ContractCodeSha256, VersionedHashDef, VersionedHashGeneric};
+
 pub const MEMORY_CELLS_PER_PAGE: usize = (1 << 16) - 1;
 
+/// Codes that were already decommitted: code hash -> (page, length in words)
+#[derive(Clone, Debug, Default)]
+pub struct DecommittmentHistory(HashMap<U256, (u32, u16)>);
+
+impl DecommittmentHistory {
+    /// Points the query to the page where the same code was decommitted before, if any
+    pub fn repeated(&self, mut partial_query: DecommittmentQuery) -> Option<DecommittmentQuery> {
+        let (old_page, old_len) = self.0.get(&partial_query.hash).copied()?;
+        partial_query.is_fresh = false;
+        partial_query.memory_page = MemoryPage(old_page);
+        partial_query.decommitted_length = old_len;
+
+        Some(partial_query)
+    }
+
+    /// Writes the code into the query's page and remembers where it is
+    pub fn decommit_fresh<M: Memory>(
+        &mut self,
+        monotonic_cycle_counter: u32,
+        mut partial_query: DecommittmentQuery,
+        code: &[U256],
+        memory: &mut M,
+    ) -> DecommittmentQuery {
+        partial_query.decommitted_length = code.len() as u16;
+        partial_query.is_fresh = true;
+
+        let mut tmp_q = MemoryQuery {
+            timestamp: partial_query.timestamp,
+            location: MemoryLocation {
+                memory_type: MemoryType::Code,
+                page: partial_query.memory_page,
+                index: MemoryIndex(0),
+            },
+            value: U256::zero(),
+            value_is_pointer: false,
+            rw_flag: true,
+        };
+        for (i, value) in code.iter().enumerate() {
+            tmp_q.location.index = MemoryIndex(i as u32);
+            tmp_q.value = *value;
+            memory.specialized_code_query(monotonic_cycle_counter, tmp_q);
+        }
+
+        self.0.insert(
+            partial_query.hash,
+            (
+                partial_query.memory_page.0,
+                partial_query.decommitted_length,
+            ),
+        );
+
+        partial_query
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
 #[derive(Debug)]
 pub struct SimpleDecommitter<const B: bool> {
     known_hashes: HashMap<U256, Vec<U256>>,
-    history: HashMap<U256, (u32, u16)>,
+    history: DecommittmentHistory,
 }
 
 impl<const B: bool> SimpleDecommitter<B> {
     pub fn new() -> Self {
         Self {
             known_hashes: HashMap::default(),
-            history: HashMap::default(),
+            history: DecommittmentHistory::default(),
         }
     }
 
@@ -32,69 +95,159 @@ impl<const B: bool> DecommittmentProcessor for SimpleDecommitter<B> {
     fn decommit_into_memory<M: Memory>(
         &mut self,
         monotonic_cycle_counter: u32,
-        mut partial_query: DecommittmentQuery,
+        partial_query: DecommittmentQuery,
         memory: &mut M,
     ) -> anyhow::Result<(DecommittmentQuery, Option<Vec<U256>>)> {
-        if let Some((old_page, old_len)) = self.history.get(&partial_query.hash).copied() {
-            partial_query.is_fresh = false;
-            partial_query.memory_page = MemoryPage(old_page);
-            partial_query.decommitted_length = old_len;
-
-            if B {
-                Ok((partial_query, Some(vec![]))) // empty extra data
-            } else {
-                Ok((partial_query, None))
-            }
-        } else {
-            // fresh one
-            let values = self
-                .known_hashes
-                .get(&partial_query.hash)
-                .cloned()
-                .ok_or_else(|| {
-                    anyhow::anyhow!("Code hash {:?} must be known", &partial_query.hash)
-                })?;
-            let page_to_use = partial_query.memory_page;
-            let timestamp = partial_query.timestamp;
-            partial_query.decommitted_length = values.len() as u16;
-            partial_query.is_fresh = true;
-            // write into memory
-            let mut tmp_q = MemoryQuery {
-                timestamp,
-                location: MemoryLocation {
-                    memory_type: MemoryType::Code,
-                    page: page_to_use,
-                    index: MemoryIndex(0),
-                },
-                value: U256::zero(),
-                value_is_pointer: false,
-                rw_flag: true,
-            };
-
-            self.history.insert(
-                partial_query.hash,
-                (
-                    partial_query.memory_page.0,
-                    partial_query.decommitted_length,
-                ),
-            );
-            if B {
-                for (i, value) in values.iter().enumerate() {
-                    tmp_q.location.index = MemoryIndex(i as u32);
-                    tmp_q.value = *value;
-                    memory.specialized_code_query(monotonic_cycle_counter, tmp_q);
-                }
-
-                Ok((partial_query, Some(values)))
-            } else {
-                for (i, value) in values.into_iter().enumerate() {
-                    tmp_q.location.index = MemoryIndex(i as u32);
-                    tmp_q.value = value;
-                    memory.specialized_code_query(monotonic_cycle_counter, tmp_q);
-                }
-
-                Ok((partial_query, None))
+        if let Some(query) = self.history.repeated(partial_query) {
+            return Ok((query, B.then(Vec::new))); // empty extra data
+        }
+
+        let values = self
+            .known_hashes
+            .get(&partial_query.hash)
+            .ok_or_else(|| anyhow::anyhow!("Code hash {:?} must be known", &partial_query.hash))?;
+        let query =
+            self.history
+                .decommit_fresh(monotonic_cycle_counter, partial_query, values, memory);
+
+        Ok((query, B.then(|| values.clone())))
+    }
+}
+
+/// Computes versioned bytecode hash in the format that is stored in the deployer's storage and
+/// validated by far call: version byte, "at rest" marker, length in words (BE u16) and
+/// the last 28 bytes of sha256 of the bytecode
+pub fn hash_bytecode(bytecode: &[u8]) -> Result<U256, BytecodeHashingError> {
+    if bytecode.len() % 32 != 0 {
+        return Err(BytecodeHashingError::LengthIsNotDivisibleBy32);
+    }
+    let length_in_words = bytecode.len() / 32;
+    if length_in_words > u16::MAX as usize {
+        return Err(BytecodeHashingError::BytecodeIsTooLong);
+    }
+    if length_in_words % 2 == 0 {
+        return Err(BytecodeHashingError::EvenNumberOfWords);
+    }
+
+    let mut buffer = [0u8; 32];
+    buffer.copy_from_slice(Sha256::digest(bytecode).as_slice());
+    buffer[0] = ContractCodeSha256::VERSION_BYTE;
+    buffer[1] = ContractCodeSha256::CODE_AT_REST_MARKER;
+    buffer[2..4].copy_from_slice(&(length_in_words as u16).to_be_bytes());
+
+    debug_assert!(
+        VersionedHashGeneric::<ContractCodeSha256>::try_create_from_raw(buffer).is_some()
+    );
+
+    Ok(U256::from_big_endian(&buffer))
+}
+
+pub fn hash_bytecode_words(bytecode: &[[u8; 32]]) -> Result<U256, BytecodeHashingError> {
+    hash_bytecode(&bytecode.concat())
+}
+
+/// Length in words that is encoded in the versioned hash
+pub fn code_length_in_words_from_hash(hash: &U256) -> u16 {
+    let mut buffer = [0u8; 32];
+    hash.to_big_endian(&mut buffer);
+
+    u16::from_be_bytes([buffer[2], buffer[3]])
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DecommittmentStatistics {
+    pub fresh_decommittments: u64,
+    pub repeated_decommittments: u64,
+    pub fresh_words_decommitted: u64,
+    pub unknown_hash_requests: u64,
+}
+
+/// Decommitter that computes code hashes by itself, so one can not populate it with
+/// a bytecode that doesn't match the hash. Decommittment freshness is tracked per batch
+#[derive(Debug)]
+pub struct ContentAddressedDecommitter<const B: bool> {
+    known_hashes: HashMap<U256, Vec<U256>>,
+    history: DecommittmentHistory,
+    statistics: DecommittmentStatistics,
+}
+
+impl<const B: bool> ContentAddressedDecommitter<B> {
+    pub fn new() -> Self {
+        Self {
+            known_hashes: HashMap::default(),
+            history: DecommittmentHistory::default(),
+            statistics: DecommittmentStatistics::default(),
+        }
+    }
+
+    pub fn add_bytecode(&mut self, bytecode: &[u8]) -> Result<U256, BytecodeHashingError> {
+        let hash = hash_bytecode(bytecode)?;
+        let words = bytecode
+            .chunks(32)
+            .map(|el| U256::from_big_endian(el))
+            .collect();
+        self.known_hashes.insert(hash, words);
+
+        Ok(hash)
+    }
+
+    pub fn add_bytecode_words(
+        &mut self,
+        bytecode: &[[u8; 32]],
+    ) -> Result<U256, BytecodeHashingError> {
+        self.add_bytecode(&bytecode.concat())
+    }
+
+    pub fn is_known(&self, hash: &U256) -> bool {
+        self.known_hashes.contains_key(hash)
+    }
+
+    pub fn statistics(&self) -> DecommittmentStatistics {
+        self.statistics
+    }
+
+    /// Forgets what was decommitted in the current batch, so every code will be fresh again,
+    /// and returns statistics of the finished batch
+    pub fn finish_batch(&mut self) -> DecommittmentStatistics {
+        self.history.clear();
+
+        std::mem::take(&mut self.statistics)
+    }
+}
+
+impl<const B: bool> DecommittmentProcessor for ContentAddressedDecommitter<B> {
+    fn decommit_into_memory<M: Memory>(
+        &mut self,
+        monotonic_cycle_counter: u32,
+        partial_query: DecommittmentQuery,
+        memory: &mut M,
+    ) -> anyhow::Result<(DecommittmentQuery, Option<Vec<U256>>)> {
+        if let Some(query) = self.history.repeated(partial_query) {
+            self.statistics.repeated_decommittments += 1;
+            return Ok((query, B.then(Vec::new))); // empty extra data
+        }
+
+        let Some(values) = self.known_hashes.get(&partial_query.hash) else {
+            self.statistics.unknown_hash_requests += 1;
+            return Err(DecommittmentError::UnknownCodeHash(partial_query.hash).into());
+        };
+        let declared_length_in_words = code_length_in_words_from_hash(&partial_query.hash);
+        if declared_length_in_words as usize != values.len() {
+            // can only happen if caller asks for a hash with modified length
+            return Err(DecommittmentError::CodeLengthMismatch {
+                hash: partial_query.hash,
+                declared_length_in_words,
+                actual_length_in_words: values.len() as u16,
             }
+            .into());
         }
+
+        let query =
+            self.history
+                .decommit_fresh(monotonic_cycle_counter, partial_query, values, memory);
+        self.statistics.fresh_decommittments += 1;
+        self.statistics.fresh_words_decommitted += values.len() as u64;
+
+        Ok((query, B.then(|| values.clone())))
     }
 }
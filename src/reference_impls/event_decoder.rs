@@ -0,0 +1,240 @@
+use super::*;
+
+use crate::reference_impls::event_sink::{EventMessage, InMemoryEventSink};
+use std::collections::BTreeMap;
+use zk_evm_abstractions::queries::LogQuery;
+use zkevm_opcode_defs::ethereum_types::H256;
+use zkevm_opcode_defs::system_params::ADDRESS_EVENT_WRITER;
+
+// Events are emitted as a sequence of `LogQuery`s, where the first one (marked by `is_service`,
+// that becomes `is_first` in `EventMessage`) contains number of topics in the lowest 32 bits of the key,
+// and data length in bytes in the next 32 bits. Value of the first query is the first topic
+// (or first data word if there are no topics). Every following query carries two more words
+// (key and value), that are topics while there are any left, and data after it
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SolidityLikeEvent {
+    pub shard_id: u8,
+    pub tx_number_in_block: u16,
+    pub address: Address,
+    pub topics: Vec<[u8; 32]>,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct L2ToL1Message {
+    pub shard_id: u8,
+    pub is_service: bool,
+    pub tx_number_in_block: u16,
+    pub sender: Address,
+    pub key: U256,
+    pub value: U256,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EthereumLikeLog {
+    pub tx_number_in_block: u16,
+    pub log_index_in_tx: u32,
+    pub address: Address,
+    pub topics: Vec<H256>,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TransactionEvents {
+    pub tx_number_in_block: u16,
+    pub events: Vec<SolidityLikeEvent>,
+    pub l1_messages: Vec<L2ToL1Message>,
+}
+
+struct PartialEvent {
+    remaining_topics: u32,
+    remaining_data_length: usize,
+    event: SolidityLikeEvent,
+}
+
+impl PartialEvent {
+    fn is_complete(&self) -> bool {
+        self.remaining_topics == 0 && self.remaining_data_length == 0
+    }
+
+    fn absorb_word(&mut self, word: U256) {
+        let mut buffer = [0u8; 32];
+        word.to_big_endian(&mut buffer);
+        if self.remaining_topics != 0 {
+            self.event.topics.push(buffer);
+            self.remaining_topics -= 1;
+        } else if self.remaining_data_length != 0 {
+            let to_take = std::cmp::min(32, self.remaining_data_length);
+            self.event.data.extend_from_slice(&buffer[..to_take]);
+            self.remaining_data_length -= to_take;
+        }
+        // otherwise it's a padding word in the last query
+    }
+}
+
+/// Glues consecutive `EventMessage`s into logical events. Malformed events (not enough
+/// continuation messages, or continuation from another address) are dropped
+pub fn merge_events(messages: &[EventMessage]) -> Vec<SolidityLikeEvent> {
+    let mut result = vec![];
+    let mut current: Option<PartialEvent> = None;
+
+    for message in messages.iter() {
+        let EventMessage {
+            shard_id,
+            is_first,
+            tx_number_in_block,
+            address,
+            key,
+            value,
+        } = *message;
+
+        if is_first {
+            // only take the previous one if it's well formed
+            if let Some(previous) = current.take() {
+                if previous.is_complete() {
+                    result.push(previous.event);
+                }
+            }
+
+            // ignore higher bits of the key
+            let num_topics = key.0[0] as u32;
+            let data_length = (key.0[0] >> 32) as usize;
+
+            let mut new_event = PartialEvent {
+                remaining_topics: num_topics,
+                remaining_data_length: data_length,
+                event: SolidityLikeEvent {
+                    shard_id,
+                    tx_number_in_block,
+                    address,
+                    topics: vec![],
+                    data: vec![],
+                },
+            };
+            new_event.absorb_word(value);
+
+            current = Some(new_event);
+        } else {
+            let Some(mut partial) = current.take() else {
+                // continuation without a start
+                continue;
+            };
+            if partial.event.address != address
+                || partial.event.tx_number_in_block != tx_number_in_block
+            {
+                // foreign continuation, so we drop an event that we were assembling
+                continue;
+            }
+            partial.absorb_word(key);
+            partial.absorb_word(value);
+
+            current = Some(partial);
+        }
+    }
+
+    // add the last one
+    if let Some(last) = current.take() {
+        if last.is_complete() {
+            result.push(last.event);
+        }
+    }
+
+    result
+}
+
+pub fn l1_messages_from_event_messages(messages: &[EventMessage]) -> Vec<L2ToL1Message> {
+    messages
+        .iter()
+        .map(|el| L2ToL1Message {
+            shard_id: el.shard_id,
+            is_service: el.is_first,
+            tx_number_in_block: el.tx_number_in_block,
+            sender: el.address,
+            key: el.key,
+            value: el.value,
+        })
+        .collect()
+}
+
+/// Groups logical events and L2->L1 messages per transaction, ordered by transaction number
+pub fn group_by_transaction(
+    events: Vec<SolidityLikeEvent>,
+    l1_messages: Vec<L2ToL1Message>,
+) -> Vec<TransactionEvents> {
+    let mut per_tx = BTreeMap::<u16, TransactionEvents>::new();
+    for event in events.into_iter() {
+        let tx_number_in_block = event.tx_number_in_block;
+        per_tx
+            .entry(tx_number_in_block)
+            .or_insert_with(|| TransactionEvents {
+                tx_number_in_block,
+                ..TransactionEvents::default()
+            })
+            .events
+            .push(event);
+    }
+    for message in l1_messages.into_iter() {
+        let tx_number_in_block = message.tx_number_in_block;
+        per_tx
+            .entry(tx_number_in_block)
+            .or_insert_with(|| TransactionEvents {
+                tx_number_in_block,
+                ..TransactionEvents::default()
+            })
+            .l1_messages
+            .push(message);
+    }
+
+    per_tx.into_values().collect()
+}
+
+impl SolidityLikeEvent {
+    pub fn is_from_event_writer(&self) -> bool {
+        self.address == Address::from_low_u64_be(ADDRESS_EVENT_WRITER as u64)
+    }
+
+    /// Representation that explorers expect. Events that go through the event writer system contract
+    /// carry the actual emitter as the first topic, so we unwrap it. Returns `None` if the event
+    /// writer's event has no topics at all
+    pub fn to_ethereum_log(&self, log_index_in_tx: u32) -> Option<EthereumLikeLog> {
+        let (address, topics) = if self.is_from_event_writer() {
+            let (first, rest) = self.topics.split_first()?;
+            (Address::from_slice(&first[12..]), rest)
+        } else {
+            (self.address, &self.topics[..])
+        };
+
+        Some(EthereumLikeLog {
+            tx_number_in_block: self.tx_number_in_block,
+            log_index_in_tx,
+            address,
+            topics: topics.iter().map(|el| H256(*el)).collect(),
+            data: self.data.clone(),
+        })
+    }
+}
+
+impl TransactionEvents {
+    pub fn ethereum_logs(&self) -> Vec<EthereumLikeLog> {
+        let mut result = Vec::with_capacity(self.events.len());
+        for event in self.events.iter() {
+            if let Some(log) = event.to_ethereum_log(result.len() as u32) {
+                result.push(log);
+            }
+        }
+
+        result
+    }
+}
+
+impl InMemoryEventSink {
+    /// Same as `flatten`, but returns logical events and L2->L1 messages grouped per transaction
+    pub fn flatten_and_group(self) -> (Vec<LogQuery>, Vec<TransactionEvents>) {
+        let (history, events, l1_messages) = self.flatten();
+        let events = merge_events(&events);
+        let l1_messages = l1_messages_from_event_messages(&l1_messages);
+
+        (history, group_by_transaction(events, l1_messages))
+    }
+}
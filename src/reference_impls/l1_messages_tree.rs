@@ -0,0 +1,162 @@
+use super::*;
+
+use crate::reference_impls::event_decoder::{l1_messages_from_event_messages, L2ToL1Message};
+use crate::reference_impls::event_sink::InMemoryEventSink;
+use zkevm_opcode_defs::sha3::{Digest, Keccak256};
+
+// Leaf encoding follows the log queue of the circuits:
+// shard_id (1 byte) || is_service (1 byte) || tx_number_in_block (2 bytes BE) ||
+// sender (20 bytes) || key (32 bytes BE) || value (32 bytes BE)
+pub const L2_TO_L1_LOG_SERIALIZED_SIZE: usize = 88;
+
+// tree is padded with empty leaves to this number of leaves, or to the next power of two if there are more messages
+pub const DEFAULT_L2_TO_L1_LOGS_TREE_SIZE: usize = 2048;
+
+impl L2ToL1Message {
+    pub fn to_leaf_bytes(&self) -> [u8; L2_TO_L1_LOG_SERIALIZED_SIZE] {
+        let mut result = [0u8; L2_TO_L1_LOG_SERIALIZED_SIZE];
+        result[0] = self.shard_id;
+        result[1] = self.is_service as u8;
+        result[2..4].copy_from_slice(&self.tx_number_in_block.to_be_bytes());
+        result[4..24].copy_from_slice(self.sender.as_bytes());
+        self.key.to_big_endian(&mut result[24..56]);
+        self.value.to_big_endian(&mut result[56..88]);
+
+        result
+    }
+
+    pub fn leaf_hash(&self) -> [u8; 32] {
+        keccak256(&self.to_leaf_bytes())
+    }
+}
+
+fn keccak256(input: &[u8]) -> [u8; 32] {
+    let mut result = [0u8; 32];
+    result.copy_from_slice(Keccak256::digest(input).as_slice());
+
+    result
+}
+
+fn hash_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(left);
+    hasher.update(right);
+    let mut result = [0u8; 32];
+    result.copy_from_slice(hasher.finalize().as_slice());
+
+    result
+}
+
+pub fn empty_leaf_hash() -> [u8; 32] {
+    keccak256(&[0u8; L2_TO_L1_LOG_SERIALIZED_SIZE])
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct L1MessageInclusionProof {
+    pub leaf_index: usize,
+    pub leaf_hash: [u8; 32],
+    // siblings from the leaf level up to the root
+    pub path: Vec<[u8; 32]>,
+}
+
+impl L1MessageInclusionProof {
+    pub fn compute_root(&self) -> [u8; 32] {
+        let mut current = self.leaf_hash;
+        let mut index = self.leaf_index;
+        for sibling in self.path.iter() {
+            current = if index % 2 == 0 {
+                hash_node(&current, sibling)
+            } else {
+                hash_node(sibling, &current)
+            };
+            index /= 2;
+        }
+
+        current
+    }
+
+    pub fn verify(&self, root: &[u8; 32]) -> bool {
+        &self.compute_root() == root
+    }
+}
+
+/// Full keccak-based binary Merkle tree over L2->L1 messages
+#[derive(Clone, Debug)]
+pub struct L1MessagesMerkleTree {
+    // layers from leaves to the root, the last one has exactly one element
+    layers: Vec<Vec<[u8; 32]>>,
+    num_messages: usize,
+}
+
+impl L1MessagesMerkleTree {
+    pub fn new(messages: &[L2ToL1Message]) -> Self {
+        Self::new_with_min_size(messages, DEFAULT_L2_TO_L1_LOGS_TREE_SIZE)
+    }
+
+    pub fn new_with_min_size(messages: &[L2ToL1Message], min_tree_size: usize) -> Self {
+        assert!(min_tree_size.is_power_of_two());
+        let tree_size = std::cmp::max(messages.len().next_power_of_two(), min_tree_size);
+
+        let mut leaves = Vec::with_capacity(tree_size);
+        leaves.extend(messages.iter().map(|el| el.leaf_hash()));
+        leaves.resize(tree_size, empty_leaf_hash());
+
+        let mut layers = vec![leaves];
+        while layers.last().unwrap().len() > 1 {
+            let next: Vec<_> = layers
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| hash_node(&pair[0], &pair[1]))
+                .collect();
+            layers.push(next);
+        }
+
+        Self {
+            layers,
+            num_messages: messages.len(),
+        }
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.layers.last().unwrap()[0]
+    }
+
+    pub fn num_messages(&self) -> usize {
+        self.num_messages
+    }
+
+    pub fn tree_size(&self) -> usize {
+        self.layers[0].len()
+    }
+
+    pub fn inclusion_proof(&self, message_index: usize) -> Option<L1MessageInclusionProof> {
+        if message_index >= self.num_messages {
+            return None;
+        }
+
+        let mut path = Vec::with_capacity(self.layers.len() - 1);
+        let mut index = message_index;
+        for layer in self.layers[..self.layers.len() - 1].iter() {
+            path.push(layer[index ^ 1]);
+            index /= 2;
+        }
+
+        Some(L1MessageInclusionProof {
+            leaf_index: message_index,
+            leaf_hash: self.layers[0][message_index],
+            path,
+        })
+    }
+}
+
+impl InMemoryEventSink {
+    /// Commits to net L2->L1 messages in the order in which `flatten` returns them
+    pub fn l1_messages_tree(self) -> (Vec<L2ToL1Message>, L1MessagesMerkleTree) {
+        let (_history, _events, l1_messages) = self.flatten();
+        let messages = l1_messages_from_event_messages(&l1_messages);
+        let tree = L1MessagesMerkleTree::new(&messages);
+
+        (messages, tree)
+    }
+}
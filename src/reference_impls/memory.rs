@@ -10,6 +10,8 @@ use zk_evm_abstractions::vm::{
 };
 use zkevm_opcode_defs::{FatPointer, BOOTLOADER_CALLDATA_PAGE};
 
+use super::memory_stats::*;
+use super::page_lifetimes::PageLifetimeTracker;
 use super::*;
 
 pub struct ReusablePool<
@@ -79,6 +81,7 @@ pub enum Indirection {
 #[derive(Debug)]
 pub struct HeapPagesReusablePool {
     pool: Vec<Vec<U256>>,
+    stats: PoolStats,
 }
 
 impl HeapPagesReusablePool {
@@ -89,15 +92,20 @@ impl HeapPagesReusablePool {
             pool.push(el);
         }
 
-        Self { pool }
+        Self {
+            pool,
+            stats: PoolStats::default(),
+        }
     }
 
     pub fn pull(&mut self) -> Vec<U256> {
         if let Some(mut existing) = self.pool.pop() {
+            self.stats.hits += 1;
             heap_on_pull(&mut existing);
 
             existing
         } else {
+            self.stats.misses += 1;
             let mut new = heap_init();
             heap_on_pull(&mut new);
 
@@ -109,11 +117,16 @@ impl HeapPagesReusablePool {
         heap_on_return(&mut el);
         self.pool.push(el);
     }
+
+    pub fn stats(&self) -> PoolStats {
+        self.stats
+    }
 }
 
 #[derive(Debug)]
 pub struct StackPagesReusablePool {
     pool: Vec<Vec<PrimitiveValue>>,
+    stats: PoolStats,
 }
 
 impl StackPagesReusablePool {
@@ -124,15 +137,20 @@ impl StackPagesReusablePool {
             pool.push(el);
         }
 
-        Self { pool }
+        Self {
+            pool,
+            stats: PoolStats::default(),
+        }
     }
 
     pub fn pull(&mut self) -> Vec<PrimitiveValue> {
         if let Some(mut existing) = self.pool.pop() {
+            self.stats.hits += 1;
             stack_on_pull(&mut existing);
 
             existing
         } else {
+            self.stats.misses += 1;
             let mut new = stack_init();
             stack_on_pull(&mut new);
 
@@ -144,6 +162,10 @@ impl StackPagesReusablePool {
         stack_on_return(&mut el);
         self.pool.push(el);
     }
+
+    pub fn stats(&self) -> PoolStats {
+        self.stats
+    }
 }
 
 #[derive(Debug)]
@@ -161,13 +183,26 @@ pub struct SimpleMemory<S: BuildHasher + Default = RandomState> {
     // - perform far call we are ok to just keep calldata ptr as indirection
     // - but when we return we should check that our returndata ptr doesn't force us to later on
     // extend a lifetime of calldataptr indirection
+    // `page_lifetimes` follows the same graph and tells when the extended lifetime page
+    // can no longer be referenced, so it can be freed
     pub pages_with_extended_lifetime: HashMap<u32, Vec<U256>, S>,
     pub page_numbers_indirections: HashMap<u32, Indirection, S>,
     pub indirections_to_cleanup_on_return: Vec<HashSet<u32, S>>,
+    pub page_lifetimes: PageLifetimeTracker,
 
     // we do not need a pool for code pages as those are extended lifetime always
     pub heaps_pool: HeapPagesReusablePool,
     pub stacks_pool: StackPagesReusablePool,
+
+    // statistics, see `MemoryStatistics`
+    pub allocated_pages: PageCounts,
+    pub peak_live_pages: usize,
+    // bounds of running frames, one per entry in `heaps` except the formal one
+    pub heap_bounds: Vec<FrameHeapBounds>,
+    // largest (heap, aux heap) bounds among finished frames
+    pub finished_frames_max_heap_bounds: (u32, u32),
+    // only kept if requested, as it grows with every far call
+    pub finished_frames_heap_bounds: Option<Vec<FrameHeapBounds>>,
 }
 
 fn heap_init() -> Vec<U256> {
@@ -222,6 +257,16 @@ impl<S: BuildHasher + Default> SimpleMemory<S> {
             indirections_to_cleanup_on_return: Vec::with_capacity(1024),
             heaps_pool: HeapPagesReusablePool::new_with_capacity(1 << 12),
             stacks_pool: StackPagesReusablePool::new_with_capacity(1 << 11),
+            page_lifetimes: PageLifetimeTracker::new(),
+            allocated_pages: PageCounts {
+                code: 1,
+                extended_lifetime: 1,
+                ..PageCounts::default()
+            },
+            peak_live_pages: 0,
+            heap_bounds: Vec::with_capacity(1024),
+            finished_frames_max_heap_bounds: (0, 0),
+            finished_frames_heap_bounds: None,
         };
 
         // this one virtually exists always
@@ -230,12 +275,14 @@ impl<S: BuildHasher + Default> SimpleMemory<S> {
         new.pages_with_extended_lifetime
             .insert(BOOTLOADER_CALLDATA_PAGE, vec![U256::zero(); 1 << 10]);
         new.page_numbers_indirections.insert(0, Indirection::Empty); // quicker lookup
+        new.page_lifetimes.pin_page(BOOTLOADER_CALLDATA_PAGE);
         new.indirections_to_cleanup_on_return
             .push(HashSet::with_capacity_and_hasher(4, S::default()));
         new.heaps.push((
             (0u32, vec![U256::zero(); 1 << 10]),
             (0u32, vec![U256::zero(); 1 << 20]),
         )); // formally, so we can access "last"
+        new.update_peak_live_pages();
 
         new
     }
@@ -250,6 +297,16 @@ impl<S: BuildHasher + Default> SimpleMemory<S> {
             indirections_to_cleanup_on_return: Vec::with_capacity(1024),
             heaps_pool: HeapPagesReusablePool::new_with_capacity(2),
             stacks_pool: StackPagesReusablePool::new_with_capacity(2),
+            page_lifetimes: PageLifetimeTracker::new(),
+            allocated_pages: PageCounts {
+                code: 1,
+                extended_lifetime: 1,
+                ..PageCounts::default()
+            },
+            peak_live_pages: 0,
+            heap_bounds: vec![],
+            finished_frames_max_heap_bounds: (0, 0),
+            finished_frames_heap_bounds: None,
         };
 
         // this one virtually exists always
@@ -258,12 +315,21 @@ impl<S: BuildHasher + Default> SimpleMemory<S> {
         new.pages_with_extended_lifetime
             .insert(BOOTLOADER_CALLDATA_PAGE, vec![]);
         new.page_numbers_indirections.insert(0, Indirection::Empty); // quicker lookup
+        new.page_lifetimes.pin_page(BOOTLOADER_CALLDATA_PAGE);
         new.indirections_to_cleanup_on_return
             .push(HashSet::with_capacity_and_hasher(4, S::default()));
         new.heaps.push(((0u32, vec![]), (0u32, vec![]))); // formally, so we can access "last"
+        new.update_peak_live_pages();
 
         new
     }
+
+    /// Keeps heap bounds of every finished frame for `memory_stats`
+    pub fn with_frame_heap_bounds_history(mut self) -> Self {
+        self.finished_frames_heap_bounds
+            .get_or_insert_with(Vec::new);
+        self
+    }
 }
 
 impl<S: BuildHasher + Default> SimpleMemory<S> {
@@ -277,12 +343,44 @@ impl<S: BuildHasher + Default> SimpleMemory<S> {
             let mut values = values;
             values.resize(MAX_CODE_PAGE_SIZE_IN_WORDS, U256::zero());
             self.code_pages.insert(page, values);
+            self.allocated_pages.code += 1;
             results.push((page, len));
         }
+        self.update_peak_live_pages();
 
         results
     }
 
+    pub fn live_pages(&self) -> PageCounts {
+        // formal heaps of the root frame are not counted
+        let num_heaps = self.heaps.len() - 1;
+
+        PageCounts {
+            code: self.code_pages.len(),
+            stack: self.stack_pages.len(),
+            heap: num_heaps,
+            aux_heap: num_heaps,
+            extended_lifetime: self.pages_with_extended_lifetime.len(),
+        }
+    }
+
+    fn update_peak_live_pages(&mut self) {
+        self.peak_live_pages = std::cmp::max(self.peak_live_pages, self.live_pages().total());
+    }
+
+    fn update_heap_bound(&mut self, memory_type: MemoryType, index: u32) {
+        // root frame's heap can only be populated
+        let Some(bounds) = self.heap_bounds.last_mut() else {
+            return;
+        };
+        let bound = if memory_type == MemoryType::Heap {
+            &mut bounds.heap_bound_in_words
+        } else {
+            &mut bounds.aux_heap_bound_in_words
+        };
+        *bound = std::cmp::max(*bound, index + 1);
+    }
+
     // Can never populate stack or aux heap
     pub fn populate_heap(&mut self, values: Vec<U256>) {
         let heaps_data = self.heaps.last_mut().unwrap();
@@ -398,6 +496,51 @@ impl<S: BuildHasher + Default> SimpleMemory<S> {
         let upper_bound = 1 << 10;
         self.dump_page_content(page_number, 0..upper_bound)
     }
+
+    /// Reads `len` bytes starting at the byte `offset` of the page, same as a sequence
+    /// of UMA reads would do. Unwritten memory reads as zeroes
+    pub fn read_bytes(&self, page_number: u32, offset: u32, len: u32) -> Vec<u8> {
+        let range = words_range_for_bytes(offset, len);
+        let words = self.dump_page_content_as_u256_words(page_number, range);
+
+        bytes_from_words(&words, offset, len)
+    }
+
+    /// Writes bytes starting at the byte `offset` of the page. Only heaps and aux heaps of
+    /// running frames and pages with extended lifetime can be written
+    pub fn write_bytes(&mut self, page_number: u32, offset: u32, bytes: &[u8]) {
+        let len = u32::try_from(bytes.len()).expect("too many bytes to write");
+        let range = words_range_for_bytes(offset, len);
+        if range.is_empty() {
+            return;
+        }
+        let content = self
+            .byte_addressable_page_mut(page_number)
+            .unwrap_or_else(|| panic!("page {} is not a heap or returndata page", page_number));
+        resize_to_fit(content, range.end as usize - 1);
+
+        write_bytes_into_words(
+            &mut content[(range.start as usize)..(range.end as usize)],
+            offset,
+            bytes,
+        );
+    }
+
+    fn byte_addressable_page_mut(&mut self, page_number: u32) -> Option<&mut Vec<U256>> {
+        if let Some(content) = self.pages_with_extended_lifetime.get_mut(&page_number) {
+            return Some(content);
+        }
+        // skip the formal heaps of the root frame
+        for (heap_data, aux_heap_data) in self.heaps[1..].iter_mut().rev() {
+            if heap_data.0 == page_number {
+                return Some(&mut heap_data.1);
+            } else if aux_heap_data.0 == page_number {
+                return Some(&mut aux_heap_data.1);
+            }
+        }
+
+        None
+    }
 }
 
 impl Memory for SimpleMemory {
@@ -438,6 +581,7 @@ impl Memory for SimpleMemory {
             }
             a @ MemoryType::Heap | a @ MemoryType::AuxHeap => {
                 assert!(query.value_is_pointer == false);
+                self.update_heap_bound(a, query.location.index.0);
                 if query.rw_flag {
                     let (
                         (current_heap_page, current_heap_content),
@@ -541,6 +685,8 @@ impl Memory for SimpleMemory {
             if self.code_pages.contains_key(&page) == false {
                 self.code_pages
                     .insert(page, vec![U256::zero(); MAX_CODE_PAGE_SIZE_IN_WORDS]);
+                self.allocated_pages.code += 1;
+                self.update_peak_live_pages();
             }
             let page_content = self.code_pages.get_mut(&page).unwrap();
             page_content[idx] = query.value;
@@ -605,6 +751,18 @@ impl Memory for SimpleMemory {
             (heap_page.0, heap_page_from_pool),
             (aux_heap_page.0, aux_heap_page_from_pool),
         ));
+        self.page_lifetimes
+            .start_frame(new_base_page, calldata_fat_pointer.memory_page);
+        self.heap_bounds.push(FrameHeapBounds {
+            heap_page: heap_page.0,
+            aux_heap_page: aux_heap_page.0,
+            heap_bound_in_words: 0,
+            aux_heap_bound_in_words: 0,
+        });
+        self.allocated_pages.stack += 1;
+        self.allocated_pages.heap += 1;
+        self.allocated_pages.aux_heap += 1;
+        self.update_peak_live_pages();
 
         // self.heaps.push(
         //     (
@@ -689,6 +847,13 @@ impl Memory for SimpleMemory {
         ) = self.heaps.pop().unwrap();
         assert_eq!(heap_page.0, current_heap_page);
         assert_eq!(aux_heap_page.0, current_aux_heap_page);
+        let bounds = self.heap_bounds.pop().expect("bounds must exist");
+        let (max_heap_bound, max_aux_heap_bound) = &mut self.finished_frames_max_heap_bounds;
+        *max_heap_bound = (*max_heap_bound).max(bounds.heap_bound_in_words);
+        *max_aux_heap_bound = (*max_aux_heap_bound).max(bounds.aux_heap_bound_in_words);
+        if let Some(history) = self.finished_frames_heap_bounds.as_mut() {
+            history.push(bounds);
+        }
 
         let mut current_frame_indirections_to_cleanup = self
             .indirections_to_cleanup_on_return
@@ -708,6 +873,7 @@ impl Memory for SimpleMemory {
             self.page_numbers_indirections
                 .insert(current_heap_page, Indirection::ReturndataExtendedLifetime);
             previous_frame_indirections_to_cleanup.insert(current_heap_page);
+            self.allocated_pages.extended_lifetime += 1;
 
             // and we can reuse another page
             self.heaps_pool.return_element(current_aux_heap_content);
@@ -722,6 +888,7 @@ impl Memory for SimpleMemory {
                 Indirection::ReturndataExtendedLifetime,
             );
             previous_frame_indirections_to_cleanup.insert(current_aux_heap_page);
+            self.allocated_pages.extended_lifetime += 1;
 
             // and we can reuse another page
             self.heaps_pool.return_element(current_heap_content);
@@ -755,5 +922,43 @@ impl Memory for SimpleMemory {
             let existing = self.page_numbers_indirections.remove(&el);
             assert!(existing.is_some(), "double free in indirection");
         }
+
+        // and free returndata pages that can not be referenced anymore
+        for page in self.page_lifetimes.finish_frame(base_page, returndata_page) {
+            if let Some(content) = self.pages_with_extended_lifetime.remove(&page) {
+                self.heaps_pool.return_element(content);
+            }
+        }
+    }
+}
+
+impl<S: BuildHasher + Default> MemoryStatistics for SimpleMemory<S> {
+    fn memory_stats(&self) -> MemoryStats {
+        let mut frame_heap_bounds: Vec<_> = self
+            .finished_frames_heap_bounds
+            .iter()
+            .flatten()
+            .chain(self.heap_bounds.iter())
+            .copied()
+            .collect();
+        frame_heap_bounds.sort_by_key(|el| el.heap_page);
+        let (mut max_heap_bound_in_words, mut max_aux_heap_bound_in_words) =
+            self.finished_frames_max_heap_bounds;
+        for bounds in self.heap_bounds.iter() {
+            max_heap_bound_in_words = max_heap_bound_in_words.max(bounds.heap_bound_in_words);
+            max_aux_heap_bound_in_words =
+                max_aux_heap_bound_in_words.max(bounds.aux_heap_bound_in_words);
+        }
+
+        MemoryStats {
+            allocated_pages: self.allocated_pages,
+            live_pages: self.live_pages(),
+            peak_live_pages: self.peak_live_pages,
+            heap_pages_pool: self.heaps_pool.stats(),
+            stack_pages_pool: self.stacks_pool.stats(),
+            max_heap_bound_in_words,
+            max_aux_heap_bound_in_words,
+            frame_heap_bounds,
+        }
     }
 }
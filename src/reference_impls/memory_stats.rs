@@ -0,0 +1,56 @@
+// Statistics that help to estimate how much memory the execution of a batch needs. All the
+// numbers are in pages (or in words for heap bounds), as sizes of pages depend on the memory
+// implementation.
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PageCounts {
+    pub code: usize,
+    pub stack: usize,
+    pub heap: usize,
+    pub aux_heap: usize,
+    // calldata and returndata pages that outlive the frame that has created them
+    pub extended_lifetime: usize,
+}
+
+impl PageCounts {
+    pub fn total(&self) -> usize {
+        self.code + self.stack + self.heap + self.aux_heap + self.extended_lifetime
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    // page was taken from the pool
+    pub hits: u64,
+    // pool was empty and a new page was allocated
+    pub misses: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameHeapBounds {
+    pub heap_page: u32,
+    pub aux_heap_page: u32,
+    // one past the largest accessed word index, 0 if the heap was never accessed
+    pub heap_bound_in_words: u32,
+    pub aux_heap_bound_in_words: u32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    // pages allocated since the memory was created
+    pub allocated_pages: PageCounts,
+    pub live_pages: PageCounts,
+    pub peak_live_pages: usize,
+    pub heap_pages_pool: PoolStats,
+    pub stack_pages_pool: PoolStats,
+    // over all global frames, both finished and running
+    pub max_heap_bound_in_words: u32,
+    pub max_aux_heap_bound_in_words: u32,
+    // for every running global frame, and for finished ones if the memory keeps their
+    // history, ordered by heap page
+    pub frame_heap_bounds: Vec<FrameHeapBounds>,
+}
+
+pub trait MemoryStatistics {
+    fn memory_stats(&self) -> MemoryStats;
+}
@@ -2,6 +2,16 @@ use super::*;
 
 use std::collections::HashMap;
 
+pub mod artifacts;
+pub mod artifacts_decommitter;
+pub mod checked;
 pub mod decommitter;
+pub mod event_decoder;
 pub mod event_sink;
+pub mod l1_messages_tree;
 pub mod memory;
+pub mod memory_stats;
+pub mod page_lifetimes;
+pub mod paged_memory;
+pub mod precompiles;
+pub mod receipts;
@@ -0,0 +1,175 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::vm_state::CallStackEntry;
+use zk_evm_abstractions::aux::MemoryPage;
+use zkevm_opcode_defs::decoding::EncodingModeProduction;
+
+// Lifetimes of pages that can be accessed through fat pointers. Every global frame owns its
+// stack, heap and aux heap pages for as long as it runs. Besides that a frame can reference
+// pages of other frames through fat pointers it has received:
+// - calldata pointer, that points to a heap or aux heap of the caller, or is forwarded by the
+// caller from its own calldata or returndata
+// - returndata pointers of all the callees, since the frame can keep old pointers in registers
+// or on stack
+//
+// When a frame finishes, it loses all the references and owned pages, except the page that it
+// returns: the caller starts to reference it, and if it was the frame's heap or aux heap, then
+// the page outlives the frame. A page that is neither owned by a running frame nor referenced
+// by any of them can never be accessed again and can be freed.
+//
+// Limitation: the tracker doesn't see registers and stack, so it can't tell when the frame has
+// overwritten its last copy of a returndata pointer. Returndata pages of all the callees stay
+// referenced until the frame itself finishes, e.g. for the bootloader it means until the end
+// of the batch. The estimate is conservative: pages are never freed too early, but can be
+// freed too late.
+
+type Entry = CallStackEntry<8, EncodingModeProduction>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PageLifetime {
+    // stack, heap or aux heap of the running frame with the given base page
+    OwnedByFrame { base_page: u32 },
+    // returned by the frame that has finished, but still referenced by some running frames
+    Extended,
+    // lives as long as the VM, e.g. bootloader's calldata
+    Pinned,
+    Unreachable,
+}
+
+#[derive(Clone, Debug, Default)]
+struct FrameReferences {
+    base_page: u32,
+    referenced_pages: Vec<u32>,
+}
+
+#[derive(Clone, Debug)]
+pub struct PageLifetimeTracker {
+    frames: Vec<FrameReferences>,
+    // number of running frames that reference the page
+    references: HashMap<u32, usize>,
+    pinned: HashSet<u32>,
+}
+
+impl Default for PageLifetimeTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PageLifetimeTracker {
+    pub fn new() -> Self {
+        Self {
+            // formal frame of the VM itself, it references returndata of the root frame
+            frames: vec![FrameReferences::default()],
+            references: HashMap::new(),
+            pinned: HashSet::new(),
+        }
+    }
+
+    pub fn pin_page(&mut self, page: u32) {
+        self.pinned.insert(page);
+    }
+
+    /// Base page of the current frame, 0 if no frame is running
+    pub fn current_base_page(&self) -> u32 {
+        self.frames.last().unwrap().base_page
+    }
+
+    fn owner(&self, page: u32) -> Option<u32> {
+        self.frames[1..]
+            .iter()
+            .rev()
+            .map(|el| el.base_page)
+            .find(|base_page| (base_page + 1..=base_page + 3).contains(&page))
+    }
+
+    pub fn lifetime(&self, page: u32) -> PageLifetime {
+        if self.pinned.contains(&page) {
+            PageLifetime::Pinned
+        } else if let Some(base_page) = self.owner(page) {
+            PageLifetime::OwnedByFrame { base_page }
+        } else if self.references.contains_key(&page) {
+            PageLifetime::Extended
+        } else {
+            PageLifetime::Unreachable
+        }
+    }
+
+    /// Base pages of the running frames that hold fat pointers to the page, outermost first.
+    /// The formal frame of the VM is reported with base page 0
+    pub fn referencing_frames(&self, page: u32) -> Vec<u32> {
+        self.frames
+            .iter()
+            .filter(|el| el.referenced_pages.contains(&page))
+            .map(|el| el.base_page)
+            .collect()
+    }
+
+    fn add_reference(&mut self, page: u32) {
+        // page 0 is the formal empty page
+        if page == 0 {
+            return;
+        }
+        let frame = self.frames.last_mut().unwrap();
+        if frame.referenced_pages.contains(&page) {
+            return;
+        }
+        frame.referenced_pages.push(page);
+        *self.references.entry(page).or_default() += 1;
+    }
+
+    pub fn start_frame(&mut self, new_base_page: MemoryPage, calldata_page: u32) {
+        assert!(
+            calldata_page == 0 || self.lifetime(calldata_page) != PageLifetime::Unreachable,
+            "calldata page {} is not reachable",
+            calldata_page
+        );
+        self.frames.push(FrameReferences {
+            base_page: new_base_page.0,
+            referenced_pages: vec![],
+        });
+        self.add_reference(calldata_page);
+    }
+
+    /// Finishes the current frame, returns pages that became unreachable and can be freed.
+    /// Returndata page stays referenced by the caller until the caller finishes
+    pub fn finish_frame(&mut self, base_page: MemoryPage, returndata_page: u32) -> Vec<u32> {
+        assert!(self.frames.len() > 1, "frame must be started");
+        assert!(
+            returndata_page == 0 || self.lifetime(returndata_page) != PageLifetime::Unreachable,
+            "returndata page {} is not reachable",
+            returndata_page
+        );
+        let frame = self.frames.pop().unwrap();
+        assert_eq!(frame.base_page, base_page.0);
+
+        // caller must get the reference before the frame drops its ones
+        self.add_reference(returndata_page);
+
+        let mut unreachable = vec![];
+        for page in frame.referenced_pages.into_iter() {
+            let references = self.references.get_mut(&page).unwrap();
+            *references -= 1;
+            if *references == 0 {
+                self.references.remove(&page);
+                if self.lifetime(page) == PageLifetime::Unreachable {
+                    unreachable.push(page);
+                }
+            }
+        }
+        for page in [
+            Entry::stack_page_from_base(base_page).0,
+            Entry::heap_page_from_base(base_page).0,
+            Entry::aux_heap_page_from_base(base_page).0,
+        ] {
+            // heap can also be referenced, if the callee has returned the forwarded calldata
+            if self.lifetime(page) == PageLifetime::Unreachable
+                && unreachable.contains(&page) == false
+            {
+                unreachable.push(page);
+            }
+        }
+
+        unreachable
+    }
+}
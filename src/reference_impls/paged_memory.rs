@@ -0,0 +1,288 @@
+use std::sync::Arc;
+
+use crate::vm_state::{CallStackEntry, PrimitiveValue};
+use zk_evm_abstractions::aux::{MemoryPage, Timestamp};
+use zk_evm_abstractions::queries::MemoryQuery;
+use zk_evm_abstractions::vm::{Memory, MemoryType, MAX_STACK_PAGE_SIZE_IN_WORDS};
+use zkevm_opcode_defs::decoding::EncodingModeProduction;
+use zkevm_opcode_defs::{FatPointer, BOOTLOADER_CALLDATA_PAGE};
+
+use super::page_lifetimes::PageLifetimeTracker;
+use super::*;
+
+// Alternative to `SimpleMemory` for deep callstacks. Pages are stored in a vector indexed
+// by the page number and consist of chunks of words. Chunks that were never written are not
+// allocated and read as zeroes. Both pages and chunks are shared through `Arc`, so cloning the
+// memory only copies the vector of page pointers, and a write after the clone copies just
+// the page's chunk table and the written chunk.
+//
+// Pages are dropped as soon as `PageLifetimeTracker` reports that no running frame can
+// access them anymore. Reads of dropped pages give zeroes.
+
+pub const PAGED_MEMORY_CHUNK_SIZE_IN_WORDS: usize = 64;
+
+type Chunk = [PrimitiveValue; PAGED_MEMORY_CHUNK_SIZE_IN_WORDS];
+
+#[derive(Clone, Debug, Default)]
+pub struct SparsePage {
+    chunks: Vec<Option<Arc<Chunk>>>,
+}
+
+impl SparsePage {
+    pub fn read(&self, index: u32) -> PrimitiveValue {
+        let index = index as usize;
+        self.chunks
+            .get(index / PAGED_MEMORY_CHUNK_SIZE_IN_WORDS)
+            .and_then(|el| el.as_ref())
+            .map(|el| el[index % PAGED_MEMORY_CHUNK_SIZE_IN_WORDS])
+            .unwrap_or(PrimitiveValue::empty())
+    }
+
+    pub fn write(&mut self, index: u32, value: PrimitiveValue) {
+        let index = index as usize;
+        let chunk_idx = index / PAGED_MEMORY_CHUNK_SIZE_IN_WORDS;
+        if self.chunks.len() <= chunk_idx {
+            self.chunks.resize(chunk_idx + 1, None);
+        }
+        let chunk = self.chunks[chunk_idx].get_or_insert_with(|| {
+            Arc::new([PrimitiveValue::empty(); PAGED_MEMORY_CHUNK_SIZE_IN_WORDS])
+        });
+        Arc::make_mut(chunk)[index % PAGED_MEMORY_CHUNK_SIZE_IN_WORDS] = value;
+    }
+
+    /// Number of chunks that were written into
+    pub fn allocated_chunks(&self) -> usize {
+        self.chunks.iter().filter(|el| el.is_some()).count()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PagedMemory {
+    pages: Vec<Option<Arc<SparsePage>>>,
+    page_lifetimes: PageLifetimeTracker,
+}
+
+impl Default for PagedMemory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PagedMemory {
+    pub fn new() -> Self {
+        let mut page_lifetimes = PageLifetimeTracker::new();
+        page_lifetimes.pin_page(BOOTLOADER_CALLDATA_PAGE);
+
+        Self {
+            pages: vec![],
+            page_lifetimes,
+        }
+    }
+
+    /// Cheap copy of the current memory state. Memory can be restored to it by assignment
+    pub fn snapshot(&self) -> Self {
+        self.clone()
+    }
+
+    pub fn page(&self, page: u32) -> Option<&SparsePage> {
+        self.pages.get(page as usize).and_then(|el| el.as_deref())
+    }
+
+    fn page_mut(&mut self, page: u32) -> &mut SparsePage {
+        let page = page as usize;
+        if self.pages.len() <= page {
+            self.pages.resize(page + 1, None);
+        }
+        let page = self.pages[page].get_or_insert_with(Default::default);
+
+        Arc::make_mut(page)
+    }
+
+    fn drop_page(&mut self, page: u32) {
+        if let Some(el) = self.pages.get_mut(page as usize) {
+            *el = None;
+        }
+    }
+
+    pub fn read(&self, page: u32, index: u32) -> PrimitiveValue {
+        self.page(page)
+            .map(|el| el.read(index))
+            .unwrap_or(PrimitiveValue::empty())
+    }
+
+    pub fn write(&mut self, page: u32, index: u32, value: PrimitiveValue) {
+        // page 0 is the formal empty page
+        assert!(page != 0, "can not write into the empty page");
+        self.page_mut(page).write(index, value);
+    }
+
+    pub fn populate_code(&mut self, elements: Vec<(u32, Vec<U256>)>) -> Vec<(u32, usize)> {
+        let mut results = vec![];
+        for (page, values) in elements.into_iter() {
+            assert!(self.page(page).is_none());
+            results.push((page, values.len()));
+            self.populate_page(page, values);
+        }
+
+        results
+    }
+
+    pub fn populate_page(&mut self, page: u32, values: Vec<U256>) {
+        for (idx, value) in values.into_iter().enumerate() {
+            if value.is_zero() == false {
+                self.write(page, idx as u32, PrimitiveValue::from_value(value));
+            }
+        }
+    }
+
+    pub fn dump_page_content_as_u256_words(
+        &self,
+        page_number: u32,
+        range: std::ops::Range<u32>,
+    ) -> Vec<U256> {
+        match self.page(page_number) {
+            Some(page) => range.map(|idx| page.read(idx).value).collect(),
+            None => vec![U256::zero(); range.len()],
+        }
+    }
+
+    /// Reads `len` bytes starting at the byte `offset` of the page, same as a sequence
+    /// of UMA reads would do
+    pub fn read_bytes(&self, page: u32, offset: u32, len: u32) -> Vec<u8> {
+        let words = self.dump_page_content_as_u256_words(page, words_range_for_bytes(offset, len));
+
+        bytes_from_words(&words, offset, len)
+    }
+
+    pub fn write_bytes(&mut self, page: u32, offset: u32, bytes: &[u8]) {
+        let len = u32::try_from(bytes.len()).expect("too many bytes to write");
+        let range = words_range_for_bytes(offset, len);
+        let mut words = self.dump_page_content_as_u256_words(page, range.clone());
+        write_bytes_into_words(&mut words, offset, bytes);
+        for (index, word) in range.zip(words) {
+            self.write(page, index, PrimitiveValue::from_value(word));
+        }
+    }
+
+    /// Number of pages that are alive now, including code pages
+    pub fn live_pages(&self) -> usize {
+        self.pages.iter().filter(|el| el.is_some()).count()
+    }
+}
+
+impl Memory for PagedMemory {
+    fn execute_partial_query(
+        &mut self,
+        _monotonic_cycle_counter: u32,
+        mut query: MemoryQuery,
+    ) -> MemoryQuery {
+        let page = query.location.page.0;
+        let index = query.location.index.0;
+        match query.location.memory_type {
+            MemoryType::Stack => {
+                assert!(
+                    (index as usize) < MAX_STACK_PAGE_SIZE_IN_WORDS,
+                    "out of bounds for stack page for query {:?}",
+                    query
+                );
+                debug_assert_eq!(
+                    CallStackEntry::<8, EncodingModeProduction>::stack_page_from_base(MemoryPage(
+                        self.page_lifetimes.current_base_page()
+                    ))
+                    .0,
+                    page
+                );
+            }
+            MemoryType::Heap | MemoryType::AuxHeap => {
+                assert!(query.value_is_pointer == false);
+            }
+            MemoryType::FatPointer => {
+                assert!(query.rw_flag == false);
+                assert!(query.value_is_pointer == false);
+            }
+            MemoryType::Code => {
+                unreachable!("code should be through specialized query");
+            }
+        }
+
+        if query.rw_flag {
+            self.write(
+                page,
+                index,
+                PrimitiveValue {
+                    value: query.value,
+                    is_pointer: query.value_is_pointer,
+                },
+            );
+        } else {
+            let value = self.read(page, index);
+            query.value = value.value;
+            if query.location.memory_type == MemoryType::Stack {
+                query.value_is_pointer = value.is_pointer;
+            }
+        }
+
+        query
+    }
+
+    fn specialized_code_query(
+        &mut self,
+        _monotonic_cycle_counter: u32,
+        mut query: MemoryQuery,
+    ) -> MemoryQuery {
+        assert_eq!(query.location.memory_type, MemoryType::Code);
+        if query.rw_flag {
+            self.write(
+                query.location.page.0,
+                query.location.index.0,
+                PrimitiveValue::from_value(query.value),
+            );
+        } else {
+            query.value = self
+                .read(query.location.page.0, query.location.index.0)
+                .value;
+        }
+
+        query
+    }
+
+    fn read_code_query(
+        &self,
+        _monotonic_cycle_counter: u32,
+        mut query: MemoryQuery,
+    ) -> MemoryQuery {
+        assert_eq!(query.location.memory_type, MemoryType::Code);
+        assert!(!query.rw_flag);
+        query.value = self
+            .read(query.location.page.0, query.location.index.0)
+            .value;
+
+        query
+    }
+
+    fn start_global_frame(
+        &mut self,
+        _current_base_page: MemoryPage,
+        new_base_page: MemoryPage,
+        calldata_fat_pointer: FatPointer,
+        _timestamp: Timestamp,
+    ) {
+        // pages are created lazily on the first write
+        self.page_lifetimes
+            .start_frame(new_base_page, calldata_fat_pointer.memory_page);
+    }
+
+    fn finish_global_frame(
+        &mut self,
+        base_page: MemoryPage,
+        returndata_fat_pointer: FatPointer,
+        _timestamp: Timestamp,
+    ) {
+        for page in self
+            .page_lifetimes
+            .finish_frame(base_page, returndata_fat_pointer.memory_page)
+        {
+            self.drop_page(page);
+        }
+    }
+}
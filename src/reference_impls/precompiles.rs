@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+
+use zk_evm_abstractions::aux::{MemoryPage, Timestamp};
+use zk_evm_abstractions::precompiles::DefaultPrecompilesProcessor;
+use zk_evm_abstractions::queries::{LogQuery, MemoryQuery};
+use zk_evm_abstractions::vm::{Memory, PrecompileCyclesWitness, PrecompilesProcessor};
+use zkevm_opcode_defs::system_params::{
+    ECRECOVER_INNER_FUNCTION_PRECOMPILE_FORMAL_ADDRESS,
+    KECCAK256_ROUND_FUNCTION_PRECOMPILE_FORMAL_ADDRESS,
+    SHA256_ROUND_FUNCTION_PRECOMPILE_FORMAL_ADDRESS,
+};
+use zkevm_opcode_defs::FatPointer;
+
+use super::*;
+
+// Precompiles processor that dispatches calls by the precompile address to registered
+// handlers. Handlers are trait objects, so tests can mix the default precompiles with mocks.
+// Calls to addresses without a handler do nothing, same as `DefaultPrecompilesProcessor` does
+// for unknown addresses.
+
+pub type PrecompileCallResult =
+    Option<(Vec<MemoryQuery>, Vec<MemoryQuery>, PrecompileCyclesWitness)>;
+
+pub trait PrecompileHandler: std::fmt::Debug {
+    fn execute(
+        &mut self,
+        monotonic_cycle_counter: u32,
+        query: LogQuery,
+        memory: &mut dyn Memory,
+    ) -> PrecompileCallResult;
+
+    #[inline]
+    fn start_frame(&mut self) {}
+
+    #[inline]
+    fn finish_frame(&mut self, _panicked: bool) {}
+}
+
+// `DefaultPrecompilesProcessor` is generic over the memory, so it gets the trait object
+// through this wrapper
+struct DynMemory<'a>(&'a mut dyn Memory);
+
+impl std::fmt::Debug for DynMemory<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DynMemory").finish_non_exhaustive()
+    }
+}
+
+impl Memory for DynMemory<'_> {
+    fn execute_partial_query(
+        &mut self,
+        monotonic_cycle_counter: u32,
+        query: MemoryQuery,
+    ) -> MemoryQuery {
+        self.0.execute_partial_query(monotonic_cycle_counter, query)
+    }
+
+    fn specialized_code_query(
+        &mut self,
+        monotonic_cycle_counter: u32,
+        query: MemoryQuery,
+    ) -> MemoryQuery {
+        self.0
+            .specialized_code_query(monotonic_cycle_counter, query)
+    }
+
+    fn read_code_query(&self, monotonic_cycle_counter: u32, query: MemoryQuery) -> MemoryQuery {
+        self.0.read_code_query(monotonic_cycle_counter, query)
+    }
+
+    fn start_global_frame(
+        &mut self,
+        current_base_page: MemoryPage,
+        new_base_page: MemoryPage,
+        calldata_fat_pointer: FatPointer,
+        timestamp: Timestamp,
+    ) {
+        self.0.start_global_frame(
+            current_base_page,
+            new_base_page,
+            calldata_fat_pointer,
+            timestamp,
+        )
+    }
+
+    fn finish_global_frame(
+        &mut self,
+        base_page: MemoryPage,
+        returndata_fat_pointer: FatPointer,
+        timestamp: Timestamp,
+    ) {
+        self.0
+            .finish_global_frame(base_page, returndata_fat_pointer, timestamp)
+    }
+}
+
+/// Keccak256, sha256 or ecrecover as `DefaultPrecompilesProcessor` implements them, depending
+/// on the address of the call
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultPrecompile<const B: bool>;
+
+impl<const B: bool> PrecompileHandler for DefaultPrecompile<B> {
+    fn execute(
+        &mut self,
+        monotonic_cycle_counter: u32,
+        query: LogQuery,
+        memory: &mut dyn Memory,
+    ) -> PrecompileCallResult {
+        DefaultPrecompilesProcessor::<B>.execute_precompile(
+            monotonic_cycle_counter,
+            query,
+            &mut DynMemory(memory),
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PrecompilesRegistry {
+    precompiles: HashMap<Address, Box<dyn PrecompileHandler>>,
+}
+
+impl PrecompilesRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry with keccak256, sha256 and ecrecover at their formal addresses
+    pub fn with_default_precompiles<const B: bool>() -> Self {
+        let mut registry = Self::new();
+        for address in [
+            *KECCAK256_ROUND_FUNCTION_PRECOMPILE_FORMAL_ADDRESS,
+            *SHA256_ROUND_FUNCTION_PRECOMPILE_FORMAL_ADDRESS,
+            *ECRECOVER_INNER_FUNCTION_PRECOMPILE_FORMAL_ADDRESS,
+        ] {
+            registry.register(address, Box::new(DefaultPrecompile::<B>));
+        }
+
+        registry
+    }
+
+    /// Registers the handler, returns the one it has replaced
+    pub fn register(
+        &mut self,
+        address: Address,
+        precompile: Box<dyn PrecompileHandler>,
+    ) -> Option<Box<dyn PrecompileHandler>> {
+        self.precompiles.insert(address, precompile)
+    }
+
+    pub fn unregister(&mut self, address: &Address) -> Option<Box<dyn PrecompileHandler>> {
+        self.precompiles.remove(address)
+    }
+
+    pub fn is_registered(&self, address: &Address) -> bool {
+        self.precompiles.contains_key(address)
+    }
+}
+
+impl PrecompilesProcessor for PrecompilesRegistry {
+    fn start_frame(&mut self) {
+        for precompile in self.precompiles.values_mut() {
+            precompile.start_frame();
+        }
+    }
+
+    fn execute_precompile<M: Memory>(
+        &mut self,
+        monotonic_cycle_counter: u32,
+        query: LogQuery,
+        memory: &mut M,
+    ) -> PrecompileCallResult {
+        let precompile = self.precompiles.get_mut(&query.address)?;
+
+        precompile.execute(monotonic_cycle_counter, query, memory)
+    }
+
+    fn finish_frame(&mut self, panicked: bool) {
+        for precompile in self.precompiles.values_mut() {
+            precompile.finish_frame(panicked);
+        }
+    }
+}
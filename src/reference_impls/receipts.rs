@@ -0,0 +1,182 @@
+use super::*;
+
+use crate::reference_impls::event_decoder::*;
+use crate::reference_impls::event_sink::InMemoryEventSink;
+use zkevm_opcode_defs::sha3::{Digest, Keccak256};
+
+pub const LOGS_BLOOM_SIZE_IN_BYTES: usize = 256;
+
+// Ethereum's logs bloom: every address and topic sets 3 bits out of 2048,
+// where bit indexes are taken from the first 6 bytes of keccak256 of the input
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LogsBloom(pub [u8; LOGS_BLOOM_SIZE_IN_BYTES]);
+
+impl Default for LogsBloom {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl std::fmt::Debug for LogsBloom {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "0x")?;
+        for byte in self.0.iter() {
+            write!(f, "{:02x}", byte)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl LogsBloom {
+    pub const fn empty() -> Self {
+        Self([0u8; LOGS_BLOOM_SIZE_IN_BYTES])
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|el| *el == 0)
+    }
+
+    fn bit_positions(input: &[u8]) -> [(usize, u8); 3] {
+        let hash = Keccak256::digest(input);
+        let mut result = [(0usize, 0u8); 3];
+        for (i, dst) in result.iter_mut().enumerate() {
+            let bit_index = (((hash[2 * i] as usize) << 8) | (hash[2 * i + 1] as usize)) & 2047;
+            // bloom is big-endian 2048 bit integer
+            *dst = (
+                LOGS_BLOOM_SIZE_IN_BYTES - 1 - bit_index / 8,
+                1u8 << (bit_index % 8),
+            );
+        }
+
+        result
+    }
+
+    pub fn accrue(&mut self, input: &[u8]) {
+        for (byte_index, mask) in Self::bit_positions(input).into_iter() {
+            self.0[byte_index] |= mask;
+        }
+    }
+
+    pub fn contains_input(&self, input: &[u8]) -> bool {
+        Self::bit_positions(input)
+            .into_iter()
+            .all(|(byte_index, mask)| self.0[byte_index] & mask != 0)
+    }
+
+    pub fn accrue_log(&mut self, log: &EthereumLikeLog) {
+        self.accrue(log.address.as_bytes());
+        for topic in log.topics.iter() {
+            self.accrue(topic.as_bytes());
+        }
+    }
+
+    pub fn accrue_bloom(&mut self, other: &Self) {
+        for (dst, src) in self.0.iter_mut().zip(other.0.iter()) {
+            *dst |= *src;
+        }
+    }
+
+    pub fn from_logs(logs: &[EthereumLikeLog]) -> Self {
+        let mut bloom = Self::empty();
+        for log in logs.iter() {
+            bloom.accrue_log(log);
+        }
+
+        bloom
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TransactionStatus {
+    Success,
+    Failure,
+}
+
+impl TransactionStatus {
+    // bootloader writes 1 for successful transaction and 0 otherwise
+    pub fn from_bootloader_result(word: U256) -> Self {
+        if word == U256::one() {
+            TransactionStatus::Success
+        } else {
+            TransactionStatus::Failure
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        *self == TransactionStatus::Success
+    }
+}
+
+/// Outcome of the transaction as reported by the bootloader
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TransactionResult {
+    pub tx_number_in_block: u16,
+    pub status: TransactionStatus,
+    pub ergs_used: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransactionReceipt {
+    pub tx_number_in_block: u16,
+    pub status: TransactionStatus,
+    pub ergs_used: u32,
+    pub logs: Vec<EthereumLikeLog>,
+    pub l1_messages: Vec<L2ToL1Message>,
+    pub logs_bloom: LogsBloom,
+}
+
+/// Builds a receipt for every transaction in `results`. Events are expected to be already net
+/// of rollbacks (as `InMemoryEventSink::flatten` produces them), so reverted frames
+/// do not contribute to logs or bloom
+pub fn build_receipts(
+    results: &[TransactionResult],
+    grouped_events: Vec<TransactionEvents>,
+) -> Vec<TransactionReceipt> {
+    let mut events_per_tx: HashMap<u16, TransactionEvents> = grouped_events
+        .into_iter()
+        .map(|el| (el.tx_number_in_block, el))
+        .collect();
+
+    let mut receipts = Vec::with_capacity(results.len());
+    for result in results.iter() {
+        let (logs, l1_messages) =
+            if let Some(events) = events_per_tx.remove(&result.tx_number_in_block) {
+                let logs = events.ethereum_logs();
+                (logs, events.l1_messages)
+            } else {
+                (vec![], vec![])
+            };
+
+        let logs_bloom = LogsBloom::from_logs(&logs);
+
+        receipts.push(TransactionReceipt {
+            tx_number_in_block: result.tx_number_in_block,
+            status: result.status,
+            ergs_used: result.ergs_used,
+            logs,
+            l1_messages,
+            logs_bloom,
+        });
+    }
+
+    receipts
+}
+
+pub fn block_logs_bloom(receipts: &[TransactionReceipt]) -> LogsBloom {
+    let mut bloom = LogsBloom::empty();
+    for receipt in receipts.iter() {
+        bloom.accrue_bloom(&receipt.logs_bloom);
+    }
+
+    bloom
+}
+
+impl InMemoryEventSink {
+    pub fn into_receipts(self, results: &[TransactionResult]) -> Vec<TransactionReceipt> {
+        let (_history, grouped) = self.flatten_and_group();
+
+        build_receipts(results, grouped)
+    }
+}
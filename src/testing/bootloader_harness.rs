@@ -0,0 +1,353 @@
+use super::*;
+
+use crate::block_properties::BlockProperties;
+use crate::reference_impls::event_decoder::{EthereumLikeLog, L2ToL1Message};
+use crate::reference_impls::receipts::TransactionStatus;
+use crate::tracing::*;
+use crate::vm_state::{
+    CallStackEntry, ExecutionLimits, ExecutionStopReason, VmLocalState, VmState,
+};
+use zk_evm_abstractions::aux::MemoryPage;
+use zkevm_opcode_defs::decoding::EncodingModeProduction;
+use zkevm_opcode_defs::system_params::{ADDRESS_BOOTLOADER, VM_INITIAL_FRAME_ERGS};
+use zkevm_opcode_defs::{
+    Opcode, BOOTLOADER_BASE_PAGE, BOOTLOADER_CODE_PAGE, INITIAL_SP_ON_FAR_CALL,
+    NEW_MEMORY_PAGES_PER_FAR_CALL,
+};
+
+pub const DEFAULT_BOOTLOADER_HEAP_BOUND: u32 = 1 << 24;
+pub const DEFAULT_MAX_CYCLES: usize = 1 << 28;
+
+/// Where the bootloader expects to find transactions and where it writes results.
+/// Both offsets are in 32-byte words of the bootloader's heap
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BootloaderMemoryLayout {
+    pub transactions_offset_in_words: usize,
+    pub results_offset_in_words: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct BootloaderHarnessConfig {
+    pub bootloader_code: Vec<U256>,
+    pub layout: BootloaderMemoryLayout,
+    pub bootloader_calldata: Vec<U256>,
+    pub ergs: u32,
+    pub heap_bound: u32,
+    pub max_cycles: usize,
+}
+
+impl BootloaderHarnessConfig {
+    pub fn new(bootloader_code: Vec<U256>, layout: BootloaderMemoryLayout) -> Self {
+        Self {
+            bootloader_code,
+            layout,
+            bootloader_calldata: vec![],
+            ergs: VM_INITIAL_FRAME_ERGS,
+            heap_bound: DEFAULT_BOOTLOADER_HEAP_BOUND,
+            max_cycles: DEFAULT_MAX_CYCLES,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StorageDiffEntry {
+    pub shard_id: u8,
+    pub address: Address,
+    pub key: U256,
+    pub initial_value: U256,
+    pub final_value: U256,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransactionOutcome {
+    pub tx_number_in_block: u16,
+    pub status: TransactionStatus,
+    pub ergs_used: u32,
+    pub events: Vec<EthereumLikeLog>,
+    pub l1_messages: Vec<L2ToL1Message>,
+    pub storage_diff: Vec<StorageDiffEntry>,
+}
+
+#[derive(Debug)]
+pub struct BootloaderRunResult {
+    pub transactions: Vec<TransactionOutcome>,
+    pub cycles_used: usize,
+    pub final_local_state: VmLocalState,
+    pub final_storage: [HashMap<Address, HashMap<U256, U256>>; NUM_SHARDS],
+    pub full_storage_history: Vec<LogQuery>,
+}
+
+// Attributes ergs to the transaction that is current when they are spent, and grabs
+// bootloader's results right before it returns (as its heap is released after it)
+#[derive(Debug)]
+struct BootloaderHarnessTracer {
+    layout: BootloaderMemoryLayout,
+    num_transactions: usize,
+    previous: Option<(u16, u64)>,
+    ergs_used: BTreeMap<u16, u64>,
+    results: Option<Vec<U256>>,
+}
+
+impl BootloaderHarnessTracer {
+    fn total_ergs(state: &VmLocalState) -> u64 {
+        let callstack = &state.callstack;
+        let saved: u64 = callstack
+            .inner
+            .iter()
+            .map(|el| el.ergs_remaining as u64)
+            .sum();
+
+        saved + callstack.current.ergs_remaining as u64
+    }
+
+    fn account(&mut self, state: &VmLocalState) {
+        let total = Self::total_ergs(state);
+        if let Some((tx_number, previous_total)) = self.previous {
+            *self.ergs_used.entry(tx_number).or_default() += previous_total.saturating_sub(total);
+        }
+        self.previous = Some((state.tx_number_in_block, total));
+    }
+}
+
+impl Tracer for BootloaderHarnessTracer {
+    const CALL_BEFORE_DECODING: bool = true;
+    const CALL_BEFORE_EXECUTION: bool = true;
+
+    type SupportedMemory = SimpleMemory;
+
+    fn before_decoding(&mut self, state: VmLocalStateData<'_>, _memory: &Self::SupportedMemory) {
+        self.account(state.vm_local_state);
+    }
+    fn after_decoding(
+        &mut self,
+        _state: VmLocalStateData<'_>,
+        _data: AfterDecodingData,
+        _memory: &Self::SupportedMemory,
+    ) {
+    }
+    fn before_execution(
+        &mut self,
+        state: VmLocalStateData<'_>,
+        data: BeforeExecutionData,
+        memory: &Self::SupportedMemory,
+    ) {
+        let current = state.vm_local_state.callstack.get_current_stack();
+        let is_bootloader_frame = current.base_memory_page == MemoryPage(BOOTLOADER_BASE_PAGE)
+            && current.is_local_frame == false;
+        if is_bootloader_frame && matches!(data.opcode.variant.opcode, Opcode::Ret(_)) {
+            let heap_page = CallStackEntry::<8, EncodingModeProduction>::heap_page_from_base(
+                current.base_memory_page,
+            );
+            let start = self.layout.results_offset_in_words as u32;
+            let end = start + self.num_transactions as u32;
+            self.results = Some(memory.dump_page_content_as_u256_words(heap_page.0, start..end));
+        }
+    }
+    fn after_execution(
+        &mut self,
+        _state: VmLocalStateData<'_>,
+        _data: AfterExecutionData,
+        _memory: &Self::SupportedMemory,
+    ) {
+    }
+}
+
+pub fn bootloader_initial_context(ergs: u32, heap_bound: u32) -> CallStackEntry {
+    let bootloader_address = Address::from_low_u64_be(ADDRESS_BOOTLOADER as u64);
+
+    CallStackEntry {
+        this_address: bootloader_address,
+        msg_sender: Address::zero(),
+        code_address: bootloader_address,
+        base_memory_page: MemoryPage(BOOTLOADER_BASE_PAGE),
+        code_page: MemoryPage(BOOTLOADER_CODE_PAGE),
+        sp: INITIAL_SP_ON_FAR_CALL as u16,
+        pc: 0,
+        exception_handler_location: u16::MAX,
+        ergs_remaining: ergs,
+        this_shard_id: 0,
+        caller_shard_id: 0,
+        code_shard_id: 0,
+        is_static: false,
+        is_local_frame: false,
+        context_u128_value: 0,
+        heap_bound,
+        aux_heap_bound: heap_bound,
+    }
+}
+
+/// Pushes the root frame and moves the page counter past the bootloader's pages, so frames
+/// created by far calls never share pages with it
+pub fn push_bootloader_root_frame<
+    S: zk_evm_abstractions::vm::Storage,
+    M: zk_evm_abstractions::vm::Memory,
+    EV: zk_evm_abstractions::vm::EventSink,
+    PP: zk_evm_abstractions::vm::PrecompilesProcessor,
+    DP: zk_evm_abstractions::vm::DecommittmentProcessor,
+    WT: crate::witness_trace::VmWitnessTracer<8, EncodingModeProduction>,
+>(
+    vm: &mut VmState<S, M, EV, PP, DP, WT>,
+    context: CallStackEntry,
+) {
+    vm.push_bootloader_context(0, context);
+    if vm.local_state.memory_page_counter <= BOOTLOADER_BASE_PAGE {
+        vm.local_state.memory_page_counter = BOOTLOADER_BASE_PAGE + NEW_MEMORY_PAGES_PER_FAR_CALL;
+    }
+}
+
+fn storage_diff_per_transaction(history: &[LogQuery]) -> BTreeMap<u16, Vec<StorageDiffEntry>> {
+    // replay writes and rollbacks in the order in which they were applied
+    let mut per_tx = BTreeMap::<u16, BTreeMap<(u8, Address, U256), (U256, U256)>>::new();
+    for query in history.iter().filter(|el| el.rw_flag) {
+        let slot = (query.shard_id, query.address, query.key);
+        let value_after = if query.rollback {
+            query.read_value
+        } else {
+            query.written_value
+        };
+        per_tx
+            .entry(query.tx_number_in_block)
+            .or_default()
+            .entry(slot)
+            .or_insert((query.read_value, value_after))
+            .1 = value_after;
+    }
+
+    per_tx
+        .into_iter()
+        .map(|(tx_number, slots)| {
+            let diff = slots
+                .into_iter()
+                .filter(|(_, (initial, last))| initial != last)
+                .map(
+                    |((shard_id, address, key), (initial_value, final_value))| StorageDiffEntry {
+                        shard_id,
+                        address,
+                        key,
+                        initial_value,
+                        final_value,
+                    },
+                )
+                .collect();
+
+            (tx_number, diff)
+        })
+        .collect()
+}
+
+/// Lays out transactions in the bootloader's heap, runs bootloader from the kernel context until
+/// the callstack is empty and splits the results per transaction. Transaction `i` is expected
+/// to run with `tx_number_in_block == i`, and its result is the `i`-th word of the results area
+pub fn run_bootloader<const B: bool>(
+    tools: BasicTestingTools<B>,
+    block_properties: BlockProperties,
+    config: &BootloaderHarnessConfig,
+    transactions: &[Vec<U256>],
+) -> anyhow::Result<BootloaderRunResult> {
+    let BasicTestingTools::<B> {
+        storage,
+        mut memory,
+        event_sink,
+        precompiles_processor,
+        decommittment_processor,
+        witness_tracer,
+    } = tools;
+
+    memory.populate_code(vec![(BOOTLOADER_CODE_PAGE, config.bootloader_code.clone())]);
+    if config.bootloader_calldata.is_empty() == false {
+        memory.polulate_bootloaders_calldata(config.bootloader_calldata.clone());
+    }
+
+    let mut vm: VmState<_, _, _, _, _, _> = VmState::empty_state(
+        storage,
+        memory,
+        event_sink,
+        precompiles_processor,
+        decommittment_processor,
+        witness_tracer,
+        block_properties,
+    );
+
+    let initial_context = bootloader_initial_context(config.ergs, config.heap_bound);
+    push_bootloader_root_frame(&mut vm, initial_context);
+
+    // heap of the bootloader was created when we pushed the context
+    let mut heap = vec![U256::zero(); config.layout.transactions_offset_in_words];
+    for tx in transactions.iter() {
+        heap.extend_from_slice(tx);
+    }
+    let results_end = config.layout.results_offset_in_words + transactions.len();
+    if heap.len() < results_end {
+        heap.resize(results_end, U256::zero());
+    }
+    vm.memory.populate_heap(heap);
+
+    let mut tracer = BootloaderHarnessTracer {
+        layout: config.layout,
+        num_transactions: transactions.len(),
+        previous: None,
+        ergs_used: BTreeMap::new(),
+        results: None,
+    };
+
+    vm.set_execution_limits(ExecutionLimits {
+        max_cycles: Some(config.max_cycles as u64),
+        ..ExecutionLimits::default()
+    });
+    if let ExecutionStopReason::LimitExceeded(reason) = vm.run(&mut tracer)? {
+        anyhow::bail!("bootloader did not finish: {}", reason);
+    }
+    let cycles_used = vm.watchdog.cycles_executed() as usize;
+    tracer.account(&vm.local_state);
+
+    let VmState {
+        local_state,
+        storage,
+        event_sink,
+        ..
+    } = vm;
+
+    let final_storage = storage.inner.clone();
+    let (full_storage_history, _) = storage.flatten_and_net_history();
+    let mut storage_diffs = storage_diff_per_transaction(&full_storage_history);
+    let (_, grouped_events) = event_sink.flatten_and_group();
+    let mut events_per_tx: HashMap<u16, _> = grouped_events
+        .into_iter()
+        .map(|el| (el.tx_number_in_block, el))
+        .collect();
+    let Some(results) = tracer.results else {
+        anyhow::bail!("bootloader has finished without returning from its root frame");
+    };
+
+    let mut outcomes = Vec::with_capacity(transactions.len());
+    for (i, result) in results.into_iter().enumerate() {
+        let tx_number_in_block = i as u16;
+        let (events, l1_messages) = match events_per_tx.remove(&tx_number_in_block) {
+            Some(events) => (events.ethereum_logs(), events.l1_messages),
+            None => (vec![], vec![]),
+        };
+
+        outcomes.push(TransactionOutcome {
+            tx_number_in_block,
+            status: TransactionStatus::from_bootloader_result(result),
+            ergs_used: tracer
+                .ergs_used
+                .get(&tx_number_in_block)
+                .copied()
+                .unwrap_or(0) as u32,
+            events,
+            l1_messages,
+            storage_diff: storage_diffs
+                .remove(&tx_number_in_block)
+                .unwrap_or_default(),
+        });
+    }
+
+    Ok(BootloaderRunResult {
+        transactions: outcomes,
+        cycles_used,
+        final_local_state: local_state,
+        final_storage,
+        full_storage_history,
+    })
+}
@@ -0,0 +1,2059 @@
+use super::*;
+
+use crate::block_properties::BlockProperties;
+use crate::flags::Flags;
+use crate::reference_impls::decommitter::hash_bytecode_words;
+use crate::testing::bootloader_harness::{bootloader_initial_context, push_bootloader_root_frame};
+use crate::tracing::*;
+use crate::vm_state::{CallStackEntry, PrimitiveValue, VmState};
+use crate::witness_trace::{DummyTracer, VmWitnessTracer};
+use lazy_static::lazy_static;
+use zk_evm_abstractions::aux::MemoryPage;
+use zkevm_opcode_defs::decoding::*;
+use zkevm_opcode_defs::system_params::{ADDRESS_BOOTLOADER, DEPLOYER_SYSTEM_CONTRACT_ADDRESS};
+use zkevm_opcode_defs::*;
+
+// Table-driven conformance vectors: a short program is executed from the bootloader-like
+// root frame with the given initial state, and the final state is compared against
+// the expectation. Programs are kept as raw 64-bit encodings, so other implementations
+// can consume vectors without depending on the assembler or on this crate's types.
+
+pub const CONFORMANCE_DEFAULT_ERGS: u32 = 1_000_000;
+
+// low bits of the opcode encoding select the variant, then go 3 bits of condition
+pub(crate) const VARIANT_BITS: u64 = 11;
+const CONDITION_BITS: u64 = 3;
+
+lazy_static! {
+    // (opcode, flags, src0 is immediate) -> first variant index with register operands otherwise
+    static ref REGISTER_ONLY_VARIANTS: HashMap<(Opcode, [bool; 2], bool), u64> = {
+        let mut variants = HashMap::new();
+        for idx in 0..(1u64 << VARIANT_BITS) {
+            let (decoded, _) =
+                EncodingModeProduction::parse_preliminary_variant_and_absolute_number(idx);
+            let variant = decoded.variant;
+            let dst0_is_reg = matches!(
+                variant.dst0_operand_type,
+                Operand::RegOnly
+                    | Operand::Full(ImmMemHandlerFlags::UseRegOnly)
+                    | Operand::RegOrImm(RegOrImmFlags::UseRegOnly)
+            );
+            let src0_is_imm = match variant.src0_operand_type {
+                Operand::Full(ImmMemHandlerFlags::UseImm16Only)
+                | Operand::RegOrImm(RegOrImmFlags::UseImm16Only) => true,
+                Operand::RegOnly
+                | Operand::Full(ImmMemHandlerFlags::UseRegOnly)
+                | Operand::RegOrImm(RegOrImmFlags::UseRegOnly) => false,
+                _ => continue,
+            };
+            if dst0_is_reg {
+                variants
+                    .entry((variant.opcode, variant.flags, src0_is_imm))
+                    .or_insert(idx);
+            }
+        }
+
+        variants
+    };
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Instruction {
+    pub opcode: Opcode,
+    pub flags: [bool; 2],
+    pub condition: Condition,
+    pub src0_is_imm: bool,
+    pub src0: u8,
+    pub src1: u8,
+    pub dst0: u8,
+    pub dst1: u8,
+    pub imm0: u16,
+    pub imm1: u16,
+}
+
+impl Instruction {
+    pub fn new(opcode: Opcode) -> Self {
+        Self {
+            opcode,
+            flags: [false; 2],
+            condition: Condition::Always,
+            src0_is_imm: false,
+            src0: 0,
+            src1: 0,
+            dst0: 0,
+            dst1: 0,
+            imm0: 0,
+            imm1: 0,
+        }
+    }
+
+    pub fn src0(mut self, reg: u8) -> Self {
+        self.src0 = reg;
+        self
+    }
+
+    /// Takes src0 from imm0
+    pub fn src0_imm(mut self, imm: u16) -> Self {
+        self.src0_is_imm = true;
+        self.imm0 = imm;
+        self
+    }
+
+    pub fn src1(mut self, reg: u8) -> Self {
+        self.src1 = reg;
+        self
+    }
+
+    pub fn dst0(mut self, reg: u8) -> Self {
+        self.dst0 = reg;
+        self
+    }
+
+    pub fn dst1(mut self, reg: u8) -> Self {
+        self.dst1 = reg;
+        self
+    }
+
+    pub fn imm0(mut self, imm: u16) -> Self {
+        self.imm0 = imm;
+        self
+    }
+
+    pub fn imm1(mut self, imm: u16) -> Self {
+        self.imm1 = imm;
+        self
+    }
+
+    pub fn flag(mut self, idx: usize) -> Self {
+        self.flags[idx] = true;
+        self
+    }
+
+    pub fn condition(mut self, condition: Condition) -> Self {
+        self.condition = condition;
+        self
+    }
+
+    fn variant_index(&self) -> u64 {
+        *REGISTER_ONLY_VARIANTS
+            .get(&(self.opcode, self.flags, self.src0_is_imm))
+            .unwrap_or_else(|| panic!("there is no register-only encoding for {:?}", self))
+    }
+
+    fn condition_bits(&self, variant_index: u64) -> u64 {
+        for condition in 0..(1u64 << CONDITION_BITS) {
+            let (decoded, _) =
+                EncodingModeProduction::parse_preliminary_variant_and_absolute_number(
+                    variant_index | (condition << VARIANT_BITS),
+                );
+            if std::mem::discriminant(&decoded.condition) == std::mem::discriminant(&self.condition)
+            {
+                return condition;
+            }
+        }
+
+        unreachable!("all conditions are encodable");
+    }
+
+    pub fn ergs_price(&self) -> u32 {
+        let (_, idx) = EncodingModeProduction::parse_preliminary_variant_and_absolute_number(
+            self.variant_index(),
+        );
+
+        OPCODES_PRICES[idx.into_usize()] as u32
+    }
+
+    pub fn encode(&self) -> u64 {
+        let variant_index = self.variant_index();
+        let condition = self.condition_bits(variant_index);
+        let encoding = variant_index
+            | (condition << VARIANT_BITS)
+            | ((self.src0 as u64) << 16)
+            | ((self.src1 as u64) << 20)
+            | ((self.dst0 as u64) << 24)
+            | ((self.dst1 as u64) << 28)
+            | ((self.imm0 as u64) << 32)
+            | ((self.imm1 as u64) << 48);
+
+        // make sure that decoder sees exactly what we wanted
+        let (decoded, _) =
+            EncodingModeProduction::parse_preliminary_variant_and_absolute_number(encoding);
+        assert!(
+            decoded.variant.opcode == self.opcode
+                && decoded.src0_reg_idx == self.src0
+                && decoded.src1_reg_idx == self.src1
+                && decoded.dst0_reg_idx == self.dst0
+                && decoded.dst1_reg_idx == self.dst1
+                && decoded.imm_0.as_u64() == self.imm0 as u64
+                && decoded.imm_1.as_u64() == self.imm1 as u64,
+            "encoding 0x{:016x} of {:?} decodes into {}",
+            encoding,
+            self,
+            decoded
+        );
+
+        encoding
+    }
+}
+
+/// Packs opcodes into code words, the first opcode of the word is in its highest 64 bits
+pub fn assemble(program: &[u64]) -> Vec<U256> {
+    program
+        .chunks(4)
+        .map(|chunk| {
+            let mut word = U256::zero();
+            for (i, opcode) in chunk.iter().enumerate() {
+                word.0[3 - i] = *opcode;
+            }
+
+            word
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug)]
+pub struct InitialState {
+    pub this_address: Address,
+    pub msg_sender: Address,
+    pub ergs: u32,
+    pub heap_bound: u32,
+    pub aux_heap_bound: u32,
+    pub is_static: bool,
+    pub context_u128_value: u128,
+    pub current_ergs_per_pubdata_byte: u32,
+    pub registers: Vec<(u8, PrimitiveValue)>,
+    pub flags: Flags,
+    // sparse, word index -> value
+    pub heap: Vec<(u32, U256)>,
+    pub aux_heap: Vec<(u32, U256)>,
+    pub storage: Vec<(u8, Address, U256, U256)>,
+    // every bytecode is known to the decommitter under its versioned hash
+    pub bytecodes: Vec<Vec<U256>>,
+    pub default_aa_bytecode: Option<Vec<U256>>,
+}
+
+impl Default for InitialState {
+    fn default() -> Self {
+        Self {
+            this_address: Address::from_low_u64_be(ADDRESS_BOOTLOADER as u64),
+            msg_sender: Address::zero(),
+            ergs: CONFORMANCE_DEFAULT_ERGS,
+            heap_bound: 0,
+            aux_heap_bound: 0,
+            is_static: false,
+            context_u128_value: 0,
+            current_ergs_per_pubdata_byte: 0,
+            registers: vec![],
+            flags: Flags::empty(),
+            heap: vec![],
+            aux_heap: vec![],
+            storage: vec![],
+            bytecodes: vec![],
+            default_aa_bytecode: None,
+        }
+    }
+}
+
+/// Only filled fields are checked, except `panicked` and `execution_has_ended`
+#[derive(Clone, Debug, Default)]
+pub struct ExpectedState {
+    pub registers: Vec<(u8, PrimitiveValue)>,
+    pub flags: Option<Flags>,
+    pub pc: Option<u16>,
+    pub sp: Option<u16>,
+    pub ergs_remaining: Option<u32>,
+    pub heap_bound: Option<u32>,
+    pub aux_heap_bound: Option<u32>,
+    pub callstack_depth: Option<usize>,
+    pub context_u128_register: Option<u128>,
+    pub current_ergs_per_pubdata_byte: Option<u32>,
+    pub tx_number_in_block: Option<u16>,
+    pub panicked: bool,
+    pub execution_has_ended: bool,
+    pub heap: Vec<(u32, U256)>,
+    pub aux_heap: Vec<(u32, U256)>,
+    pub storage: Vec<(u8, Address, U256, U256)>,
+    pub events: Option<Vec<(Address, U256, U256)>>,
+    pub l1_messages: Option<Vec<(Address, U256, U256)>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ConformanceVector {
+    pub name: String,
+    pub program: Vec<u64>,
+    // number of cycles to run, pending exception is always resolved after it
+    pub cycles: usize,
+    pub initial: InitialState,
+    pub expected: ExpectedState,
+}
+
+#[derive(Clone, Debug)]
+pub struct FinalState {
+    pub registers: [PrimitiveValue; REGISTERS_COUNT],
+    pub flags: Flags,
+    pub pc: u16,
+    pub sp: u16,
+    pub ergs_remaining: u32,
+    pub heap_bound: u32,
+    pub aux_heap_bound: u32,
+    pub callstack_depth: usize,
+    pub context_u128_register: u128,
+    pub current_ergs_per_pubdata_byte: u32,
+    pub tx_number_in_block: u16,
+    pub panicked: bool,
+    pub execution_has_ended: bool,
+    pub cycles_used: usize,
+    // heaps of the frame that is current at the end
+    pub heap: Vec<U256>,
+    pub aux_heap: Vec<U256>,
+    pub storage: [HashMap<Address, HashMap<U256, U256>>; NUM_SHARDS],
+    pub events: Vec<EventMessage>,
+    pub l1_messages: Vec<EventMessage>,
+}
+
+// Notes if any panic (explicit or due to exception) was executed
+#[derive(Debug, Default)]
+struct ConformanceTracer {
+    panicked: bool,
+}
+
+impl Tracer for ConformanceTracer {
+    const CALL_AFTER_DECODING: bool = true;
+
+    type SupportedMemory = SimpleMemory;
+
+    fn before_decoding(&mut self, _state: VmLocalStateData<'_>, _memory: &Self::SupportedMemory) {}
+    fn after_decoding(
+        &mut self,
+        _state: VmLocalStateData<'_>,
+        data: AfterDecodingData,
+        _memory: &Self::SupportedMemory,
+    ) {
+        if data.did_skip_cycle == false
+            && data.opcode_masked.variant.opcode == Opcode::Ret(RetOpcode::Panic)
+        {
+            self.panicked = true;
+        }
+    }
+    fn before_execution(
+        &mut self,
+        _state: VmLocalStateData<'_>,
+        _data: BeforeExecutionData,
+        _memory: &Self::SupportedMemory,
+    ) {
+    }
+    fn after_execution(
+        &mut self,
+        _state: VmLocalStateData<'_>,
+        _data: AfterExecutionData,
+        _memory: &Self::SupportedMemory,
+    ) {
+    }
+}
+
+fn words_to_bytes(words: &[U256]) -> Vec<[u8; 32]> {
+    words
+        .iter()
+        .map(|el| {
+            let mut buffer = [0u8; 32];
+            el.to_big_endian(&mut buffer);
+            buffer
+        })
+        .collect()
+}
+
+pub fn versioned_hash_of_words(words: &[U256]) -> U256 {
+    hash_bytecode_words(&words_to_bytes(words)).expect("conformance bytecode must be hashable")
+}
+
+fn write_sparse(page: &mut Vec<U256>, values: &[(u32, U256)]) {
+    for (index, value) in values.iter() {
+        let index = *index as usize;
+        if page.len() <= index {
+            page.resize(index + 1, U256::zero());
+        }
+        page[index] = *value;
+    }
+}
+
+pub(crate) type ConformanceVm<WT> = VmState<
+    InMemoryStorage,
+    SimpleMemory,
+    InMemoryEventSink,
+    PrecompilesRegistry,
+    SimpleDecommitter<true>,
+    WT,
+>;
+
+pub(crate) fn conformance_bytecodes(initial: &InitialState) -> Vec<(U256, Vec<U256>)> {
+    let mut bytecodes = BTreeMap::new();
+    for words in initial
+        .bytecodes
+        .iter()
+        .chain(initial.default_aa_bytecode.iter())
+    {
+        bytecodes.insert(versioned_hash_of_words(words), words.clone());
+    }
+
+    bytecodes.into_iter().collect()
+}
+
+pub(crate) fn conformance_block_properties(initial: &InitialState) -> BlockProperties {
+    BlockProperties {
+        default_aa_code_hash: initial
+            .default_aa_bytecode
+            .as_ref()
+            .map(|el| versioned_hash_of_words(el))
+            .unwrap_or(U256::zero()),
+        zkporter_is_available: false,
+    }
+}
+
+/// Creates VM with the vector's root frame on top of the callstack, ready to run the program
+pub(crate) fn prepare_conformance_vm<WT: VmWitnessTracer<8, EncodingModeProduction>>(
+    vector: &ConformanceVector,
+    witness_tracer: WT,
+) -> ConformanceVm<WT> {
+    prepare_conformance_vm_with_memory(vector, SimpleMemory::new(), witness_tracer)
+}
+
+/// Same as `prepare_conformance_vm`, but places the program into the given memory, so callers
+/// that create many short-living VMs can avoid preallocating page pools
+pub(crate) fn prepare_conformance_vm_with_memory<WT: VmWitnessTracer<8, EncodingModeProduction>>(
+    vector: &ConformanceVector,
+    mut memory: SimpleMemory,
+    witness_tracer: WT,
+) -> ConformanceVm<WT> {
+    let initial = &vector.initial;
+
+    let mut storage = InMemoryStorage::new();
+    storage.populate(initial.storage.clone());
+    memory.populate_code(vec![(BOOTLOADER_CODE_PAGE, assemble(&vector.program))]);
+
+    // decommitter must report witnesses, otherwise decommittments never reach the tracer
+    let mut decommittment_processor = SimpleDecommitter::<true>::new();
+    decommittment_processor.populate(conformance_bytecodes(initial));
+
+    let mut vm: ConformanceVm<WT> = VmState::empty_state(
+        storage,
+        memory,
+        InMemoryEventSink::new(),
+        PrecompilesRegistry::with_default_precompiles::<false>(),
+        decommittment_processor,
+        witness_tracer,
+        conformance_block_properties(initial),
+    );
+
+    let mut context = bootloader_initial_context(initial.ergs, initial.heap_bound);
+    context.this_address = initial.this_address;
+    context.code_address = initial.this_address;
+    context.msg_sender = initial.msg_sender;
+    context.aux_heap_bound = initial.aux_heap_bound;
+    context.is_static = initial.is_static;
+    context.context_u128_value = initial.context_u128_value;
+    push_bootloader_root_frame(&mut vm, context);
+
+    vm.local_state.current_ergs_per_pubdata_byte = initial.current_ergs_per_pubdata_byte;
+    vm.local_state.flags = initial.flags;
+    for (reg, value) in initial.registers.iter() {
+        assert!(*reg >= 1 && (*reg as usize) <= REGISTERS_COUNT);
+        vm.local_state.registers[*reg as usize - 1] = *value;
+    }
+    {
+        let ((_, heap), (_, aux_heap)) = vm.memory.heaps.last_mut().unwrap();
+        write_sparse(heap, &initial.heap);
+        write_sparse(aux_heap, &initial.aux_heap);
+    }
+
+    vm
+}
+
+/// Runs up to `cycles` cycles and resolves pending exception. Returns number of cycles
+/// actually used and whether any panic was executed
+pub(crate) fn run_conformance_cycles<WT: VmWitnessTracer<8, EncodingModeProduction>>(
+    vm: &mut ConformanceVm<WT>,
+    cycles: usize,
+) -> anyhow::Result<(usize, bool)> {
+    let mut tracer = ConformanceTracer::default();
+    let mut cycles_used = 0;
+    while cycles_used < cycles && vm.execution_has_ended() == false {
+        vm.cycle(&mut tracer)?;
+        cycles_used += 1;
+    }
+    // shorthand panic is only visible on the next cycle
+    while vm.local_state.pending_exception && vm.execution_has_ended() == false {
+        vm.cycle(&mut tracer)?;
+        cycles_used += 1;
+    }
+
+    Ok((cycles_used, tracer.panicked))
+}
+
+pub fn run_conformance_vector(vector: &ConformanceVector) -> anyhow::Result<FinalState> {
+    let mut vm = prepare_conformance_vm(vector, DummyTracer);
+    let (cycles_used, panicked) = run_conformance_cycles(&mut vm, vector.cycles)?;
+
+    let VmState {
+        local_state,
+        storage,
+        memory,
+        event_sink,
+        ..
+    } = vm;
+
+    let current = local_state.callstack.get_current_stack();
+    let ((_, heap), (_, aux_heap)) = memory.heaps.last().unwrap();
+    let (_, events, l1_messages) = event_sink.flatten();
+
+    Ok(FinalState {
+        registers: local_state.registers,
+        flags: local_state.flags,
+        pc: current.pc,
+        sp: current.sp,
+        ergs_remaining: current.ergs_remaining,
+        heap_bound: current.heap_bound,
+        aux_heap_bound: current.aux_heap_bound,
+        callstack_depth: local_state.callstack.depth(),
+        context_u128_register: local_state.context_u128_register,
+        current_ergs_per_pubdata_byte: local_state.current_ergs_per_pubdata_byte,
+        tx_number_in_block: local_state.tx_number_in_block,
+        panicked,
+        execution_has_ended: local_state.execution_has_ended(),
+        cycles_used,
+        heap: heap.clone(),
+        aux_heap: aux_heap.clone(),
+        storage: storage.inner.clone(),
+        events,
+        l1_messages,
+    })
+}
+
+fn compare<T: PartialEq + std::fmt::Debug>(
+    mismatches: &mut Vec<String>,
+    what: &str,
+    expected: &T,
+    actual: &T,
+) {
+    if expected != actual {
+        mismatches.push(format!(
+            "{}: expected {:?}, got {:?}",
+            what, expected, actual
+        ));
+    }
+}
+
+fn compare_if_set<T: PartialEq + std::fmt::Debug>(
+    mismatches: &mut Vec<String>,
+    what: &str,
+    expected: &Option<T>,
+    actual: &T,
+) {
+    if let Some(expected) = expected.as_ref() {
+        compare(mismatches, what, expected, actual);
+    }
+}
+
+fn messages_content(messages: &[EventMessage]) -> Vec<(Address, U256, U256)> {
+    messages
+        .iter()
+        .map(|el| (el.address, el.key, el.value))
+        .collect()
+}
+
+impl ExpectedState {
+    pub fn check(&self, state: &FinalState) -> anyhow::Result<()> {
+        let mut mismatches = vec![];
+
+        compare(&mut mismatches, "panicked", &self.panicked, &state.panicked);
+        compare(
+            &mut mismatches,
+            "execution has ended",
+            &self.execution_has_ended,
+            &state.execution_has_ended,
+        );
+        for (reg, value) in self.registers.iter() {
+            compare(
+                &mut mismatches,
+                &format!("r{}", reg),
+                value,
+                &state.registers[*reg as usize - 1],
+            );
+        }
+        compare_if_set(&mut mismatches, "flags", &self.flags, &state.flags);
+        compare_if_set(&mut mismatches, "pc", &self.pc, &state.pc);
+        compare_if_set(&mut mismatches, "sp", &self.sp, &state.sp);
+        compare_if_set(
+            &mut mismatches,
+            "ergs remaining",
+            &self.ergs_remaining,
+            &state.ergs_remaining,
+        );
+        compare_if_set(
+            &mut mismatches,
+            "heap bound",
+            &self.heap_bound,
+            &state.heap_bound,
+        );
+        compare_if_set(
+            &mut mismatches,
+            "aux heap bound",
+            &self.aux_heap_bound,
+            &state.aux_heap_bound,
+        );
+        compare_if_set(
+            &mut mismatches,
+            "callstack depth",
+            &self.callstack_depth,
+            &state.callstack_depth,
+        );
+        compare_if_set(
+            &mut mismatches,
+            "context u128 register",
+            &self.context_u128_register,
+            &state.context_u128_register,
+        );
+        compare_if_set(
+            &mut mismatches,
+            "ergs per pubdata byte",
+            &self.current_ergs_per_pubdata_byte,
+            &state.current_ergs_per_pubdata_byte,
+        );
+        compare_if_set(
+            &mut mismatches,
+            "tx number in block",
+            &self.tx_number_in_block,
+            &state.tx_number_in_block,
+        );
+        for (index, value) in self.heap.iter() {
+            let actual = state.heap.get(*index as usize).copied().unwrap_or_default();
+            compare(&mut mismatches, &format!("heap[{}]", index), value, &actual);
+        }
+        for (index, value) in self.aux_heap.iter() {
+            let actual = state
+                .aux_heap
+                .get(*index as usize)
+                .copied()
+                .unwrap_or_default();
+            compare(
+                &mut mismatches,
+                &format!("aux heap[{}]", index),
+                value,
+                &actual,
+            );
+        }
+        for (shard_id, address, key, value) in self.storage.iter() {
+            let actual = state.storage[*shard_id as usize]
+                .get(address)
+                .and_then(|el| el.get(key))
+                .copied()
+                .unwrap_or_default();
+            compare(
+                &mut mismatches,
+                &format!("storage[{}][{:?}][{}]", shard_id, address, key),
+                value,
+                &actual,
+            );
+        }
+        compare_if_set(
+            &mut mismatches,
+            "events",
+            &self.events,
+            &messages_content(&state.events),
+        );
+        compare_if_set(
+            &mut mismatches,
+            "l1 messages",
+            &self.l1_messages,
+            &messages_content(&state.l1_messages),
+        );
+
+        if mismatches.is_empty() == false {
+            anyhow::bail!("{}", mismatches.join("\n"));
+        }
+
+        Ok(())
+    }
+}
+
+impl ConformanceVector {
+    pub fn run_and_check(&self) -> anyhow::Result<FinalState> {
+        let state = run_conformance_vector(self)?;
+        self.expected
+            .check(&state)
+            .map_err(|e| anyhow::anyhow!("vector `{}` failed:\n{}", self.name, e))?;
+
+        Ok(state)
+    }
+}
+
+// Vectors
+
+fn int(value: u64) -> PrimitiveValue {
+    PrimitiveValue {
+        value: U256::from(value),
+        is_pointer: false,
+    }
+}
+
+fn int_u256(value: U256) -> PrimitiveValue {
+    PrimitiveValue {
+        value,
+        is_pointer: false,
+    }
+}
+
+fn ptr(pointer: FatPointer) -> PrimitiveValue {
+    PrimitiveValue {
+        value: pointer.to_u256(),
+        is_pointer: true,
+    }
+}
+
+fn fat_pointer(memory_page: u32, start: u32, length: u32, offset: u32) -> FatPointer {
+    FatPointer {
+        offset,
+        memory_page,
+        start,
+        length,
+    }
+}
+
+fn flags(lt_or_overflow: bool, eq: bool, gt: bool) -> Flags {
+    Flags {
+        overflow_or_less_than_flag: lt_or_overflow,
+        equality_flag: eq,
+        greater_than_flag: gt,
+    }
+}
+
+fn ergs_after(executed: &[Instruction]) -> u32 {
+    CONFORMANCE_DEFAULT_ERGS - executed.iter().map(|el| el.ergs_price()).sum::<u32>()
+}
+
+fn vector(
+    name: &str,
+    program: &[Instruction],
+    initial: InitialState,
+    expected: ExpectedState,
+) -> ConformanceVector {
+    ConformanceVector {
+        name: name.to_owned(),
+        program: program.iter().map(|el| el.encode()).collect(),
+        cycles: program.len(),
+        initial,
+        expected,
+    }
+}
+
+fn panicking_vector(
+    name: &str,
+    program: &[Instruction],
+    initial: InitialState,
+) -> ConformanceVector {
+    vector(
+        name,
+        program,
+        initial,
+        ExpectedState {
+            panicked: true,
+            execution_has_ended: true,
+            ..ExpectedState::default()
+        },
+    )
+}
+
+fn user_address() -> Address {
+    Address::from_low_u64_be(1 << 16)
+}
+
+fn add() -> Instruction {
+    Instruction::new(Opcode::Add(AddOpcode::Add))
+}
+
+fn sub() -> Instruction {
+    Instruction::new(Opcode::Sub(SubOpcode::Sub))
+}
+
+fn context(variant: ContextOpcode) -> Instruction {
+    Instruction::new(Opcode::Context(variant))
+}
+
+fn uma(variant: UMAOpcode) -> Instruction {
+    Instruction::new(Opcode::UMA(variant))
+}
+
+fn log(variant: LogOpcode) -> Instruction {
+    Instruction::new(Opcode::Log(variant))
+}
+
+fn ret(variant: RetOpcode) -> Instruction {
+    Instruction::new(Opcode::Ret(variant))
+}
+
+fn arithmetic_vectors() -> Vec<ConformanceVector> {
+    let mut vectors = vec![];
+
+    let nop = Instruction::new(Opcode::Nop(NopOpcode));
+    vectors.push(vector(
+        "nop",
+        &[nop],
+        InitialState::default(),
+        ExpectedState {
+            pc: Some(1),
+            sp: Some(INITIAL_SP_ON_FAR_CALL as u16),
+            ergs_remaining: Some(ergs_after(&[nop])),
+            flags: Some(Flags::empty()),
+            callstack_depth: Some(1),
+            ..ExpectedState::default()
+        },
+    ));
+
+    let op = add().src0(1).src1(2).dst0(3);
+    vectors.push(vector(
+        "add_reg_reg",
+        &[op],
+        InitialState {
+            registers: vec![(1, int(2)), (2, int(3))],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(3, int(5))],
+            flags: Some(Flags::empty()),
+            pc: Some(1),
+            ergs_remaining: Some(ergs_after(&[op])),
+            ..ExpectedState::default()
+        },
+    ));
+
+    let op = add().src0_imm(7).src1(1).dst0(2);
+    vectors.push(vector(
+        "add_imm_reg",
+        &[op],
+        InitialState {
+            registers: vec![(1, int(1))],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(2, int(8))],
+            ergs_remaining: Some(ergs_after(&[op])),
+            ..ExpectedState::default()
+        },
+    ));
+
+    let op = add().src0(1).src1(2).dst0(3).flag(SET_FLAGS_FLAG_IDX);
+    vectors.push(vector(
+        "add_set_flags_overflow",
+        &[op],
+        InitialState {
+            registers: vec![(1, int_u256(U256::MAX)), (2, int(1))],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(3, int(0))],
+            flags: Some(flags(true, true, false)),
+            ..ExpectedState::default()
+        },
+    ));
+    vectors.push(vector(
+        "add_set_flags_gt",
+        &[op],
+        InitialState {
+            registers: vec![(1, int(1)), (2, int(1))],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(3, int(2))],
+            flags: Some(flags(false, false, true)),
+            ..ExpectedState::default()
+        },
+    ));
+    // flags are kept as is if not requested
+    vectors.push(vector(
+        "add_keeps_flags",
+        &[add().src0(1).src1(2).dst0(3)],
+        InitialState {
+            registers: vec![(1, int_u256(U256::MAX)), (2, int(1))],
+            flags: flags(false, false, true),
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(3, int(0))],
+            flags: Some(flags(false, false, true)),
+            ..ExpectedState::default()
+        },
+    ));
+
+    let op = sub().src0(1).src1(2).dst0(3).flag(SET_FLAGS_FLAG_IDX);
+    vectors.push(vector(
+        "sub_set_flags_underflow",
+        &[op],
+        InitialState {
+            registers: vec![(1, int(1)), (2, int(2))],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(3, int_u256(U256::MAX))],
+            flags: Some(flags(true, false, false)),
+            ..ExpectedState::default()
+        },
+    ));
+    vectors.push(vector(
+        "sub_set_flags_equal",
+        &[op],
+        InitialState {
+            registers: vec![(1, int(5)), (2, int(5))],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(3, int(0))],
+            flags: Some(flags(false, true, false)),
+            ..ExpectedState::default()
+        },
+    ));
+
+    let op = Instruction::new(Opcode::Sub(SubOpcode::Sub))
+        .src0(1)
+        .src1(2)
+        .dst0(3)
+        .flag(SWAP_OPERANDS_FLAG_IDX_FOR_ARITH_OPCODES);
+    vectors.push(vector(
+        "sub_swapped_operands",
+        &[op],
+        InitialState {
+            registers: vec![(1, int(2)), (2, int(5))],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(3, int(3))],
+            flags: Some(Flags::empty()),
+            ..ExpectedState::default()
+        },
+    ));
+
+    let op = Instruction::new(Opcode::Mul(MulOpcode))
+        .src0(1)
+        .src1(2)
+        .dst0(3)
+        .dst1(4)
+        .flag(SET_FLAGS_FLAG_IDX);
+    vectors.push(vector(
+        "mul_set_flags_overflow",
+        &[op],
+        InitialState {
+            registers: vec![(1, int_u256(U256::MAX)), (2, int(2))],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(3, int_u256(U256::MAX - U256::one())), (4, int(1))],
+            flags: Some(flags(true, false, false)),
+            ..ExpectedState::default()
+        },
+    ));
+
+    let op = Instruction::new(Opcode::Div(DivOpcode))
+        .src0(1)
+        .src1(2)
+        .dst0(3)
+        .dst1(4)
+        .flag(SET_FLAGS_FLAG_IDX);
+    vectors.push(vector(
+        "div_with_remainder",
+        &[op],
+        InitialState {
+            registers: vec![(1, int(7)), (2, int(2))],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(3, int(3)), (4, int(1))],
+            flags: Some(flags(false, false, false)),
+            ..ExpectedState::default()
+        },
+    ));
+    vectors.push(vector(
+        "div_by_zero",
+        &[op],
+        InitialState {
+            registers: vec![(1, int(7)), (2, int(0)), (3, int(11)), (4, int(12))],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(3, int(0)), (4, int(0))],
+            flags: Some(flags(true, false, false)),
+            ..ExpectedState::default()
+        },
+    ));
+
+    let shift = |variant: ShiftOpcode| {
+        Instruction::new(Opcode::Shift(variant))
+            .src0(1)
+            .src1(2)
+            .dst0(3)
+            .flag(SET_FLAGS_FLAG_IDX)
+    };
+    let top_bit = U256::one() << 255;
+    let shift_cases = [
+        ("shl", ShiftOpcode::Shl, U256::one(), 255u64, top_bit, false),
+        (
+            "shr_to_zero",
+            ShiftOpcode::Shr,
+            U256::one(),
+            1,
+            U256::zero(),
+            true,
+        ),
+        (
+            "rol",
+            ShiftOpcode::Rol,
+            top_bit | U256::one(),
+            1,
+            U256::from(3u64),
+            false,
+        ),
+        ("ror", ShiftOpcode::Ror, U256::one(), 1, top_bit, false),
+    ];
+    for (name, variant, value, shift_by, result, eq) in shift_cases.into_iter() {
+        vectors.push(vector(
+            name,
+            &[shift(variant)],
+            InitialState {
+                registers: vec![(1, int_u256(value)), (2, int(shift_by))],
+                ..InitialState::default()
+            },
+            ExpectedState {
+                registers: vec![(3, int_u256(result))],
+                flags: Some(flags(false, eq, false)),
+                ..ExpectedState::default()
+            },
+        ));
+    }
+
+    let binop_cases = [
+        ("xor", BinopOpcode::Xor, 0b0110u64),
+        ("and", BinopOpcode::And, 0b1000),
+        ("or", BinopOpcode::Or, 0b1110),
+    ];
+    for (name, variant, result) in binop_cases.into_iter() {
+        vectors.push(vector(
+            name,
+            &[Instruction::new(Opcode::Binop(variant))
+                .src0(1)
+                .src1(2)
+                .dst0(3)
+                .flag(SET_FLAGS_FLAG_IDX)],
+            InitialState {
+                registers: vec![(1, int(0b1100)), (2, int(0b1010))],
+                ..InitialState::default()
+            },
+            ExpectedState {
+                registers: vec![(3, int(result))],
+                flags: Some(flags(false, false, false)),
+                ..ExpectedState::default()
+            },
+        ));
+    }
+    vectors.push(vector(
+        "and_set_flags_zero",
+        &[Instruction::new(Opcode::Binop(BinopOpcode::And))
+            .src0(1)
+            .src1(2)
+            .dst0(3)
+            .flag(SET_FLAGS_FLAG_IDX)],
+        InitialState {
+            registers: vec![(1, int(0b0101)), (2, int(0b1010))],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(3, int(0))],
+            flags: Some(flags(false, true, false)),
+            ..ExpectedState::default()
+        },
+    ));
+
+    vectors
+}
+
+fn control_flow_vectors() -> Vec<ConformanceVector> {
+    let mut vectors = vec![];
+
+    // condition that doesn't hold masks the opcode into NOP, but ergs are still paid
+    let op = add().src0(1).src1(2).dst0(3).condition(Condition::Gt);
+    vectors.push(vector(
+        "condition_not_satisfied",
+        &[op],
+        InitialState {
+            registers: vec![(1, int(2)), (2, int(3))],
+            flags: flags(true, false, false),
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(3, int(0))],
+            flags: Some(flags(true, false, false)),
+            pc: Some(1),
+            ergs_remaining: Some(ergs_after(&[op])),
+            ..ExpectedState::default()
+        },
+    ));
+    vectors.push(vector(
+        "condition_satisfied",
+        &[op],
+        InitialState {
+            registers: vec![(1, int(2)), (2, int(3))],
+            flags: flags(false, false, true),
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(3, int(5))],
+            pc: Some(1),
+            ..ExpectedState::default()
+        },
+    ));
+
+    let jump = Instruction::new(Opcode::Jump(JumpOpcode));
+    let program = [
+        jump.src0_imm(2),
+        add().src0_imm(1).dst0(1),
+        add().src0_imm(2).dst0(2),
+    ];
+    vectors.push(vector(
+        "jump_imm",
+        &program,
+        InitialState::default(),
+        ExpectedState {
+            registers: vec![(1, int(0)), (2, int(2))],
+            pc: Some(3),
+            ..ExpectedState::default()
+        },
+    ));
+    let program = [
+        jump.src0(3),
+        add().src0_imm(1).dst0(1),
+        add().src0_imm(2).dst0(2),
+    ];
+    let mut jump_reg = vector(
+        "jump_reg",
+        &program,
+        InitialState {
+            registers: vec![(3, int(2))],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(1, int(0)), (2, int(2))],
+            pc: Some(3),
+            ..ExpectedState::default()
+        },
+    );
+    jump_reg.cycles = 2;
+    vectors.push(jump_reg);
+
+    // near call passes all ergs if ABI asks for 0, and the rest is returned on ret
+    let near_call = Instruction::new(Opcode::NearCall(NearCallOpcode));
+    let program = [
+        near_call.src0(1).imm0(2).imm1(3),
+        add().src0_imm(1).dst0(5),
+        ret(RetOpcode::Ok),
+        add().src0_imm(7).dst0(6),
+    ];
+    let mut near_call_ok = vector(
+        "near_call_and_ret_ok",
+        &program,
+        InitialState::default(),
+        ExpectedState {
+            registers: vec![(5, int(1)), (6, int(0))],
+            pc: Some(2),
+            callstack_depth: Some(1),
+            flags: Some(Flags::empty()),
+            ergs_remaining: Some(ergs_after(&program[..3])),
+            ..ExpectedState::default()
+        },
+    );
+    near_call_ok.cycles = 3;
+    vectors.push(near_call_ok);
+
+    let program = [
+        near_call.src0(1).imm0(2).imm1(3),
+        add().src0_imm(1).dst0(5),
+        ret(RetOpcode::Panic),
+        add().src0_imm(7).dst0(6),
+    ];
+    let mut near_call_panic = vector(
+        "near_call_panic_goes_to_exception_handler",
+        &program,
+        InitialState::default(),
+        ExpectedState {
+            registers: vec![(5, int(0)), (6, int(7))],
+            pc: Some(4),
+            callstack_depth: Some(1),
+            flags: Some(flags(true, false, false)),
+            panicked: true,
+            ..ExpectedState::default()
+        },
+    );
+    near_call_panic.cycles = 3;
+    vectors.push(near_call_panic);
+
+    let ergs_left = context(ContextOpcode::ErgsLeft).dst0(2);
+    let program = [
+        near_call.src0(1).imm0(2).imm1(4),
+        add().src0_imm(1).dst0(5),
+        ergs_left,
+        ret(RetOpcode::Ok),
+    ];
+    let mut near_call_limited = vector(
+        "near_call_passes_requested_ergs",
+        &program,
+        InitialState {
+            registers: vec![(1, int(100))],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(2, int(100 - ergs_left.ergs_price() as u64)), (5, int(1))],
+            pc: Some(2),
+            callstack_depth: Some(1),
+            ergs_remaining: Some(ergs_after(&program)),
+            ..ExpectedState::default()
+        },
+    );
+    near_call_limited.cycles = 4;
+    vectors.push(near_call_limited);
+
+    let program = [
+        near_call.src0(1).imm0(2).imm1(4),
+        add().src0_imm(1).dst0(5),
+        ret(RetOpcode::Ok).flag(RET_TO_LABEL_BIT_IDX).imm0(3),
+        add().src0_imm(2).dst0(6),
+    ];
+    let mut ret_to_label = vector(
+        "near_ret_to_label",
+        &program,
+        InitialState::default(),
+        ExpectedState {
+            registers: vec![(5, int(0)), (6, int(2))],
+            pc: Some(4),
+            callstack_depth: Some(1),
+            ..ExpectedState::default()
+        },
+    );
+    ret_to_label.cycles = 3;
+    vectors.push(ret_to_label);
+
+    // returning from the root frame ends execution, and returndata pointer is placed into r1
+    let root_heap_page = CallStackEntry::<8, EncodingModeProduction>::heap_page_from_base(
+        MemoryPage(BOOTLOADER_BASE_PAGE),
+    )
+    .0;
+    vectors.push(vector(
+        "far_ret_ok_forwards_heap_slice",
+        &[ret(RetOpcode::Ok).src0(1)],
+        InitialState {
+            registers: vec![
+                (1, int_u256(fat_pointer(0, 0, 64, 0).to_u256())),
+                (7, int(3)),
+            ],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(1, ptr(fat_pointer(root_heap_page, 0, 64, 0))), (7, int(0))],
+            execution_has_ended: true,
+            flags: Some(Flags::empty()),
+            ..ExpectedState::default()
+        },
+    ));
+    vectors.push(panicking_vector(
+        "far_ret_with_malformed_slice_panics",
+        &[ret(RetOpcode::Ok).src0(1)],
+        InitialState {
+            registers: vec![(1, int_u256(fat_pointer(0, 0, 5, 10).to_u256()))],
+            ..InitialState::default()
+        },
+    ));
+    vectors.push(vector(
+        "far_revert",
+        &[ret(RetOpcode::Revert)],
+        InitialState::default(),
+        ExpectedState {
+            execution_has_ended: true,
+            flags: Some(Flags::empty()),
+            ..ExpectedState::default()
+        },
+    ));
+    vectors.push(panicking_vector(
+        "explicit_panic",
+        &[ret(RetOpcode::Panic)],
+        InitialState::default(),
+    ));
+
+    vectors
+}
+
+fn context_vectors() -> Vec<ConformanceVector> {
+    let mut vectors = vec![];
+    let bootloader = Address::from_low_u64_be(ADDRESS_BOOTLOADER as u64);
+    let caller = Address::from_low_u64_be(0x1234);
+
+    let initial = InitialState {
+        msg_sender: caller,
+        context_u128_value: 42,
+        current_ergs_per_pubdata_byte: 3,
+        heap_bound: 64,
+        aux_heap_bound: 96,
+        registers: vec![(1, int(77))],
+        ..InitialState::default()
+    };
+
+    let reading_cases = [
+        (
+            "context_this",
+            ContextOpcode::This,
+            address_to_u256(&bootloader),
+        ),
+        (
+            "context_caller",
+            ContextOpcode::Caller,
+            address_to_u256(&caller),
+        ),
+        (
+            "context_code_address",
+            ContextOpcode::CodeAddress,
+            address_to_u256(&bootloader),
+        ),
+        (
+            "context_sp",
+            ContextOpcode::Sp,
+            U256::from(INITIAL_SP_ON_FAR_CALL),
+        ),
+        (
+            "context_get_context_u128",
+            ContextOpcode::GetContextU128,
+            U256::from(42u64),
+        ),
+        (
+            "context_meta",
+            ContextOpcode::Meta,
+            VmMetaParameters {
+                ergs_per_pubdata_byte: 3,
+                this_shard_id: 0,
+                caller_shard_id: 0,
+                code_shard_id: 0,
+                heap_size: 64,
+                aux_heap_size: 96,
+            }
+            .to_u256(),
+        ),
+    ];
+    for (name, variant, result) in reading_cases.into_iter() {
+        vectors.push(vector(
+            name,
+            &[context(variant).dst0(2)],
+            initial.clone(),
+            ExpectedState {
+                registers: vec![(2, int_u256(result))],
+                ..ExpectedState::default()
+            },
+        ));
+    }
+
+    // ergs are paid before execution
+    let op = context(ContextOpcode::ErgsLeft).dst0(2);
+    vectors.push(vector(
+        "context_ergs_left",
+        &[op],
+        initial.clone(),
+        ExpectedState {
+            registers: vec![(2, int(ergs_after(&[op]) as u64))],
+            ..ExpectedState::default()
+        },
+    ));
+    vectors.push(vector(
+        "context_set_context_u128",
+        &[context(ContextOpcode::SetContextU128).src0(1)],
+        initial.clone(),
+        ExpectedState {
+            context_u128_register: Some(77),
+            ..ExpectedState::default()
+        },
+    ));
+    vectors.push(vector(
+        "context_set_ergs_per_pubdata",
+        &[context(ContextOpcode::SetErgsPerPubdataByte).src0(1)],
+        initial.clone(),
+        ExpectedState {
+            current_ergs_per_pubdata_byte: Some(77),
+            ..ExpectedState::default()
+        },
+    ));
+    vectors.push(vector(
+        "context_increment_tx_number",
+        &[context(ContextOpcode::IncrementTxNumber)],
+        initial.clone(),
+        ExpectedState {
+            tx_number_in_block: Some(1),
+            ..ExpectedState::default()
+        },
+    ));
+    vectors.push(panicking_vector(
+        "context_set_context_u128_requires_kernel_mode",
+        &[context(ContextOpcode::SetContextU128).src0(1)],
+        InitialState {
+            this_address: user_address(),
+            ..initial.clone()
+        },
+    ));
+
+    vectors
+}
+
+fn ptr_vectors() -> Vec<ConformanceVector> {
+    let mut vectors = vec![];
+    let ptr_op = |variant: PtrOpcode| {
+        Instruction::new(Opcode::Ptr(variant))
+            .src0(1)
+            .src1(2)
+            .dst0(3)
+    };
+    let pointer = fat_pointer(5, 0, 64, 32);
+
+    let ok_cases = [
+        (
+            "ptr_add",
+            PtrOpcode::Add,
+            U256::from(32u64),
+            fat_pointer(5, 0, 64, 64).to_u256(),
+        ),
+        (
+            "ptr_sub",
+            PtrOpcode::Sub,
+            U256::from(16u64),
+            fat_pointer(5, 0, 64, 16).to_u256(),
+        ),
+        (
+            "ptr_shrink",
+            PtrOpcode::Shrink,
+            U256::from(16u64),
+            fat_pointer(5, 0, 48, 32).to_u256(),
+        ),
+        (
+            "ptr_pack",
+            PtrOpcode::Pack,
+            U256::from(0xabcu64) << 128,
+            pointer.to_u256() | (U256::from(0xabcu64) << 128),
+        ),
+    ];
+    for (name, variant, src1, result) in ok_cases.into_iter() {
+        vectors.push(vector(
+            name,
+            &[ptr_op(variant)],
+            InitialState {
+                registers: vec![(1, ptr(pointer)), (2, int_u256(src1))],
+                ..InitialState::default()
+            },
+            ExpectedState {
+                registers: vec![(
+                    3,
+                    PrimitiveValue {
+                        value: result,
+                        is_pointer: true,
+                    },
+                )],
+                ..ExpectedState::default()
+            },
+        ));
+    }
+
+    let panic_cases = [
+        (
+            "ptr_add_of_integer_panics",
+            PtrOpcode::Add,
+            int_u256(pointer.to_u256()),
+            int(1),
+        ),
+        (
+            "ptr_add_of_two_pointers_panics",
+            PtrOpcode::Add,
+            ptr(pointer),
+            ptr(pointer),
+        ),
+        (
+            "ptr_sub_underflow_panics",
+            PtrOpcode::Sub,
+            ptr(pointer),
+            int(33),
+        ),
+        (
+            "ptr_add_too_large_offset_panics",
+            PtrOpcode::Add,
+            ptr(pointer),
+            int_u256(U256::one() << 32),
+        ),
+        (
+            "ptr_shrink_underflow_panics",
+            PtrOpcode::Shrink,
+            ptr(pointer),
+            int(65),
+        ),
+        (
+            "ptr_pack_with_low_bits_panics",
+            PtrOpcode::Pack,
+            ptr(pointer),
+            int(1),
+        ),
+    ];
+    for (name, variant, src0, src1) in panic_cases.into_iter() {
+        vectors.push(panicking_vector(
+            name,
+            &[ptr_op(variant)],
+            InitialState {
+                registers: vec![(1, src0), (2, src1)],
+                ..InitialState::default()
+            },
+        ));
+    }
+
+    vectors
+}
+
+fn uma_vectors() -> Vec<ConformanceVector> {
+    let mut vectors = vec![];
+    let value = U256::from_str_radix(
+        "0102030405060708091011121314151617181920212223242526272829303132",
+        16,
+    )
+    .unwrap();
+
+    // memory growth is paid per byte
+    let op = uma(UMAOpcode::HeapWrite).src0(1).src1(2);
+    vectors.push(vector(
+        "heap_write_grows_memory",
+        &[op],
+        InitialState {
+            registers: vec![(1, int(64)), (2, int_u256(value))],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            heap: vec![(2, value)],
+            heap_bound: Some(96),
+            ergs_remaining: Some(ergs_after(&[op]) - 96 * MEMORY_GROWTH_ERGS_PER_BYTE),
+            ..ExpectedState::default()
+        },
+    ));
+    let op = uma(UMAOpcode::HeapRead).src0(1).dst0(3);
+    vectors.push(vector(
+        "heap_read_in_bounds_is_free",
+        &[op],
+        InitialState {
+            registers: vec![(1, int(32))],
+            heap: vec![(1, value)],
+            heap_bound: 64,
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(3, int_u256(value))],
+            heap_bound: Some(64),
+            ergs_remaining: Some(ergs_after(&[op])),
+            ..ExpectedState::default()
+        },
+    ));
+    vectors.push(vector(
+        "heap_write_unaligned",
+        &[uma(UMAOpcode::HeapWrite).src0(1).src1(2)],
+        InitialState {
+            registers: vec![(1, int(1)), (2, int_u256(value))],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            heap: vec![(0, value >> 8), (1, value << 248)],
+            heap_bound: Some(33),
+            ..ExpectedState::default()
+        },
+    ));
+    vectors.push(vector(
+        "heap_read_unaligned",
+        &[uma(UMAOpcode::HeapRead).src0(1).dst0(3)],
+        InitialState {
+            registers: vec![(1, int(1))],
+            heap: vec![(0, value), (1, U256::MAX)],
+            heap_bound: 64,
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(3, int_u256((value << 8) | U256::from(0xffu64)))],
+            ..ExpectedState::default()
+        },
+    ));
+    vectors.push(vector(
+        "heap_write_and_read_back",
+        &[
+            uma(UMAOpcode::HeapWrite).src0(1).src1(2),
+            uma(UMAOpcode::HeapRead).src0(1).dst0(3),
+        ],
+        InitialState {
+            registers: vec![(1, int(100)), (2, int_u256(value))],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(3, int_u256(value))],
+            heap_bound: Some(132),
+            ..ExpectedState::default()
+        },
+    ));
+    vectors.push(vector(
+        "aux_heap_write",
+        &[uma(UMAOpcode::AuxHeapWrite).src0(1).src1(2)],
+        InitialState {
+            registers: vec![(1, int(64)), (2, int_u256(value))],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            aux_heap: vec![(2, value)],
+            aux_heap_bound: Some(96),
+            heap_bound: Some(0),
+            ..ExpectedState::default()
+        },
+    ));
+    vectors.push(vector(
+        "aux_heap_read",
+        &[uma(UMAOpcode::AuxHeapRead).src0(1).dst0(3)],
+        InitialState {
+            registers: vec![(1, int(0))],
+            aux_heap: vec![(0, value)],
+            aux_heap_bound: 32,
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(3, int_u256(value))],
+            ..ExpectedState::default()
+        },
+    ));
+    vectors.push(vector(
+        "heap_write_with_increment",
+        &[uma(UMAOpcode::HeapWrite)
+            .src0(1)
+            .src1(2)
+            .dst0(3)
+            .flag(UMA_INCREMENT_FLAG_IDX)],
+        InitialState {
+            registers: vec![(1, int(64)), (2, int_u256(value))],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(3, int(96))],
+            heap: vec![(2, value)],
+            ..ExpectedState::default()
+        },
+    ));
+    vectors.push(vector(
+        "heap_read_with_increment",
+        &[uma(UMAOpcode::HeapRead)
+            .src0(1)
+            .dst0(3)
+            .dst1(4)
+            .flag(UMA_INCREMENT_FLAG_IDX)],
+        InitialState {
+            registers: vec![(1, int(0))],
+            heap: vec![(0, value)],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(3, int_u256(value)), (4, int(32))],
+            ..ExpectedState::default()
+        },
+    ));
+    vectors.push(panicking_vector(
+        "heap_read_beyond_u32_offset_panics",
+        &[uma(UMAOpcode::HeapRead).src0(1).dst0(3)],
+        InitialState {
+            registers: vec![(1, int_u256(U256::one() << 32))],
+            ..InitialState::default()
+        },
+    ));
+    vectors.push(panicking_vector(
+        "heap_write_without_ergs_for_growth_panics",
+        &[uma(UMAOpcode::HeapWrite).src0(1).src1(2)],
+        InitialState {
+            registers: vec![(1, int(CONFORMANCE_DEFAULT_ERGS as u64))],
+            ..InitialState::default()
+        },
+    ));
+    vectors.push(panicking_vector(
+        "fat_pointer_read_of_integer_panics",
+        &[uma(UMAOpcode::FatPointerRead).src0(1).dst0(3)],
+        InitialState {
+            registers: vec![(1, int_u256(fat_pointer(0, 0, 64, 0).to_u256()))],
+            ..InitialState::default()
+        },
+    ));
+    // reading beyond the slice is not an exception, but gives zero
+    vectors.push(vector(
+        "fat_pointer_read_out_of_bounds_gives_zero",
+        &[uma(UMAOpcode::FatPointerRead)
+            .src0(1)
+            .dst0(3)
+            .dst1(4)
+            .flag(UMA_INCREMENT_FLAG_IDX)],
+        InitialState {
+            registers: vec![(1, ptr(fat_pointer(0, 0, 32, 32))), (3, int(5))],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(3, int(0)), (4, ptr(fat_pointer(0, 0, 32, 64)))],
+            ..ExpectedState::default()
+        },
+    ));
+
+    vectors
+}
+
+fn log_vectors() -> Vec<ConformanceVector> {
+    let mut vectors = vec![];
+    let bootloader = Address::from_low_u64_be(ADDRESS_BOOTLOADER as u64);
+
+    vectors.push(vector(
+        "storage_write_and_read",
+        &[
+            log(LogOpcode::StorageWrite).src0(1).src1(2),
+            log(LogOpcode::StorageRead).src0(1).dst0(3),
+        ],
+        InitialState {
+            registers: vec![(1, int(7)), (2, int(9))],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(3, int(9))],
+            storage: vec![(0, bootloader, U256::from(7u64), U256::from(9u64))],
+            ..ExpectedState::default()
+        },
+    ));
+    vectors.push(vector(
+        "storage_read_of_initial_value",
+        &[log(LogOpcode::StorageRead).src0(1).dst0(3)],
+        InitialState {
+            registers: vec![(1, int(1))],
+            storage: vec![(0, bootloader, U256::one(), U256::from(0x55u64))],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(3, int(0x55))],
+            ..ExpectedState::default()
+        },
+    ));
+    vectors.push(panicking_vector(
+        "storage_write_in_static_context_panics",
+        &[log(LogOpcode::StorageWrite).src0(1).src1(2)],
+        InitialState {
+            registers: vec![(1, int(7)), (2, int(9))],
+            is_static: true,
+            ..InitialState::default()
+        },
+    ));
+    vectors.push(vector(
+        "event",
+        &[log(LogOpcode::Event)
+            .src0(1)
+            .src1(2)
+            .flag(FIRST_MESSAGE_FLAG_IDX)],
+        InitialState {
+            registers: vec![(1, int(3)), (2, int(4))],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            events: Some(vec![(bootloader, U256::from(3u64), U256::from(4u64))]),
+            l1_messages: Some(vec![]),
+            ..ExpectedState::default()
+        },
+    ));
+    vectors.push(vector(
+        "l1_message",
+        &[log(LogOpcode::ToL1Message)
+            .src0(1)
+            .src1(2)
+            .flag(FIRST_MESSAGE_FLAG_IDX)],
+        InitialState {
+            registers: vec![(1, int(5)), (2, int(6))],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            events: Some(vec![]),
+            l1_messages: Some(vec![(bootloader, U256::from(5u64), U256::from(6u64))]),
+            ..ExpectedState::default()
+        },
+    ));
+    vectors.push(panicking_vector(
+        "precompile_call_requires_kernel_mode",
+        &[log(LogOpcode::PrecompileCall).src0(1).src1(2).dst0(3)],
+        InitialState {
+            this_address: user_address(),
+            ..InitialState::default()
+        },
+    ));
+
+    vectors
+}
+
+fn far_call_vectors() -> Vec<ConformanceVector> {
+    let mut vectors = vec![];
+    let callee = Address::from_low_u64_be((1 << 16) + 1);
+    let returning_code = assemble(&[ret(RetOpcode::Ok).encode()]);
+    let writing_code = assemble(&[log(LogOpcode::StorageWrite).src0(1).src1(1).encode()]);
+    let far_call = |variant: FarCallOpcode| Instruction::new(Opcode::FarCall(variant)).src1(2);
+    let code_hash_entry = |code: &Vec<U256>| {
+        (
+            0u8,
+            *DEPLOYER_SYSTEM_CONTRACT_ADDRESS,
+            address_to_u256(&callee),
+            versioned_hash_of_words(code),
+        )
+    };
+
+    // ret from callee zeroes all registers except returndata
+    let program = [
+        far_call(FarCallOpcode::Normal).imm0(3),
+        add().src0_imm(1).dst0(5),
+    ];
+    let mut call_and_return = vector(
+        "far_call_and_ret_ok",
+        &program,
+        InitialState {
+            registers: vec![(2, int_u256(address_to_u256(&callee))), (6, int(3))],
+            storage: vec![code_hash_entry(&returning_code)],
+            bytecodes: vec![returning_code.clone()],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(2, int(0)), (5, int(1)), (6, int(0))],
+            pc: Some(2),
+            callstack_depth: Some(1),
+            ..ExpectedState::default()
+        },
+    );
+    call_and_return.cycles = 3;
+    vectors.push(call_and_return);
+
+    // calling an address without code from user space goes to default account
+    let mut default_aa = vector(
+        "far_call_of_empty_address_uses_default_account",
+        &program,
+        InitialState {
+            registers: vec![(2, int_u256(address_to_u256(&callee)))],
+            default_aa_bytecode: Some(returning_code.clone()),
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(5, int(1))],
+            pc: Some(2),
+            callstack_depth: Some(1),
+            ..ExpectedState::default()
+        },
+    );
+    default_aa.cycles = 3;
+    vectors.push(default_aa);
+
+    // but kernel space addresses are never masked, and the callee panics right away
+    let program = [
+        far_call(FarCallOpcode::Normal).imm0(2),
+        add().src0_imm(1).dst0(5),
+        add().src0_imm(2).dst0(6),
+    ];
+    let mut empty_kernel_address = vector(
+        "far_call_of_empty_kernel_address_panics",
+        &program,
+        InitialState {
+            registers: vec![(2, int(0x8010))],
+            default_aa_bytecode: Some(returning_code.clone()),
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(5, int(0)), (6, int(2))],
+            pc: Some(3),
+            callstack_depth: Some(1),
+            flags: Some(flags(true, false, false)),
+            panicked: true,
+            ..ExpectedState::default()
+        },
+    );
+    empty_kernel_address.cycles = 3;
+    vectors.push(empty_kernel_address);
+
+    // callee of static call can not write, and panic returns to the caller's exception handler
+    let program = [
+        far_call(FarCallOpcode::Normal)
+            .flag(FAR_CALL_STATIC_FLAG_IDX)
+            .imm0(2),
+        add().src0_imm(1).dst0(5),
+        add().src0_imm(2).dst0(6),
+    ];
+    let mut static_call = vector(
+        "far_call_static_callee_can_not_write",
+        &program,
+        InitialState {
+            registers: vec![(2, int_u256(address_to_u256(&callee)))],
+            storage: vec![code_hash_entry(&writing_code)],
+            bytecodes: vec![writing_code.clone()],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(5, int(0)), (6, int(2))],
+            pc: Some(3),
+            callstack_depth: Some(1),
+            flags: Some(flags(true, false, false)),
+            panicked: true,
+            ..ExpectedState::default()
+        },
+    );
+    static_call.cycles = 3;
+    vectors.push(static_call);
+
+    // callees below report what they see through storage, so ergs are passed explicitly
+    let abi_with_ergs = |to_system: bool| {
+        let mut abi = U256::zero();
+        abi.0[3] = 100_000 | ((to_system as u64) << 56);
+        abi
+    };
+
+    // system call marker and system ABI registers are masked for calls to user space
+    let masking_code = assemble(
+        &[
+            add().src0_imm(7).dst0(5),
+            add().src0_imm(1).src1(2).dst0(6),
+            add().src0(6).src1(3).dst0(6),
+            log(LogOpcode::StorageWrite).src0(5).src1(6),
+            ret(RetOpcode::Ok),
+        ]
+        .map(|el| el.encode()),
+    );
+    let program = [
+        far_call(FarCallOpcode::Normal).src0(1).imm0(2),
+        add().src0_imm(1).dst0(5),
+    ];
+    let mut system_call_masking = vector(
+        "far_call_to_user_space_masks_system_call",
+        &program,
+        InitialState {
+            registers: vec![
+                (1, int_u256(abi_with_ergs(true))),
+                (2, int_u256(address_to_u256(&callee))),
+                (3, int(40)),
+            ],
+            storage: vec![code_hash_entry(&masking_code)],
+            bytecodes: vec![masking_code.clone()],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(5, int(1))],
+            pc: Some(2),
+            callstack_depth: Some(1),
+            storage: vec![(0, callee, U256::from(7u64), U256::one())],
+            ..ExpectedState::default()
+        },
+    );
+    system_call_masking.cycles = 7;
+    vectors.push(system_call_masking);
+
+    // delegate call runs the callee's code on behalf of the caller
+    let delegated_code = assemble(
+        &[
+            add().src0_imm(7).dst0(3),
+            log(LogOpcode::StorageWrite).src0(3).src1(3),
+            ret(RetOpcode::Ok),
+        ]
+        .map(|el| el.encode()),
+    );
+    let program = [
+        far_call(FarCallOpcode::Delegate).src0(1).imm0(2),
+        add().src0_imm(1).dst0(5),
+    ];
+    let this_address = InitialState::default().this_address;
+    let mut delegate_call = vector(
+        "far_call_delegate_writes_caller_storage",
+        &program,
+        InitialState {
+            registers: vec![
+                (1, int_u256(abi_with_ergs(false))),
+                (2, int_u256(address_to_u256(&callee))),
+            ],
+            storage: vec![code_hash_entry(&delegated_code)],
+            bytecodes: vec![delegated_code.clone()],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(5, int(1))],
+            pc: Some(2),
+            callstack_depth: Some(1),
+            storage: vec![
+                (0, this_address, U256::from(7u64), U256::from(7u64)),
+                (0, callee, U256::from(7u64), U256::zero()),
+            ],
+            ..ExpectedState::default()
+        },
+    );
+    delegate_call.cycles = 5;
+    vectors.push(delegate_call);
+
+    // mimic call takes the callee's msg.sender from the implicit parameter register
+    let mimicked = Address::from_low_u64_be((1 << 16) + 2);
+    let caller_reporting_code = assemble(
+        &[
+            context(ContextOpcode::Caller).dst0(3),
+            log(LogOpcode::StorageWrite).src0(3).src1(3),
+            ret(RetOpcode::Ok),
+        ]
+        .map(|el| el.encode()),
+    );
+    let program = [
+        far_call(FarCallOpcode::Mimic).src0(1).imm0(2),
+        add().src0_imm(1).dst0(5),
+    ];
+    let mut mimic_call = vector(
+        "far_call_mimic_sets_msg_sender",
+        &program,
+        InitialState {
+            registers: vec![
+                (1, int_u256(abi_with_ergs(false))),
+                (2, int_u256(address_to_u256(&callee))),
+                (
+                    CALL_IMPLICIT_PARAMETER_REG_IDX as u8 + 1,
+                    int_u256(address_to_u256(&mimicked)),
+                ),
+            ],
+            storage: vec![code_hash_entry(&caller_reporting_code)],
+            bytecodes: vec![caller_reporting_code.clone()],
+            ..InitialState::default()
+        },
+        ExpectedState {
+            registers: vec![(5, int(1))],
+            pc: Some(2),
+            callstack_depth: Some(1),
+            storage: vec![(
+                0,
+                callee,
+                address_to_u256(&mimicked),
+                address_to_u256(&mimicked),
+            )],
+            ..ExpectedState::default()
+        },
+    );
+    mimic_call.cycles = 5;
+    vectors.push(mimic_call);
+
+    vectors
+}
+
+/// Vectors that cover every opcode handled by `DecodedOpcode::apply`
+pub fn default_conformance_vectors() -> Vec<ConformanceVector> {
+    let mut vectors = vec![];
+    vectors.extend(arithmetic_vectors());
+    vectors.extend(control_flow_vectors());
+    vectors.extend(context_vectors());
+    vectors.extend(ptr_vectors());
+    vectors.extend(uma_vectors());
+    vectors.extend(log_vectors());
+    vectors.extend(far_call_vectors());
+
+    vectors
+}
+
+/// One of the default vectors, panics if there is no such vector
+pub fn vector_by_name(name: &str) -> ConformanceVector {
+    default_conformance_vectors()
+        .into_iter()
+        .find(|el| el.name == name)
+        .unwrap_or_else(|| panic!("there is no conformance vector {}", name))
+}
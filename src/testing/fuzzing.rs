@@ -0,0 +1,503 @@
+use super::*;
+
+use crate::testing::conformance::*;
+use crate::tracing::*;
+use crate::vm_state::PrimitiveValue;
+use crate::witness_trace::VmWitnessTracer;
+use lazy_static::lazy_static;
+use zk_evm_abstractions::queries::DecommittmentQuery;
+use zkevm_opcode_defs::decoding::*;
+use zkevm_opcode_defs::system_params::{DEPLOYER_SYSTEM_CONTRACT_ADDRESS, VM_MAX_STACK_DEPTH};
+use zkevm_opcode_defs::*;
+
+// Property-based fuzzing of the opcode pipeline. Raw fuzzer input is turned into a well-formed
+// program (every instruction decodes into a valid variant), random registers and a few callees
+// that are programs of the same kind. The program is executed from the bootloader-like root
+// frame, and invariants that every opcode must respect are checked after each cycle.
+// Same entry points are used by the cargo-fuzz target in `fuzz/` and by proptest tests.
+
+pub const FUZZ_MAX_PROGRAM_LENGTH: usize = 32;
+pub const FUZZ_MAX_CYCLES: usize = 128;
+pub const FUZZ_MAX_ERGS: u32 = 1 << 20;
+pub const FUZZ_CALLEES_COUNT: usize = 2;
+
+const CONDITION_MASK: u64 = 0b111;
+
+lazy_static! {
+    // variants with register or immediate operands only. Precompile calls are excluded as
+    // the reference precompiles processor expects well-formed inputs in memory
+    static ref FUZZABLE_VARIANTS: Vec<u64> = (0..(1u64 << VARIANT_BITS))
+        .filter(|idx| {
+            let (decoded, _) =
+                EncodingModeProduction::parse_preliminary_variant_and_absolute_number(*idx);
+            let variant = decoded.variant;
+            let opcode_is_fuzzable = !matches!(
+                variant.opcode,
+                Opcode::Invalid(_) | Opcode::Log(LogOpcode::PrecompileCall)
+            );
+            let src0_is_fuzzable = matches!(
+                variant.src0_operand_type,
+                Operand::RegOnly
+                    | Operand::RegOrImm(_)
+                    | Operand::Full(ImmMemHandlerFlags::UseRegOnly)
+                    | Operand::Full(ImmMemHandlerFlags::UseImm16Only)
+            );
+            let dst0_is_fuzzable = matches!(
+                variant.dst0_operand_type,
+                Operand::RegOnly
+                    | Operand::RegOrImm(RegOrImmFlags::UseRegOnly)
+                    | Operand::Full(ImmMemHandlerFlags::UseRegOnly)
+            );
+
+            opcode_is_fuzzable && src0_is_fuzzable && dst0_is_fuzzable
+        })
+        .collect();
+}
+
+/// Reads structured values from raw fuzzer input, yields zeroes once the input is exhausted
+#[derive(Clone, Copy, Debug)]
+pub struct FuzzBytes<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> FuzzBytes<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn take<const L: usize>(&mut self) -> [u8; L] {
+        let mut buffer = [0u8; L];
+        let len = std::cmp::min(L, self.data.len());
+        buffer[..len].copy_from_slice(&self.data[..len]);
+        self.data = &self.data[len..];
+
+        buffer
+    }
+
+    pub fn u8(&mut self) -> u8 {
+        self.take::<1>()[0]
+    }
+
+    pub fn u16(&mut self) -> u16 {
+        u16::from_le_bytes(self.take::<2>())
+    }
+
+    pub fn u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.take::<4>())
+    }
+
+    pub fn u64(&mut self) -> u64 {
+        u64::from_le_bytes(self.take::<8>())
+    }
+
+    pub fn bool(&mut self) -> bool {
+        self.u8() & 1 == 1
+    }
+
+    pub fn u256(&mut self) -> U256 {
+        U256::from_big_endian(&self.take::<32>())
+    }
+
+    /// Uniform-ish value in `0..bound`, zero if `bound` is zero
+    pub fn below(&mut self, bound: usize) -> usize {
+        if bound == 0 {
+            return 0;
+        }
+
+        self.u32() as usize % bound
+    }
+}
+
+pub fn fuzz_callee_address(index: usize) -> Address {
+    Address::from_low_u64_be((1 << 16) + 1 + index as u64)
+}
+
+fn small_immediate(bytes: &mut FuzzBytes<'_>) -> u16 {
+    // mostly small values, so jumps and exception handlers land inside the program
+    if bytes.u8() % 4 == 0 {
+        bytes.u16()
+    } else {
+        bytes.below(FUZZ_MAX_PROGRAM_LENGTH + 4) as u16
+    }
+}
+
+/// Random instruction that always decodes into a valid variant
+pub fn random_instruction(bytes: &mut FuzzBytes<'_>) -> u64 {
+    let variant_index = FUZZABLE_VARIANTS[bytes.below(FUZZABLE_VARIANTS.len())];
+    let condition = if bytes.bool() {
+        0
+    } else {
+        bytes.u8() as u64 & CONDITION_MASK
+    };
+    let src0 = bytes.u8() as u64 & 0xf;
+    let src1 = bytes.u8() as u64 & 0xf;
+    let dst0 = bytes.u8() as u64 & 0xf;
+    let dst1 = bytes.u8() as u64 & 0xf;
+    let imm0 = small_immediate(bytes) as u64;
+    let imm1 = small_immediate(bytes) as u64;
+
+    variant_index
+        | (condition << VARIANT_BITS)
+        | (src0 << 16)
+        | (src1 << 20)
+        | (dst0 << 24)
+        | (dst1 << 28)
+        | (imm0 << 32)
+        | (imm1 << 48)
+}
+
+pub fn random_program(bytes: &mut FuzzBytes<'_>) -> Vec<u64> {
+    let length = 1 + bytes.below(FUZZ_MAX_PROGRAM_LENGTH);
+
+    (0..length).map(|_| random_instruction(bytes)).collect()
+}
+
+/// Callees with code, user space addresses without code (so default account is used),
+/// and kernel space addresses without code. Msg value simulator is never called as it takes
+/// a pubdata-related stipend
+pub fn random_call_target(bytes: &mut FuzzBytes<'_>) -> Address {
+    match bytes.u8() % 4 {
+        0 | 1 => fuzz_callee_address(bytes.below(FUZZ_CALLEES_COUNT)),
+        2 => Address::from_low_u64_be((1 << 20) + bytes.u16() as u64),
+        _ => Address::from_low_u64_be(bytes.u16() as u64 & 0x7fff),
+    }
+}
+
+/// Far call ABI with random ergs, shard, forwarding mode and system flags. Calldata pointer
+/// bounds are kept small, so memory growth is affordable
+pub fn random_far_call_abi(bytes: &mut FuzzBytes<'_>) -> U256 {
+    let calldata = FatPointer {
+        offset: bytes.u8() as u32,
+        memory_page: bytes.u32(),
+        start: bytes.u16() as u32,
+        length: bytes.u16() as u32 % 1024,
+    };
+    let mut abi = calldata.to_u256();
+    abi.0[3] = (bytes.u32() as u64)
+        | ((bytes.u8() as u64 & 1) << 32)
+        | ((bytes.u8() as u64 % 3) << 40)
+        | ((bytes.bool() as u64) << 48)
+        | ((bytes.bool() as u64) << 56);
+
+    abi
+}
+
+/// Random register content. Only fat pointers to page 0 are generated: pointers to other pages
+/// can only be produced by the VM itself, and forging them is not a well-formed input
+pub fn random_register_value(bytes: &mut FuzzBytes<'_>) -> PrimitiveValue {
+    match bytes.u8() % 6 {
+        0 => PrimitiveValue::from_value(U256::from(bytes.u8())),
+        1 => PrimitiveValue::from_value(U256::from(bytes.u64())),
+        2 => PrimitiveValue::from_value(bytes.u256()),
+        3 => PrimitiveValue::from_value(address_to_u256(&random_call_target(bytes))),
+        4 => PrimitiveValue::from_value(random_far_call_abi(bytes)),
+        _ => {
+            let pointer = FatPointer {
+                offset: bytes.u32(),
+                memory_page: 0,
+                start: bytes.u32(),
+                length: bytes.u32(),
+            };
+
+            PrimitiveValue {
+                value: pointer.to_u256(),
+                is_pointer: true,
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FuzzInput {
+    pub program: Vec<u64>,
+    // callee `i` is deployed at `fuzz_callee_address(i)`, the first one is also a default account
+    pub callee_programs: Vec<Vec<u64>>,
+    pub registers: Vec<(u8, PrimitiveValue)>,
+    pub ergs: u32,
+    pub cycles: usize,
+}
+
+impl FuzzInput {
+    pub fn from_bytes(data: &[u8]) -> Self {
+        let mut bytes = FuzzBytes::new(data);
+        let ergs = bytes.u32() % FUZZ_MAX_ERGS;
+        let cycles = 1 + bytes.below(FUZZ_MAX_CYCLES);
+        let registers = (1..=REGISTERS_COUNT as u8)
+            .map(|reg| (reg, random_register_value(&mut bytes)))
+            .collect();
+        let program = random_program(&mut bytes);
+        let callee_programs = (0..FUZZ_CALLEES_COUNT)
+            .map(|_| random_program(&mut bytes))
+            .collect();
+
+        Self {
+            program,
+            callee_programs,
+            registers,
+            ergs,
+            cycles,
+        }
+    }
+
+    pub fn to_conformance_vector(&self) -> ConformanceVector {
+        let bytecodes: Vec<Vec<U256>> = self
+            .callee_programs
+            .iter()
+            .map(|program| {
+                let mut code = assemble(program);
+                // versioned hash requires odd number of words
+                if code.len() % 2 == 0 {
+                    code.push(U256::zero());
+                }
+
+                code
+            })
+            .collect();
+        let storage = bytecodes
+            .iter()
+            .enumerate()
+            .map(|(idx, code)| {
+                (
+                    0u8,
+                    *DEPLOYER_SYSTEM_CONTRACT_ADDRESS,
+                    address_to_u256(&fuzz_callee_address(idx)),
+                    versioned_hash_of_words(code),
+                )
+            })
+            .collect();
+
+        ConformanceVector {
+            name: "fuzz".to_owned(),
+            program: self.program.clone(),
+            cycles: self.cycles,
+            initial: InitialState {
+                ergs: self.ergs,
+                registers: self.registers.clone(),
+                storage,
+                default_aa_bytecode: bytecodes.first().cloned(),
+                bytecodes,
+                ..InitialState::default()
+            },
+            expected: ExpectedState::default(),
+        }
+    }
+}
+
+// Collects refunds that far calls get for decommitting already known code
+#[derive(Clone, Debug, Default)]
+struct DecommitRefundsTracer {
+    refunds: u64,
+}
+
+impl VmWitnessTracer<8, EncodingModeProduction> for DecommitRefundsTracer {
+    fn add_decommittment(
+        &mut self,
+        _monotonic_cycle_counter: u32,
+        decommittment_query: DecommittmentQuery,
+        _mem_witness: Vec<U256>,
+    ) {
+        if decommittment_query.is_fresh == false {
+            self.refunds += ERGS_PER_CODE_WORD_DECOMMITTMENT as u64
+                * decommittment_query.decommitted_length as u64;
+        }
+    }
+}
+
+// Notes the opcode that is actually executed in the cycle, if any
+#[derive(Debug, Default)]
+struct ExecutedOpcodeTracer {
+    executed: Option<Opcode>,
+}
+
+impl Tracer for ExecutedOpcodeTracer {
+    const CALL_AFTER_DECODING: bool = true;
+
+    type SupportedMemory = SimpleMemory;
+
+    fn before_decoding(&mut self, _state: VmLocalStateData<'_>, _memory: &Self::SupportedMemory) {}
+    fn after_decoding(
+        &mut self,
+        _state: VmLocalStateData<'_>,
+        data: AfterDecodingData,
+        _memory: &Self::SupportedMemory,
+    ) {
+        self.executed = if data.did_skip_cycle {
+            None
+        } else {
+            Some(data.opcode_masked.variant.opcode)
+        };
+    }
+    fn before_execution(
+        &mut self,
+        _state: VmLocalStateData<'_>,
+        _data: BeforeExecutionData,
+        _memory: &Self::SupportedMemory,
+    ) {
+    }
+    fn after_execution(
+        &mut self,
+        _state: VmLocalStateData<'_>,
+        _data: AfterExecutionData,
+        _memory: &Self::SupportedMemory,
+    ) {
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct InvariantsSnapshot {
+    timestamp: u32,
+    monotonic_cycle_counter: u32,
+    memory_page_counter: u32,
+    // over all frames, including the ones that are not current
+    total_ergs: u64,
+    depth: usize,
+}
+
+impl InvariantsSnapshot {
+    fn of<WT: VmWitnessTracer<8, EncodingModeProduction>>(vm: &ConformanceVm<WT>) -> Self {
+        let callstack = &vm.local_state.callstack;
+        let total_ergs = callstack
+            .inner
+            .iter()
+            .chain(std::iter::once(&callstack.current))
+            .map(|el| el.ergs_remaining as u64)
+            .sum();
+
+        Self {
+            timestamp: vm.local_state.timestamp,
+            monotonic_cycle_counter: vm.local_state.monotonic_cycle_counter,
+            memory_page_counter: vm.local_state.memory_page_counter,
+            total_ergs,
+            depth: callstack.depth(),
+        }
+    }
+}
+
+fn check_transition(
+    before: &InvariantsSnapshot,
+    after: &InvariantsSnapshot,
+    executed: Option<Opcode>,
+    refunds: u64,
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        after.timestamp >= before.timestamp,
+        "timestamp went back from {} to {}",
+        before.timestamp,
+        after.timestamp
+    );
+    anyhow::ensure!(
+        after.monotonic_cycle_counter == before.monotonic_cycle_counter + 1,
+        "cycle counter moved from {} to {}",
+        before.monotonic_cycle_counter,
+        after.monotonic_cycle_counter
+    );
+
+    let is_far_call = matches!(executed, Some(Opcode::FarCall(_)));
+    let expected_pages = if is_far_call {
+        before.memory_page_counter + NEW_MEMORY_PAGES_PER_FAR_CALL
+    } else {
+        before.memory_page_counter
+    };
+    anyhow::ensure!(
+        after.memory_page_counter == expected_pages,
+        "memory page counter moved from {} to {} on {:?}",
+        before.memory_page_counter,
+        after.memory_page_counter,
+        executed
+    );
+
+    anyhow::ensure!(
+        after.total_ergs <= before.total_ergs + refunds,
+        "ergs grew from {} to {} with {} refunded on {:?}",
+        before.total_ergs,
+        after.total_ergs,
+        refunds,
+        executed
+    );
+
+    anyhow::ensure!(
+        after.depth <= VM_MAX_STACK_DEPTH as usize,
+        "callstack depth {} is over the limit",
+        after.depth
+    );
+    let depth_is_valid = if after.depth == before.depth + 1 {
+        matches!(executed, Some(Opcode::FarCall(_) | Opcode::NearCall(_)))
+    } else if after.depth + 1 == before.depth {
+        matches!(executed, Some(Opcode::Ret(_)))
+    } else {
+        after.depth == before.depth
+    };
+    anyhow::ensure!(
+        depth_is_valid,
+        "callstack depth moved from {} to {} on {:?}",
+        before.depth,
+        after.depth,
+        executed
+    );
+
+    Ok(())
+}
+
+// storage and event sink start a frame on every call and finish it on every return,
+// on top of the frame that is never finished
+fn check_frames_balance<WT: VmWitnessTracer<8, EncodingModeProduction>>(
+    vm: &ConformanceVm<WT>,
+) -> anyhow::Result<()> {
+    let expected_frames = vm.local_state.callstack.depth() + 1;
+    anyhow::ensure!(
+        vm.storage.frames_stack.len() == expected_frames,
+        "storage has {} frames for callstack depth {}",
+        vm.storage.frames_stack.len(),
+        vm.local_state.callstack.depth()
+    );
+    anyhow::ensure!(
+        vm.event_sink.frames_stack.len() == expected_frames,
+        "event sink has {} frames for callstack depth {}",
+        vm.event_sink.frames_stack.len(),
+        vm.local_state.callstack.depth()
+    );
+
+    Ok(())
+}
+
+/// Runs the input and checks invariants after every cycle. Returns number of cycles executed
+pub fn check_invariants(input: &FuzzInput) -> anyhow::Result<usize> {
+    let vector = input.to_conformance_vector();
+    let mut vm = prepare_conformance_vm_with_memory(
+        &vector,
+        SimpleMemory::new_without_preallocations(),
+        DecommitRefundsTracer::default(),
+    );
+    check_frames_balance(&vm)?;
+
+    let mut tracer = ExecutedOpcodeTracer::default();
+    let mut cycles_used = 0;
+    while cycles_used < input.cycles && vm.execution_has_ended() == false {
+        let before = InvariantsSnapshot::of(&vm);
+        vm.witness_tracer.refunds = 0;
+        tracer.executed = None;
+
+        vm.cycle(&mut tracer)
+            .map_err(|error| anyhow::anyhow!("cycle {} failed: {}", cycles_used, error))?;
+
+        let after = InvariantsSnapshot::of(&vm);
+        check_transition(&before, &after, tracer.executed, vm.witness_tracer.refunds)
+            .and_then(|_| check_frames_balance(&vm))
+            .map_err(|error| anyhow::anyhow!("cycle {}: {}", cycles_used, error))?;
+        cycles_used += 1;
+    }
+
+    Ok(cycles_used)
+}
+
+/// Entry point for fuzzers: any violated invariant is a crash
+pub fn fuzz_one(data: &[u8]) {
+    let input = FuzzInput::from_bytes(data);
+    if let Err(error) = check_invariants(&input) {
+        panic!("invariant is violated: {}\n{:?}", error, input);
+    }
+}
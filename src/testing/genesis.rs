@@ -0,0 +1,240 @@
+use super::*;
+
+use crate::block_properties::BlockProperties;
+use crate::reference_impls::artifacts::{decode_hex, read_artifact};
+use crate::reference_impls::decommitter::hash_bytecode;
+use std::path::{Path, PathBuf};
+use zkevm_opcode_defs::sha3::{Digest, Keccak256};
+use zkevm_opcode_defs::system_params::{
+    ADDRESS_ETH_TOKEN, ADDRESS_KNOWN_CODES_STORAGE, DEPLOYER_SYSTEM_CONTRACT_ADDRESS,
+};
+use zkevm_opcode_defs::BOOTLOADER_CODE_PAGE;
+
+// Declarative description of the initial state. All numbers are hex strings (with or without 0x),
+// and bytecode can be given either inline as hex, or as a path to an artifact
+// (raw `.bin`, `.hex` or JSON with `bytecode` field) relative to the description file.
+//
+// {
+//     "bootloader": { "bytecode": "0x..." },
+//     "default_account": { "artifact": "artifacts/DefaultAccount.json" },
+//     "accounts": [
+//         {
+//             "address": "0x0000000000000000000000000000000000008006",
+//             "code": { "artifact": "artifacts/ContractDeployer.json" },
+//             "storage": { "0x00": "0x01" },
+//             "balance": "0xde0b6b3a7640000"
+//         }
+//     ],
+//     "known_bytecodes": [{ "bytecode": "0x..." }]
+// }
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BytecodeSource {
+    Bytecode(String),
+    Artifact(PathBuf),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct GenesisAccount {
+    pub address: String,
+    #[serde(default)]
+    pub shard_id: u8,
+    #[serde(default)]
+    pub code: Option<BytecodeSource>,
+    #[serde(default)]
+    pub storage: BTreeMap<String, String>,
+    #[serde(default)]
+    pub balance: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct GenesisDescription {
+    #[serde(default)]
+    pub bootloader: Option<BytecodeSource>,
+    #[serde(default)]
+    pub default_account: Option<BytecodeSource>,
+    #[serde(default)]
+    pub accounts: Vec<GenesisAccount>,
+    #[serde(default)]
+    pub known_bytecodes: Vec<BytecodeSource>,
+    #[serde(default)]
+    pub zkporter_is_available: bool,
+}
+
+/// Resolved initial state, ready to be placed into the testing oracles
+#[derive(Clone, Debug)]
+pub struct Genesis {
+    pub storage: Vec<(u8, Address, U256, U256)>,
+    pub bytecodes: Vec<(U256, Vec<U256>)>,
+    pub bootloader_code: Option<Vec<U256>>,
+    pub block_properties: BlockProperties,
+}
+
+fn parse_u256(input: &str) -> anyhow::Result<U256> {
+    let input = input.trim();
+    let input = input.strip_prefix("0x").unwrap_or(input);
+    if input.is_empty() {
+        return Ok(U256::zero());
+    }
+
+    U256::from_str_radix(input, 16)
+        .map_err(|e| anyhow::anyhow!("invalid integer {}: {:?}", input, e))
+}
+
+fn parse_address(input: &str) -> anyhow::Result<Address> {
+    let value = parse_u256(input)?;
+    if value > *U256_TO_ADDRESS_MASK {
+        anyhow::bail!("address {} is longer than 20 bytes", input);
+    }
+
+    Ok(u256_to_address_unchecked(&value))
+}
+
+/// Key of `balance[address]` in the base token contract, where balances are a mapping at slot 0
+pub fn balance_storage_key(address: &Address) -> U256 {
+    let mut hasher = Keccak256::new();
+    hasher.update([0u8; 12]);
+    hasher.update(address.as_bytes());
+    hasher.update([0u8; 32]);
+
+    U256::from_big_endian(hasher.finalize().as_slice())
+}
+
+impl BytecodeSource {
+    pub fn resolve(&self, base_dir: &Path) -> anyhow::Result<Vec<u8>> {
+        match self {
+            BytecodeSource::Bytecode(hex) => decode_hex(hex),
+            BytecodeSource::Artifact(path) => read_artifact(&base_dir.join(path)),
+        }
+    }
+}
+
+impl GenesisDescription {
+    pub fn from_json_str(input: &str) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(input)?)
+    }
+
+    /// Loads and resolves description, artifact paths are relative to the file's directory
+    pub fn load_from_file(path: impl AsRef<Path>) -> anyhow::Result<Genesis> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("failed to read genesis {:?}: {}", path, e))?;
+        let description = Self::from_json_str(&content)?;
+        let base_dir = path.parent().unwrap_or(Path::new("."));
+
+        description.resolve(base_dir)
+    }
+
+    pub fn resolve(&self, base_dir: &Path) -> anyhow::Result<Genesis> {
+        let mut storage = vec![];
+        let mut bytecodes = BTreeMap::<U256, Vec<U256>>::new();
+
+        let mut add_bytecode = |source: &BytecodeSource| -> anyhow::Result<U256> {
+            let bytecode = source.resolve(base_dir)?;
+            let hash = hash_bytecode(&bytecode)?;
+            let words = bytecode
+                .chunks(32)
+                .map(|el| U256::from_big_endian(el))
+                .collect();
+            bytecodes.insert(hash, words);
+
+            Ok(hash)
+        };
+
+        let default_aa_code_hash = match self.default_account.as_ref() {
+            Some(source) => add_bytecode(source)?,
+            None => U256::zero(),
+        };
+        for source in self.known_bytecodes.iter() {
+            let _ = add_bytecode(source)?;
+        }
+
+        let known_codes_storage = Address::from_low_u64_be(ADDRESS_KNOWN_CODES_STORAGE as u64);
+        let base_token = Address::from_low_u64_be(ADDRESS_ETH_TOKEN as u64);
+
+        for account in self.accounts.iter() {
+            let address = parse_address(&account.address)?;
+            if let Some(code) = account.code.as_ref() {
+                let hash = add_bytecode(code)?;
+                // far call reads code hash from the deployer's storage using address as a key
+                storage.push((
+                    account.shard_id,
+                    *DEPLOYER_SYSTEM_CONTRACT_ADDRESS,
+                    address_to_u256(&address),
+                    hash,
+                ));
+            }
+            for (key, value) in account.storage.iter() {
+                storage.push((
+                    account.shard_id,
+                    address,
+                    parse_u256(key)?,
+                    parse_u256(value)?,
+                ));
+            }
+            if let Some(balance) = account.balance.as_ref() {
+                storage.push((
+                    0,
+                    base_token,
+                    balance_storage_key(&address),
+                    parse_u256(balance)?,
+                ));
+            }
+        }
+
+        // every bytecode is marked as known
+        for hash in bytecodes.keys() {
+            storage.push((0, known_codes_storage, *hash, U256::one()));
+        }
+
+        let bootloader_code = match self.bootloader.as_ref() {
+            Some(source) => {
+                let bytecode = source.resolve(base_dir)?;
+                if bytecode.len() % 32 != 0 {
+                    anyhow::bail!("bootloader bytecode length is not divisible by 32");
+                }
+                Some(
+                    bytecode
+                        .chunks(32)
+                        .map(|el| U256::from_big_endian(el))
+                        .collect(),
+                )
+            }
+            None => None,
+        };
+
+        Ok(Genesis {
+            storage,
+            bytecodes: bytecodes.into_iter().collect(),
+            bootloader_code,
+            block_properties: BlockProperties {
+                default_aa_code_hash,
+                zkporter_is_available: self.zkporter_is_available,
+            },
+        })
+    }
+}
+
+impl Genesis {
+    /// Populates storage, decommitter and (if bootloader is given) its code page
+    pub fn apply_to<const B: bool>(&self, tools: &mut BasicTestingTools<B>) {
+        tools.storage.populate(self.storage.clone());
+        tools.decommittment_processor.populate(self.bytecodes.clone());
+        if let Some(code) = self.bootloader_code.as_ref() {
+            tools
+                .memory
+                .populate_code(vec![(BOOTLOADER_CODE_PAGE, code.clone())]);
+        }
+    }
+
+    pub fn code_hash_of(&self, address: &Address) -> Option<U256> {
+        let key = address_to_u256(address);
+        self.storage
+            .iter()
+            .find(|(_, storage_address, storage_key, _)| {
+                storage_address == &*DEPLOYER_SYSTEM_CONTRACT_ADDRESS && storage_key == &key
+            })
+            .map(|el| el.3)
+    }
+}
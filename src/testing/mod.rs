@@ -3,20 +3,27 @@ use std::collections::*;
 
 pub const NUM_SHARDS: usize = 2;
 
-use crate::reference_impls::{decommitter::SimpleDecommitter, event_sink::*, memory::SimpleMemory};
+use crate::reference_impls::{
+    decommitter::SimpleDecommitter, event_sink::*, memory::SimpleMemory,
+    precompiles::PrecompilesRegistry,
+};
+pub mod bootloader_harness;
+pub mod conformance;
+pub mod fuzzing;
+pub mod genesis;
+pub mod portable;
 pub mod simple_tracer;
 pub mod storage;
 
 use self::storage::InMemoryStorage;
 use crate::witness_trace::DummyTracer;
-use zk_evm_abstractions::precompiles::DefaultPrecompilesProcessor;
 use zk_evm_abstractions::queries::LogQuery;
 
 pub struct BasicTestingTools<const B: bool> {
     pub storage: InMemoryStorage,
     pub memory: SimpleMemory,
     pub event_sink: InMemoryEventSink,
-    pub precompiles_processor: DefaultPrecompilesProcessor<B>,
+    pub precompiles_processor: PrecompilesRegistry,
     pub decommittment_processor: SimpleDecommitter<B>,
     pub witness_tracer: DummyTracer,
 }
@@ -25,7 +32,7 @@ pub fn create_default_testing_tools() -> BasicTestingTools<false> {
     let storage = InMemoryStorage::new();
     let memory = SimpleMemory::new();
     let event_sink = InMemoryEventSink::new();
-    let precompiles_processor = DefaultPrecompilesProcessor::<false>;
+    let precompiles_processor = PrecompilesRegistry::with_default_precompiles::<false>();
     let decommittment_processor = SimpleDecommitter::<false>::new();
     let witness_tracer = DummyTracer;
 
@@ -0,0 +1,593 @@
+use super::*;
+
+use crate::block_properties::BlockProperties;
+use crate::testing::conformance::*;
+use crate::vm_state::{CallStackEntry, VmLocalState, VmState};
+use crate::witness_trace::VmWitnessTracer;
+use std::path::{Path, PathBuf};
+use zk_evm_abstractions::queries::{DecommittmentQuery, MemoryQuery};
+use zkevm_opcode_defs::decoding::{EncodingModeProduction, VmEncodingMode};
+use zkevm_opcode_defs::BOOTLOADER_CODE_PAGE;
+
+// Conformance vector in a self-contained JSON form, so it can be executed by another VM
+// implementation. Initial state is the full local state of the VM right before the first cycle
+// (the callstack must only contain the root frame on top of the formal empty one), with code
+// pages, heaps of the root frame, storage and known bytecodes. Expected state is the full
+// local state after execution, heaps of the frame that is current at the end, storage,
+// events and the witness queries in the order in which the VM produced them.
+// 256-bit values and addresses are encoded as 0x-prefixed hex strings.
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PortablePage {
+    pub page: u32,
+    // sparse, only non-zero words are listed
+    pub words: Vec<(u32, U256)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PortableStorageSlot {
+    pub shard_id: u8,
+    pub address: Address,
+    pub key: U256,
+    pub value: U256,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PortableBytecode {
+    pub hash: U256,
+    pub words: Vec<U256>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PortableEvent {
+    pub shard_id: u8,
+    pub is_first: bool,
+    pub tx_number_in_block: u16,
+    pub address: Address,
+    pub key: U256,
+    pub value: U256,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PortableMemoryQuery {
+    pub timestamp: u32,
+    // one of `Heap`, `AuxHeap`, `Stack`, `Code`, `FatPointer`
+    pub memory_type: String,
+    pub page: u32,
+    pub index: u32,
+    pub value: U256,
+    pub value_is_pointer: bool,
+    pub rw_flag: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PortableLogQuery {
+    pub timestamp: u32,
+    pub tx_number_in_block: u16,
+    pub aux_byte: u8,
+    pub shard_id: u8,
+    pub address: Address,
+    pub key: U256,
+    pub read_value: U256,
+    pub written_value: U256,
+    pub rw_flag: bool,
+    pub rollback: bool,
+    pub is_service: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PortableDecommittmentQuery {
+    pub hash: U256,
+    pub timestamp: u32,
+    pub memory_page: u32,
+    pub decommitted_length: u16,
+    pub is_fresh: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PortableWitness {
+    pub memory_queries: Vec<PortableMemoryQuery>,
+    pub log_queries: Vec<PortableLogQuery>,
+    pub decommittment_queries: Vec<PortableDecommittmentQuery>,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PortableInitialState {
+    pub local_state: VmLocalState,
+    pub code_pages: Vec<PortablePage>,
+    pub heap: PortablePage,
+    pub aux_heap: PortablePage,
+    pub storage: Vec<PortableStorageSlot>,
+    pub bytecodes: Vec<PortableBytecode>,
+    pub default_aa_code_hash: U256,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PortableFinalState {
+    pub local_state: VmLocalState,
+    pub cycles_used: usize,
+    pub heap: PortablePage,
+    pub aux_heap: PortablePage,
+    // non-zero slots only, sorted
+    pub storage: Vec<PortableStorageSlot>,
+    pub events: Vec<PortableEvent>,
+    pub l1_messages: Vec<PortableEvent>,
+    pub witness: PortableWitness,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PortableVector {
+    pub name: String,
+    pub cycles: usize,
+    pub initial: PortableInitialState,
+    pub expected: PortableFinalState,
+}
+
+/// Witness tracer that keeps the queries in a portable form
+#[derive(Clone, Debug, Default)]
+pub struct PortableWitnessRecorder {
+    pub witness: PortableWitness,
+}
+
+impl<const N: usize, E: VmEncodingMode<N>> VmWitnessTracer<N, E> for PortableWitnessRecorder {
+    fn add_memory_query(&mut self, _monotonic_cycle_counter: u32, memory_query: MemoryQuery) {
+        self.witness.memory_queries.push(PortableMemoryQuery {
+            timestamp: memory_query.timestamp.0,
+            memory_type: format!("{:?}", memory_query.location.memory_type),
+            page: memory_query.location.page.0,
+            index: memory_query.location.index.0,
+            value: memory_query.value,
+            value_is_pointer: memory_query.value_is_pointer,
+            rw_flag: memory_query.rw_flag,
+        });
+    }
+
+    fn add_log_query(&mut self, _monotonic_cycle_counter: u32, log_query: LogQuery) {
+        self.witness.log_queries.push(PortableLogQuery {
+            timestamp: log_query.timestamp.0,
+            tx_number_in_block: log_query.tx_number_in_block,
+            aux_byte: log_query.aux_byte,
+            shard_id: log_query.shard_id,
+            address: log_query.address,
+            key: log_query.key,
+            read_value: log_query.read_value,
+            written_value: log_query.written_value,
+            rw_flag: log_query.rw_flag,
+            rollback: log_query.rollback,
+            is_service: log_query.is_service,
+        });
+    }
+
+    fn add_decommittment(
+        &mut self,
+        _monotonic_cycle_counter: u32,
+        decommittment_query: DecommittmentQuery,
+        _mem_witness: Vec<U256>,
+    ) {
+        self.witness
+            .decommittment_queries
+            .push(PortableDecommittmentQuery {
+                hash: decommittment_query.hash,
+                timestamp: decommittment_query.timestamp.0,
+                memory_page: decommittment_query.memory_page.0,
+                decommitted_length: decommittment_query.decommitted_length,
+                is_fresh: decommittment_query.is_fresh,
+            });
+    }
+}
+
+impl PortablePage {
+    pub fn from_words(page: u32, words: &[U256]) -> Self {
+        Self {
+            page,
+            words: words
+                .iter()
+                .enumerate()
+                .filter(|(_, el)| el.is_zero() == false)
+                .map(|(idx, el)| (idx as u32, *el))
+                .collect(),
+        }
+    }
+
+    pub fn to_words(&self) -> Vec<U256> {
+        let len = self
+            .words
+            .iter()
+            .map(|(idx, _)| *idx as usize + 1)
+            .max()
+            .unwrap_or(0);
+        let mut words = vec![U256::zero(); len];
+        for (idx, value) in self.words.iter() {
+            words[*idx as usize] = *value;
+        }
+
+        words
+    }
+}
+
+impl From<EventMessage> for PortableEvent {
+    fn from(value: EventMessage) -> Self {
+        Self {
+            shard_id: value.shard_id,
+            is_first: value.is_first,
+            tx_number_in_block: value.tx_number_in_block,
+            address: value.address,
+            key: value.key,
+            value: value.value,
+        }
+    }
+}
+
+fn non_zero_storage(
+    storage: &[HashMap<Address, HashMap<U256, U256>>; NUM_SHARDS],
+) -> Vec<PortableStorageSlot> {
+    let mut result = vec![];
+    for (shard_id, shard) in storage.iter().enumerate() {
+        for (address, slots) in shard.iter() {
+            for (key, value) in slots.iter().filter(|(_, el)| el.is_zero() == false) {
+                result.push(PortableStorageSlot {
+                    shard_id: shard_id as u8,
+                    address: *address,
+                    key: *key,
+                    value: *value,
+                });
+            }
+        }
+    }
+    result.sort_by_key(|el| (el.shard_id, el.address, el.key));
+
+    result
+}
+
+fn current_heaps<WT: VmWitnessTracer<8, EncodingModeProduction>>(
+    vm: &ConformanceVm<WT>,
+) -> (PortablePage, PortablePage) {
+    let ((heap_page, heap), (aux_heap_page, aux_heap)) = vm.memory.heaps.last().unwrap();
+
+    (
+        PortablePage::from_words(*heap_page, heap),
+        PortablePage::from_words(*aux_heap_page, aux_heap),
+    )
+}
+
+impl PortableInitialState {
+    /// Captures the state of the VM prepared for the conformance vector
+    pub fn from_conformance_vector(vector: &ConformanceVector) -> Self {
+        let vm = prepare_conformance_vm(vector, PortableWitnessRecorder::default());
+        let (heap, aux_heap) = current_heaps(&vm);
+
+        let mut storage: Vec<_> = vector
+            .initial
+            .storage
+            .iter()
+            .map(|(shard_id, address, key, value)| PortableStorageSlot {
+                shard_id: *shard_id,
+                address: *address,
+                key: *key,
+                value: *value,
+            })
+            .collect();
+        storage.sort_by_key(|el| (el.shard_id, el.address, el.key));
+
+        Self {
+            local_state: vm.local_state.clone(),
+            code_pages: vec![PortablePage::from_words(
+                BOOTLOADER_CODE_PAGE,
+                &assemble(&vector.program),
+            )],
+            heap,
+            aux_heap,
+            storage,
+            bytecodes: conformance_bytecodes(&vector.initial)
+                .into_iter()
+                .map(|(hash, words)| PortableBytecode { hash, words })
+                .collect(),
+            default_aa_code_hash: conformance_block_properties(&vector.initial)
+                .default_aa_code_hash,
+        }
+    }
+
+    fn restore(&self) -> anyhow::Result<ConformanceVm<PortableWitnessRecorder>> {
+        let callstack = &self.local_state.callstack;
+        if callstack.depth() != 1 || callstack.current.is_local_frame {
+            anyhow::bail!("only a single root frame can be restored");
+        }
+        let root = callstack.current;
+        let expected_heap_page =
+            CallStackEntry::<8, EncodingModeProduction>::heap_page_from_base(root.base_memory_page);
+        let expected_aux_heap_page =
+            CallStackEntry::<8, EncodingModeProduction>::aux_heap_page_from_base(
+                root.base_memory_page,
+            );
+        if self.heap.page != expected_heap_page.0 || self.aux_heap.page != expected_aux_heap_page.0
+        {
+            anyhow::bail!(
+                "heap pages {} and {} do not match the root frame's base page {}",
+                self.heap.page,
+                self.aux_heap.page,
+                root.base_memory_page.0
+            );
+        }
+        for (hash, bytecode) in self.bytecodes.iter().map(|el| (el.hash, &el.words)) {
+            let actual_hash = versioned_hash_of_words(bytecode);
+            if actual_hash != hash {
+                anyhow::bail!(
+                    "bytecode for hash {:#066x} actually has hash {:#066x}",
+                    hash,
+                    actual_hash
+                );
+            }
+        }
+
+        let mut tools = create_default_testing_tools();
+        tools.storage.populate(
+            self.storage
+                .iter()
+                .map(|el| (el.shard_id, el.address, el.key, el.value))
+                .collect(),
+        );
+        let mut decommittment_processor = SimpleDecommitter::<true>::new();
+        decommittment_processor.populate(
+            self.bytecodes
+                .iter()
+                .map(|el| (el.hash, el.words.clone()))
+                .collect(),
+        );
+        tools.memory.populate_code(
+            self.code_pages
+                .iter()
+                .map(|el| (el.page, el.to_words()))
+                .collect(),
+        );
+
+        let BasicTestingTools::<false> {
+            storage,
+            memory,
+            event_sink,
+            precompiles_processor,
+            ..
+        } = tools;
+        let mut vm: ConformanceVm<PortableWitnessRecorder> = VmState::empty_state(
+            storage,
+            memory,
+            event_sink,
+            precompiles_processor,
+            decommittment_processor,
+            PortableWitnessRecorder::default(),
+            BlockProperties {
+                default_aa_code_hash: self.default_aa_code_hash,
+                zkporter_is_available: false,
+            },
+        );
+
+        // creates root frame's memory, then we take the rest of the state as is
+        vm.push_bootloader_context(0, root);
+        vm.local_state = self.local_state.clone();
+        {
+            let ((_, heap), (_, aux_heap)) = vm.memory.heaps.last_mut().unwrap();
+            for (page, words) in [(&self.heap, heap), (&self.aux_heap, aux_heap)] {
+                let content = page.to_words();
+                if words.len() < content.len() {
+                    words.resize(content.len(), U256::zero());
+                }
+                words[..content.len()].copy_from_slice(&content);
+            }
+        }
+
+        Ok(vm)
+    }
+
+    /// Executes up to `cycles` cycles (and resolves pending exception) from this state
+    pub fn run(&self, cycles: usize) -> anyhow::Result<PortableFinalState> {
+        let mut vm = self.restore()?;
+        let (cycles_used, _) = run_conformance_cycles(&mut vm, cycles)?;
+        let (heap, aux_heap) = current_heaps(&vm);
+
+        let VmState {
+            local_state,
+            storage,
+            event_sink,
+            witness_tracer,
+            ..
+        } = vm;
+        let (_, events, l1_messages) = event_sink.flatten();
+
+        Ok(PortableFinalState {
+            local_state,
+            cycles_used,
+            heap,
+            aux_heap,
+            storage: non_zero_storage(&storage.inner),
+            events: events.into_iter().map(PortableEvent::from).collect(),
+            l1_messages: l1_messages.into_iter().map(PortableEvent::from).collect(),
+            witness: witness_tracer.witness,
+        })
+    }
+}
+
+fn compare_section<T: PartialEq + std::fmt::Debug>(
+    mismatches: &mut Vec<String>,
+    what: &str,
+    expected: &T,
+    actual: &T,
+) {
+    if expected != actual {
+        mismatches.push(format!(
+            "{}:\nexpected {:#?}\ngot {:#?}",
+            what, expected, actual
+        ));
+    }
+}
+
+fn compare_queries<T: PartialEq + std::fmt::Debug>(
+    mismatches: &mut Vec<String>,
+    what: &str,
+    expected: &[T],
+    actual: &[T],
+) {
+    if expected.len() != actual.len() {
+        mismatches.push(format!(
+            "{}: expected {} queries, got {}",
+            what,
+            expected.len(),
+            actual.len()
+        ));
+    }
+    // first divergence is the most useful one
+    if let Some((idx, (expected, actual))) = expected
+        .iter()
+        .zip(actual.iter())
+        .enumerate()
+        .find(|(_, (expected, actual))| expected != actual)
+    {
+        mismatches.push(format!(
+            "{}[{}]: expected {:?}, got {:?}",
+            what, idx, expected, actual
+        ));
+    }
+}
+
+impl PortableFinalState {
+    pub fn check(&self, actual: &PortableFinalState) -> anyhow::Result<()> {
+        let mut mismatches = vec![];
+        compare_section(
+            &mut mismatches,
+            "local state",
+            &self.local_state,
+            &actual.local_state,
+        );
+        compare_section(
+            &mut mismatches,
+            "cycles used",
+            &self.cycles_used,
+            &actual.cycles_used,
+        );
+        compare_section(&mut mismatches, "heap", &self.heap, &actual.heap);
+        compare_section(
+            &mut mismatches,
+            "aux heap",
+            &self.aux_heap,
+            &actual.aux_heap,
+        );
+        compare_section(&mut mismatches, "storage", &self.storage, &actual.storage);
+        compare_section(&mut mismatches, "events", &self.events, &actual.events);
+        compare_section(
+            &mut mismatches,
+            "l1 messages",
+            &self.l1_messages,
+            &actual.l1_messages,
+        );
+        compare_queries(
+            &mut mismatches,
+            "memory queries",
+            &self.witness.memory_queries,
+            &actual.witness.memory_queries,
+        );
+        compare_queries(
+            &mut mismatches,
+            "log queries",
+            &self.witness.log_queries,
+            &actual.witness.log_queries,
+        );
+        compare_queries(
+            &mut mismatches,
+            "decommittment queries",
+            &self.witness.decommittment_queries,
+            &actual.witness.decommittment_queries,
+        );
+
+        if mismatches.is_empty() == false {
+            anyhow::bail!("{}", mismatches.join("\n"));
+        }
+
+        Ok(())
+    }
+}
+
+impl PortableVector {
+    /// Checks the vector's own expectations and records the full resulting state
+    pub fn from_conformance_vector(vector: &ConformanceVector) -> anyhow::Result<Self> {
+        vector.run_and_check()?;
+        let initial = PortableInitialState::from_conformance_vector(vector);
+        let expected = initial.run(vector.cycles)?;
+
+        Ok(Self {
+            name: vector.name.clone(),
+            cycles: vector.cycles,
+            initial,
+            expected,
+        })
+    }
+
+    pub fn run_and_check(&self) -> anyhow::Result<PortableFinalState> {
+        let actual = self.initial.run(self.cycles)?;
+        self.expected
+            .check(&actual)
+            .map_err(|e| anyhow::anyhow!("vector `{}` failed:\n{}", self.name, e))?;
+
+        Ok(actual)
+    }
+
+    pub fn to_json_string(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json_str(input: &str) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(input)?)
+    }
+
+    pub fn save_to_file(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_json_string()?)
+            .map_err(|e| anyhow::anyhow!("failed to write vector {:?}: {}", path, e))
+    }
+
+    pub fn load_from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("failed to read vector {:?}: {}", path, e))?;
+
+        Self::from_json_str(&content)
+    }
+}
+
+/// Writes every vector as `<name>.json` into the directory and returns the paths
+pub fn export_conformance_vectors(
+    vectors: &[ConformanceVector],
+    dir: impl AsRef<Path>,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let dir = dir.as_ref();
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::with_capacity(vectors.len());
+    for vector in vectors.iter() {
+        let path = dir.join(format!("{}.json", vector.name));
+        PortableVector::from_conformance_vector(vector)?.save_to_file(&path)?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+/// Runs every `.json` vector in the directory, returns names of the vectors that passed
+pub fn run_portable_vectors_from_dir(dir: impl AsRef<Path>) -> anyhow::Result<Vec<String>> {
+    let mut paths: Vec<_> = std::fs::read_dir(dir.as_ref())?
+        .map(|el| el.map(|el| el.path()))
+        .collect::<Result<_, _>>()?;
+    paths.retain(|el| el.extension().and_then(|el| el.to_str()) == Some("json"));
+    paths.sort();
+
+    let mut passed = vec![];
+    let mut failures = vec![];
+    for path in paths.iter() {
+        let vector = PortableVector::load_from_file(path)?;
+        match vector.run_and_check() {
+            Ok(_) => passed.push(vector.name),
+            Err(e) => failures.push(e.to_string()),
+        }
+    }
+    if failures.is_empty() == false {
+        anyhow::bail!("{}", failures.join("\n\n"));
+    }
+
+    Ok(passed)
+}
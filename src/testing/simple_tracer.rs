@@ -1,4 +1,5 @@
 use crate::tracing::*;
+use zk_evm_abstractions::vm::Memory;
 
 #[derive(Debug, Clone, Copy)]
 pub struct NoopTracer;
@@ -33,75 +34,166 @@ impl Tracer for NoopTracer {
     }
 }
 
-// pub struct ClosureBasedTracer<
-//     I,
-//     F0: FnMut(&mut I, VmLocalStateData<'_>, &dyn Memory) -> (),
-//     F1: FnMut(&mut I, VmLocalStateData<'_>, AfterDecodingData, &dyn Memory) -> (),
-//     F2: FnMut(&mut I, VmLocalStateData<'_>, BeforeExecutionData, &dyn Memory) -> (),
-//     F3: FnMut(&mut I, VmLocalStateData<'_>, AfterExecutionData, &dyn Memory) -> (),
-//     const BEFORE_DECODING: bool,
-//     const AFTER_DECODING: bool,
-//     const BEFORE_EXECUTION: bool,
-//     const AFTER_EXECUTION: bool,
-// > {
-//     pub inner_state: I,
-//     pub before_decoding: Option<F0>,
-//     pub after_decoding: Option<F1>,
-//     pub before_execution: Option<F2>,
-//     pub after_execution: Option<F3>,
-// }
+/// Calls closures (if any) with the tracer's inner state, so tests can inspect execution
+/// without declaring a new tracer type
+pub struct ClosureBasedTracer<
+    I,
+    M,
+    F0: FnMut(&mut I, VmLocalStateData<'_>, &M),
+    F1: FnMut(&mut I, VmLocalStateData<'_>, AfterDecodingData, &M),
+    F2: FnMut(&mut I, VmLocalStateData<'_>, BeforeExecutionData, &M),
+    F3: FnMut(&mut I, VmLocalStateData<'_>, AfterExecutionData, &M),
+    const BEFORE_DECODING: bool,
+    const AFTER_DECODING: bool,
+    const BEFORE_EXECUTION: bool,
+    const AFTER_EXECUTION: bool,
+> {
+    pub inner_state: I,
+    pub before_decoding: Option<F0>,
+    pub after_decoding: Option<F1>,
+    pub before_execution: Option<F2>,
+    pub after_execution: Option<F3>,
+    _marker: std::marker::PhantomData<fn(&M)>,
+}
+
+impl<
+        I,
+        M,
+        F0: FnMut(&mut I, VmLocalStateData<'_>, &M),
+        F1: FnMut(&mut I, VmLocalStateData<'_>, AfterDecodingData, &M),
+        F2: FnMut(&mut I, VmLocalStateData<'_>, BeforeExecutionData, &M),
+        F3: FnMut(&mut I, VmLocalStateData<'_>, AfterExecutionData, &M),
+        const BEFORE_DECODING: bool,
+        const AFTER_DECODING: bool,
+        const BEFORE_EXECUTION: bool,
+        const AFTER_EXECUTION: bool,
+    >
+    ClosureBasedTracer<
+        I,
+        M,
+        F0,
+        F1,
+        F2,
+        F3,
+        BEFORE_DECODING,
+        AFTER_DECODING,
+        BEFORE_EXECUTION,
+        AFTER_EXECUTION,
+    >
+{
+    pub fn new(
+        inner_state: I,
+        before_decoding: Option<F0>,
+        after_decoding: Option<F1>,
+        before_execution: Option<F2>,
+        after_execution: Option<F3>,
+    ) -> Self {
+        Self {
+            inner_state,
+            before_decoding,
+            after_decoding,
+            before_execution,
+            after_execution,
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
 
-// impl<
-//     I: 'static,
-//     F0: FnMut(&mut I, VmLocalStateData<'_>, &dyn Memory) -> () + 'static,
-//     F1: FnMut(&mut I, VmLocalStateData<'_>, AfterDecodingData, &dyn Memory) -> () + 'static,
-//     F2: FnMut(&mut I, VmLocalStateData<'_>, BeforeExecutionData, &dyn Memory) -> () + 'static,
-//     F3: FnMut(&mut I, VmLocalStateData<'_>, AfterExecutionData, &dyn Memory) -> () + 'static,
-//     const BEFORE_DECODING: bool,
-//     const AFTER_DECODING: bool,
-//     const BEFORE_EXECUTION: bool,
-//     const AFTER_EXECUTION: bool,
-// > Tracer for ClosureBasedTracer<I, F0, F1, F2, F3, BEFORE_DECODING, AFTER_DECODING, BEFORE_EXECUTION, AFTER_EXECUTION> {
-//     const CALL_BEFORE_DECODING: bool = BEFORE_DECODING;
-//     const CALL_AFTER_DECODING: bool = AFTER_DECODING;
-//     const CALL_BEFORE_EXECUTION: bool = BEFORE_EXECUTION;
-//     const CALL_AFTER_EXECUTION: bool = AFTER_EXECUTION;
+impl<
+        I,
+        M: Memory,
+        F0: FnMut(&mut I, VmLocalStateData<'_>, &M),
+        F1: FnMut(&mut I, VmLocalStateData<'_>, AfterDecodingData, &M),
+        F2: FnMut(&mut I, VmLocalStateData<'_>, BeforeExecutionData, &M),
+        F3: FnMut(&mut I, VmLocalStateData<'_>, AfterExecutionData, &M),
+        const BEFORE_DECODING: bool,
+        const AFTER_DECODING: bool,
+        const BEFORE_EXECUTION: bool,
+        const AFTER_EXECUTION: bool,
+    > Tracer
+    for ClosureBasedTracer<
+        I,
+        M,
+        F0,
+        F1,
+        F2,
+        F3,
+        BEFORE_DECODING,
+        AFTER_DECODING,
+        BEFORE_EXECUTION,
+        AFTER_EXECUTION,
+    >
+{
+    const CALL_BEFORE_DECODING: bool = BEFORE_DECODING;
+    const CALL_AFTER_DECODING: bool = AFTER_DECODING;
+    const CALL_BEFORE_EXECUTION: bool = BEFORE_EXECUTION;
+    const CALL_AFTER_EXECUTION: bool = AFTER_EXECUTION;
 
-//     fn before_decoding(&mut self, state: VmLocalStateData<'_>, memory: &dyn Memory) {
-//         if let Some(c) = self.before_decoding.as_mut() {
-//             (c)(&mut self.inner_state, state, memory)
-//         }
-//     }
-//     fn after_decoding(&mut self, state: VmLocalStateData<'_>, data: AfterDecodingData, memory: &dyn Memory) {
-//         if let Some(c) = self.after_decoding.as_mut() {
-//             c(&mut self.inner_state, state, data, memory)
-//         }
-//     }
-//     fn before_execution(&mut self, state: VmLocalStateData<'_>, data: BeforeExecutionData, memory: &dyn Memory) {
-//         if let Some(c) = self.before_execution.as_mut() {
-//             c(&mut self.inner_state, state, data, memory)
-//         }
-//     }
-//     fn after_execution(&mut self, state: VmLocalStateData<'_>, data: AfterExecutionData, memory: &dyn Memory) {
-//         if let Some(c) = self.after_execution.as_mut() {
-//             c(&mut self.inner_state, state, data, memory)
-//         }
-//     }
-// }
+    type SupportedMemory = M;
 
-// impl<
-//     I,
-//     F0: FnMut(&mut I, VmLocalStateData<'_>, &dyn Memory) -> (),
-//     F1: FnMut(&mut I, VmLocalStateData<'_>, AfterDecodingData, &dyn Memory) -> (),
-//     F2: FnMut(&mut I, VmLocalStateData<'_>, BeforeExecutionData, &dyn Memory) -> (),
-//     F3: FnMut(&mut I, VmLocalStateData<'_>, AfterExecutionData, &dyn Memory) -> (),
-//     const BEFORE_DECODING: bool,
-//     const AFTER_DECODING: bool,
-//     const BEFORE_EXECUTION: bool,
-//     const AFTER_EXECUTION: bool,
-// > std::fmt::Debug for ClosureBasedTracer<I, F0, F1, F2, F3, BEFORE_DECODING, AFTER_DECODING, BEFORE_EXECUTION, AFTER_EXECUTION> {
-//     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-//         f.debug_struct(&"ClosureBasedTracer")
-//             .finish()
-//     }
-// }
+    fn before_decoding(&mut self, state: VmLocalStateData<'_>, memory: &Self::SupportedMemory) {
+        if let Some(c) = self.before_decoding.as_mut() {
+            c(&mut self.inner_state, state, memory)
+        }
+    }
+    fn after_decoding(
+        &mut self,
+        state: VmLocalStateData<'_>,
+        data: AfterDecodingData,
+        memory: &Self::SupportedMemory,
+    ) {
+        if let Some(c) = self.after_decoding.as_mut() {
+            c(&mut self.inner_state, state, data, memory)
+        }
+    }
+    fn before_execution(
+        &mut self,
+        state: VmLocalStateData<'_>,
+        data: BeforeExecutionData,
+        memory: &Self::SupportedMemory,
+    ) {
+        if let Some(c) = self.before_execution.as_mut() {
+            c(&mut self.inner_state, state, data, memory)
+        }
+    }
+    fn after_execution(
+        &mut self,
+        state: VmLocalStateData<'_>,
+        data: AfterExecutionData,
+        memory: &Self::SupportedMemory,
+    ) {
+        if let Some(c) = self.after_execution.as_mut() {
+            c(&mut self.inner_state, state, data, memory)
+        }
+    }
+}
+
+impl<
+        I,
+        M,
+        F0: FnMut(&mut I, VmLocalStateData<'_>, &M),
+        F1: FnMut(&mut I, VmLocalStateData<'_>, AfterDecodingData, &M),
+        F2: FnMut(&mut I, VmLocalStateData<'_>, BeforeExecutionData, &M),
+        F3: FnMut(&mut I, VmLocalStateData<'_>, AfterExecutionData, &M),
+        const BEFORE_DECODING: bool,
+        const AFTER_DECODING: bool,
+        const BEFORE_EXECUTION: bool,
+        const AFTER_EXECUTION: bool,
+    > std::fmt::Debug
+    for ClosureBasedTracer<
+        I,
+        M,
+        F0,
+        F1,
+        F2,
+        F3,
+        BEFORE_DECODING,
+        AFTER_DECODING,
+        BEFORE_EXECUTION,
+        AFTER_EXECUTION,
+    >
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClosureBasedTracer").finish()
+    }
+}
@@ -0,0 +1,407 @@
+use super::*;
+
+use crate::block_properties::BlockProperties;
+use crate::errors::OracleViolation;
+use crate::reference_impls::checked::*;
+use crate::reference_impls::decommitter::SimpleDecommitter;
+use crate::testing::bootloader_harness::bootloader_initial_context;
+use crate::testing::conformance::*;
+use crate::tracing::*;
+use crate::vm_state::{PrimitiveValue, VmState};
+use crate::witness_trace::DummyTracer;
+use zk_evm_abstractions::aux::*;
+use zk_evm_abstractions::queries::{DecommittmentQuery, LogQuery, MemoryQuery};
+use zk_evm_abstractions::vm::{DecommittmentProcessor, EventSink, Memory, MemoryType, Storage};
+use zkevm_opcode_defs::system_params::DEPLOYER_SYSTEM_CONTRACT_ADDRESS;
+use zkevm_opcode_defs::*;
+
+// Drops all writes to heaps and stacks
+#[derive(Debug)]
+struct ForgetfulMemory(SimpleMemory);
+
+impl Memory for ForgetfulMemory {
+    fn execute_partial_query(
+        &mut self,
+        monotonic_cycle_counter: u32,
+        query: MemoryQuery,
+    ) -> MemoryQuery {
+        if query.rw_flag {
+            return query;
+        }
+        self.0.execute_partial_query(monotonic_cycle_counter, query)
+    }
+    fn specialized_code_query(
+        &mut self,
+        monotonic_cycle_counter: u32,
+        query: MemoryQuery,
+    ) -> MemoryQuery {
+        self.0
+            .specialized_code_query(monotonic_cycle_counter, query)
+    }
+    fn read_code_query(&self, monotonic_cycle_counter: u32, query: MemoryQuery) -> MemoryQuery {
+        self.0.read_code_query(monotonic_cycle_counter, query)
+    }
+    fn start_global_frame(
+        &mut self,
+        current_base_page: MemoryPage,
+        new_base_page: MemoryPage,
+        calldata_fat_pointer: FatPointer,
+        timestamp: Timestamp,
+    ) {
+        self.0.start_global_frame(
+            current_base_page,
+            new_base_page,
+            calldata_fat_pointer,
+            timestamp,
+        )
+    }
+    fn finish_global_frame(
+        &mut self,
+        base_page: MemoryPage,
+        returndata_fat_pointer: FatPointer,
+        timestamp: Timestamp,
+    ) {
+        self.0
+            .finish_global_frame(base_page, returndata_fat_pointer, timestamp)
+    }
+}
+
+// Never reverts writes of panicked frames
+#[derive(Debug)]
+struct NonRevertingStorage(InMemoryStorage);
+
+impl Storage for NonRevertingStorage {
+    fn estimate_refunds_for_write(
+        &mut self,
+        monotonic_cycle_counter: u32,
+        partial_query: &LogQuery,
+    ) -> zk_evm_abstractions::vm::RefundType {
+        self.0
+            .estimate_refunds_for_write(monotonic_cycle_counter, partial_query)
+    }
+    fn execute_partial_query(&mut self, monotonic_cycle_counter: u32, query: LogQuery) -> LogQuery {
+        self.0.execute_partial_query(monotonic_cycle_counter, query)
+    }
+    fn start_frame(&mut self, timestamp: Timestamp) {
+        self.0.start_frame(timestamp)
+    }
+    fn finish_frame(&mut self, timestamp: Timestamp, _panicked: bool) {
+        self.0.finish_frame(timestamp, false)
+    }
+}
+
+#[derive(Debug)]
+struct CheckedMemoryTracer;
+
+impl Tracer for CheckedMemoryTracer {
+    type SupportedMemory = CheckedMemory<SimpleMemory>;
+
+    fn before_decoding(&mut self, _state: VmLocalStateData<'_>, _memory: &Self::SupportedMemory) {}
+    fn after_decoding(
+        &mut self,
+        _state: VmLocalStateData<'_>,
+        _data: AfterDecodingData,
+        _memory: &Self::SupportedMemory,
+    ) {
+    }
+    fn before_execution(
+        &mut self,
+        _state: VmLocalStateData<'_>,
+        _data: BeforeExecutionData,
+        _memory: &Self::SupportedMemory,
+    ) {
+    }
+    fn after_execution(
+        &mut self,
+        _state: VmLocalStateData<'_>,
+        _data: AfterExecutionData,
+        _memory: &Self::SupportedMemory,
+    ) {
+    }
+}
+
+fn heap_query(page: u32, index: u32, timestamp: u32, value: Option<U256>) -> MemoryQuery {
+    MemoryQuery {
+        timestamp: Timestamp(timestamp),
+        location: MemoryLocation {
+            memory_type: MemoryType::Heap,
+            page: MemoryPage(page),
+            index: MemoryIndex(index),
+        },
+        value: value.unwrap_or(U256::zero()),
+        rw_flag: value.is_some(),
+        value_is_pointer: false,
+    }
+}
+
+fn write_and_read_heap<M: Memory>(memory: &mut CheckedMemory<M>) {
+    let base_page = MemoryPage(BOOTLOADER_BASE_PAGE);
+    let heap_page = base_page.0 + 2;
+    memory.start_global_frame(MemoryPage(0), base_page, FatPointer::empty(), Timestamp(0));
+    memory.execute_partial_query(0, heap_query(heap_page, 3, 1, Some(U256::from(42u64))));
+    memory.execute_partial_query(1, heap_query(heap_page, 3, 5, None));
+}
+
+#[test]
+fn memory_read_of_lost_write_is_reported() {
+    let mut memory = CheckedMemory::recording(SimpleMemory::new());
+    write_and_read_heap(&mut memory);
+    assert!(memory.violations().is_empty());
+
+    let mut memory = CheckedMemory::recording(ForgetfulMemory(SimpleMemory::new()));
+    write_and_read_heap(&mut memory);
+    assert!(matches!(
+        memory.violations().as_slice(),
+        [OracleViolation::MemoryReadMismatch {
+            index: 3,
+            timestamp: 5,
+            written_at: 1,
+            ..
+        }]
+    ));
+}
+
+#[test]
+fn memory_can_be_wrapped_inside_running_frame() {
+    let base_page = MemoryPage(BOOTLOADER_BASE_PAGE);
+    let stack_page = base_page.0 + 1;
+    let mut inner = SimpleMemory::new();
+    inner.start_global_frame(MemoryPage(0), base_page, FatPointer::empty(), Timestamp(0));
+    let mut memory = CheckedMemory::recording(inner).with_running_frames([base_page]);
+
+    let mut query = heap_query(stack_page, 0, 1, Some(U256::one()));
+    query.location.memory_type = MemoryType::Stack;
+    memory.execute_partial_query(0, query);
+    assert!(memory.violations().is_empty());
+}
+
+// Keeps messages of panicked frames
+#[derive(Debug)]
+struct NonRevertingEventSink(InMemoryEventSink);
+
+impl EventSink for NonRevertingEventSink {
+    fn add_partial_query(&mut self, monotonic_cycle_counter: u32, query: LogQuery) {
+        self.0.add_partial_query(monotonic_cycle_counter, query)
+    }
+    fn start_frame(&mut self, timestamp: Timestamp) {
+        self.0.start_frame(timestamp)
+    }
+    fn finish_frame(&mut self, _panicked: bool, timestamp: Timestamp) {
+        self.0.finish_frame(false, timestamp)
+    }
+}
+
+impl InspectableEventSink for NonRevertingEventSink {
+    fn alive_message_timestamps(&self) -> Vec<u32> {
+        self.0.alive_message_timestamps()
+    }
+}
+
+fn event_query(timestamp: u32) -> LogQuery {
+    LogQuery {
+        timestamp: Timestamp(timestamp),
+        tx_number_in_block: 0,
+        aux_byte: zkevm_opcode_defs::system_params::EVENT_AUX_BYTE,
+        shard_id: 0,
+        address: callee_address(),
+        key: U256::from(timestamp),
+        read_value: U256::zero(),
+        written_value: U256::one(),
+        rw_flag: true,
+        rollback: false,
+        is_service: true,
+    }
+}
+
+// message in the outer frame, then in the successful child and in the panicked one
+fn emit_events_with_panicked_frame<EV: InspectableEventSink>(sink: &mut CheckedEventSink<EV>) {
+    sink.start_frame(Timestamp(0));
+    sink.add_partial_query(0, event_query(1));
+    sink.start_frame(Timestamp(2));
+    sink.add_partial_query(1, event_query(3));
+    sink.finish_frame(false, Timestamp(4));
+    sink.start_frame(Timestamp(5));
+    sink.add_partial_query(2, event_query(6));
+    sink.finish_frame(true, Timestamp(7));
+}
+
+#[test]
+fn event_sink_without_rollbacks_is_reported() {
+    let mut sink = CheckedEventSink::recording(InMemoryEventSink::new());
+    emit_events_with_panicked_frame(&mut sink);
+    assert!(sink.violations().is_empty());
+    let mut alive = sink.inner().alive_message_timestamps();
+    alive.sort();
+    assert_eq!(alive, vec![1, 3]);
+
+    let mut sink = CheckedEventSink::recording(NonRevertingEventSink(InMemoryEventSink::new()));
+    emit_events_with_panicked_frame(&mut sink);
+    assert_eq!(
+        sink.violations(),
+        vec![OracleViolation::EventRollbackMismatch {
+            timestamp: 6,
+            is_missing: false,
+            panicked: true,
+        }]
+    );
+}
+
+fn callee_address() -> Address {
+    Address::from_low_u64_be((1 << 16) + 1)
+}
+
+// writes into caller's storage slot 0 and panics
+fn callee_code() -> Vec<U256> {
+    assemble(&[
+        Instruction::new(Opcode::Add(AddOpcode::Add))
+            .src0_imm(5)
+            .dst0(3)
+            .encode(),
+        Instruction::new(Opcode::Log(LogOpcode::StorageWrite))
+            .src1(3)
+            .encode(),
+        Instruction::new(Opcode::Ret(RetOpcode::Panic)).encode(),
+    ])
+}
+
+fn storage_with_slot_and_callee() -> InMemoryStorage {
+    let mut storage = InMemoryStorage::new();
+    storage.populate(vec![
+        (
+            0,
+            Address::from_low_u64_be(system_params::ADDRESS_BOOTLOADER as u64),
+            U256::zero(),
+            U256::from(7u64),
+        ),
+        (
+            0,
+            *DEPLOYER_SYSTEM_CONTRACT_ADDRESS,
+            address_to_u256(&callee_address()),
+            versioned_hash_of_words(&callee_code()),
+        ),
+    ]);
+
+    storage
+}
+
+type CheckedVm<S> = VmState<
+    CheckedStorage<S>,
+    CheckedMemory<SimpleMemory>,
+    InMemoryEventSink,
+    DefaultPrecompilesProcessor<false>,
+    CheckedDecommitter<SimpleDecommitter<false>>,
+    DummyTracer,
+>;
+
+// delegate call into the callee, then read slot 0 in the exception handler
+fn run_reverted_delegate_call<S: Storage>(storage: S) -> CheckedVm<S> {
+    let program = [
+        Instruction::new(Opcode::FarCall(FarCallOpcode::Delegate))
+            .src1(2)
+            .imm0(1)
+            .encode(),
+        Instruction::new(Opcode::Log(LogOpcode::StorageRead))
+            .dst0(4)
+            .encode(),
+    ];
+    let mut memory = SimpleMemory::new();
+    memory.populate_code(vec![(BOOTLOADER_CODE_PAGE, assemble(&program))]);
+    let mut decommitter = SimpleDecommitter::<false>::new();
+    decommitter.populate(vec![(
+        versioned_hash_of_words(&callee_code()),
+        callee_code(),
+    )]);
+
+    let mut vm: CheckedVm<S> = VmState::empty_state(
+        CheckedStorage::recording(storage),
+        CheckedMemory::recording(memory),
+        InMemoryEventSink::new(),
+        DefaultPrecompilesProcessor::<false>,
+        CheckedDecommitter::recording(decommitter),
+        DummyTracer,
+        BlockProperties {
+            default_aa_code_hash: U256::zero(),
+            zkporter_is_available: false,
+        },
+    );
+    vm.push_bootloader_context(0, bootloader_initial_context(100_000, 0));
+    vm.local_state.memory_page_counter = BOOTLOADER_BASE_PAGE + NEW_MEMORY_PAGES_PER_FAR_CALL;
+    vm.local_state.registers[1] = PrimitiveValue::from_value(address_to_u256(&callee_address()));
+
+    // far call, three cycles of the callee and read in the handler
+    for _ in 0..5 {
+        vm.cycle(&mut CheckedMemoryTracer).unwrap();
+    }
+
+    vm
+}
+
+#[test]
+fn reference_oracles_pass_checks() {
+    let vm = run_reverted_delegate_call(storage_with_slot_and_callee());
+
+    assert_eq!(vm.local_state.registers[3].value, U256::from(7u64));
+    assert!(vm.storage.violations().is_empty());
+    assert!(vm.memory.violations().is_empty());
+    assert!(vm.decommittment_processor.violations().is_empty());
+}
+
+#[test]
+fn storage_without_rollbacks_is_reported() {
+    let vm = run_reverted_delegate_call(NonRevertingStorage(storage_with_slot_and_callee()));
+
+    assert_eq!(vm.local_state.registers[3].value, U256::from(5u64));
+    assert!(matches!(
+        vm.storage.violations().as_slice(),
+        [OracleViolation::StorageRollbackMismatch { expected, actual, .. }]
+            if *expected == U256::from(7u64) && *actual == U256::from(5u64)
+    ));
+}
+
+fn decommittment_query(hash: U256, page: u32) -> DecommittmentQuery {
+    DecommittmentQuery {
+        hash,
+        timestamp: Timestamp(1),
+        memory_page: MemoryPage(page),
+        decommitted_length: 0,
+        is_fresh: false,
+    }
+}
+
+fn decommitter_with_wrong_length() -> (SimpleDecommitter<false>, U256) {
+    // hash declares 3 words, but decommitter knows only one
+    let hash = versioned_hash_of_words(&[U256::one(), U256::one(), U256::one()]);
+    let mut decommitter = SimpleDecommitter::<false>::new();
+    decommitter.populate(vec![(hash, vec![U256::one()])]);
+
+    (decommitter, hash)
+}
+
+#[test]
+fn decommitted_length_is_checked_against_hash() {
+    let (decommitter, hash) = decommitter_with_wrong_length();
+    let mut decommitter = CheckedDecommitter::recording(decommitter);
+    let mut memory = SimpleMemory::new();
+
+    decommitter
+        .decommit_into_memory(0, decommittment_query(hash, 100), &mut memory)
+        .unwrap();
+    assert_eq!(
+        decommitter.violations(),
+        vec![OracleViolation::DecommittedLengthMismatch {
+            hash,
+            declared_length_in_words: 3,
+            decommitted_length_in_words: 1,
+        }]
+    );
+}
+
+#[test]
+#[should_panic(expected = "oracle violation")]
+fn checked_decommitter_panics_by_default() {
+    let (decommitter, hash) = decommitter_with_wrong_length();
+    let mut decommitter = CheckedDecommitter::new(decommitter);
+    let mut memory = SimpleMemory::new();
+
+    let _ = decommitter.decommit_into_memory(0, decommittment_query(hash, 100), &mut memory);
+}
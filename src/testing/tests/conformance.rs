@@ -0,0 +1,329 @@
+use super::*;
+
+use crate::testing::conformance::*;
+use zkevm_opcode_defs::decoding::{EncodingModeProduction, VmEncodingMode};
+use zkevm_opcode_defs::*;
+
+#[test]
+fn run_default_conformance_vectors() {
+    let vectors = default_conformance_vectors();
+    let mut failures = vec![];
+    for vector in vectors.iter() {
+        if let Err(e) = vector.run_and_check() {
+            failures.push(e.to_string());
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
+
+#[test]
+fn vector_names_are_unique() {
+    let vectors = default_conformance_vectors();
+    let names: HashSet<_> = vectors.iter().map(|el| el.name.clone()).collect();
+    assert_eq!(names.len(), vectors.len());
+}
+
+// (vector, variant it must execute, flags of the variant)
+const REQUIRED_SCENARIOS: &[(&str, Opcode, [bool; 2])] = &[
+    // arithmetic flag semantics
+    ("nop", Opcode::Nop(NopOpcode), [false, false]),
+    (
+        "add_set_flags_overflow",
+        Opcode::Add(AddOpcode::Add),
+        [true, false],
+    ),
+    (
+        "add_keeps_flags",
+        Opcode::Add(AddOpcode::Add),
+        [false, false],
+    ),
+    (
+        "sub_set_flags_underflow",
+        Opcode::Sub(SubOpcode::Sub),
+        [true, false],
+    ),
+    (
+        "sub_swapped_operands",
+        Opcode::Sub(SubOpcode::Sub),
+        [false, true],
+    ),
+    (
+        "mul_set_flags_overflow",
+        Opcode::Mul(MulOpcode),
+        [true, false],
+    ),
+    ("div_by_zero", Opcode::Div(DivOpcode), [true, false]),
+    ("shl", Opcode::Shift(ShiftOpcode::Shl), [true, false]),
+    (
+        "shr_to_zero",
+        Opcode::Shift(ShiftOpcode::Shr),
+        [true, false],
+    ),
+    ("rol", Opcode::Shift(ShiftOpcode::Rol), [true, false]),
+    ("ror", Opcode::Shift(ShiftOpcode::Ror), [true, false]),
+    ("xor", Opcode::Binop(BinopOpcode::Xor), [true, false]),
+    (
+        "and_set_flags_zero",
+        Opcode::Binop(BinopOpcode::And),
+        [true, false],
+    ),
+    ("or", Opcode::Binop(BinopOpcode::Or), [true, false]),
+    // control flow
+    ("jump_reg", Opcode::Jump(JumpOpcode), [false, false]),
+    (
+        "near_call_and_ret_ok",
+        Opcode::NearCall(NearCallOpcode),
+        [false, false],
+    ),
+    // context
+    (
+        "context_this",
+        Opcode::Context(ContextOpcode::This),
+        [false, false],
+    ),
+    (
+        "context_get_context_u128",
+        Opcode::Context(ContextOpcode::GetContextU128),
+        [false, false],
+    ),
+    (
+        "context_caller",
+        Opcode::Context(ContextOpcode::Caller),
+        [false, false],
+    ),
+    (
+        "context_code_address",
+        Opcode::Context(ContextOpcode::CodeAddress),
+        [false, false],
+    ),
+    (
+        "context_meta",
+        Opcode::Context(ContextOpcode::Meta),
+        [false, false],
+    ),
+    (
+        "context_ergs_left",
+        Opcode::Context(ContextOpcode::ErgsLeft),
+        [false, false],
+    ),
+    (
+        "context_sp",
+        Opcode::Context(ContextOpcode::Sp),
+        [false, false],
+    ),
+    (
+        "context_set_context_u128",
+        Opcode::Context(ContextOpcode::SetContextU128),
+        [false, false],
+    ),
+    (
+        "context_set_ergs_per_pubdata",
+        Opcode::Context(ContextOpcode::SetErgsPerPubdataByte),
+        [false, false],
+    ),
+    (
+        "context_increment_tx_number",
+        Opcode::Context(ContextOpcode::IncrementTxNumber),
+        [false, false],
+    ),
+    // ptr ops
+    ("ptr_add", Opcode::Ptr(PtrOpcode::Add), [false, false]),
+    ("ptr_sub", Opcode::Ptr(PtrOpcode::Sub), [false, false]),
+    ("ptr_shrink", Opcode::Ptr(PtrOpcode::Shrink), [false, false]),
+    ("ptr_pack", Opcode::Ptr(PtrOpcode::Pack), [false, false]),
+    // UMA bounds and growth
+    (
+        "heap_write_grows_memory",
+        Opcode::UMA(UMAOpcode::HeapWrite),
+        [false, false],
+    ),
+    (
+        "heap_read_in_bounds_is_free",
+        Opcode::UMA(UMAOpcode::HeapRead),
+        [false, false],
+    ),
+    (
+        "heap_write_with_increment",
+        Opcode::UMA(UMAOpcode::HeapWrite),
+        [true, false],
+    ),
+    (
+        "heap_read_with_increment",
+        Opcode::UMA(UMAOpcode::HeapRead),
+        [true, false],
+    ),
+    (
+        "aux_heap_write",
+        Opcode::UMA(UMAOpcode::AuxHeapWrite),
+        [false, false],
+    ),
+    (
+        "aux_heap_read",
+        Opcode::UMA(UMAOpcode::AuxHeapRead),
+        [false, false],
+    ),
+    (
+        "heap_read_beyond_u32_offset_panics",
+        Opcode::UMA(UMAOpcode::HeapRead),
+        [false, false],
+    ),
+    (
+        "heap_write_without_ergs_for_growth_panics",
+        Opcode::UMA(UMAOpcode::HeapWrite),
+        [false, false],
+    ),
+    (
+        "fat_pointer_read_of_integer_panics",
+        Opcode::UMA(UMAOpcode::FatPointerRead),
+        [false, false],
+    ),
+    (
+        "fat_pointer_read_out_of_bounds_gives_zero",
+        Opcode::UMA(UMAOpcode::FatPointerRead),
+        [true, false],
+    ),
+    // log
+    (
+        "storage_write_and_read",
+        Opcode::Log(LogOpcode::StorageWrite),
+        [false, false],
+    ),
+    (
+        "storage_write_and_read",
+        Opcode::Log(LogOpcode::StorageRead),
+        [false, false],
+    ),
+    ("event", Opcode::Log(LogOpcode::Event), [true, false]),
+    (
+        "l1_message",
+        Opcode::Log(LogOpcode::ToL1Message),
+        [true, false],
+    ),
+    (
+        "precompile_call_requires_kernel_mode",
+        Opcode::Log(LogOpcode::PrecompileCall),
+        [false, false],
+    ),
+    // far call masking rules
+    (
+        "far_call_of_empty_address_uses_default_account",
+        Opcode::FarCall(FarCallOpcode::Normal),
+        [false, false],
+    ),
+    (
+        "far_call_of_empty_kernel_address_panics",
+        Opcode::FarCall(FarCallOpcode::Normal),
+        [false, false],
+    ),
+    (
+        "far_call_static_callee_can_not_write",
+        Opcode::FarCall(FarCallOpcode::Normal),
+        [true, false],
+    ),
+    (
+        "far_call_to_user_space_masks_system_call",
+        Opcode::FarCall(FarCallOpcode::Normal),
+        [false, false],
+    ),
+    (
+        "far_call_delegate_writes_caller_storage",
+        Opcode::FarCall(FarCallOpcode::Delegate),
+        [false, false],
+    ),
+    (
+        "far_call_mimic_sets_msg_sender",
+        Opcode::FarCall(FarCallOpcode::Mimic),
+        [false, false],
+    ),
+    // ret forwarding
+    (
+        "far_ret_ok_forwards_heap_slice",
+        Opcode::Ret(RetOpcode::Ok),
+        [false, false],
+    ),
+    (
+        "far_ret_with_malformed_slice_panics",
+        Opcode::Ret(RetOpcode::Ok),
+        [false, false],
+    ),
+    ("far_revert", Opcode::Ret(RetOpcode::Revert), [false, false]),
+    (
+        "explicit_panic",
+        Opcode::Ret(RetOpcode::Panic),
+        [false, false],
+    ),
+    (
+        "near_ret_to_label",
+        Opcode::Ret(RetOpcode::Ok),
+        [true, false],
+    ),
+];
+
+#[test]
+fn vectors_cover_required_scenarios() {
+    for (name, opcode, flags) in REQUIRED_SCENARIOS.iter() {
+        let vector = vector_by_name(name);
+        let executes_variant = vector.program.iter().any(|raw| {
+            let (decoded, _) =
+                EncodingModeProduction::parse_preliminary_variant_and_absolute_number(*raw);
+            decoded.variant.opcode == *opcode && decoded.variant.flags == *flags
+        });
+        assert!(
+            executes_variant,
+            "vector {} does not execute {:?} with flags {:?}",
+            name, opcode, flags
+        );
+    }
+}
+
+// every opcode that the decoder can produce must be executed by some vector
+#[test]
+fn vectors_cover_every_opcode_variant() {
+    let all_opcodes: HashSet<_> = (0..(1u64 << VARIANT_BITS))
+        .map(|idx| {
+            EncodingModeProduction::parse_preliminary_variant_and_absolute_number(idx)
+                .0
+                .variant
+                .opcode
+        })
+        .filter(|el| !matches!(el, Opcode::Invalid(_)))
+        .collect();
+    let used: HashSet<_> = default_conformance_vectors()
+        .iter()
+        .flat_map(|el| el.program.iter())
+        .map(|raw| {
+            EncodingModeProduction::parse_preliminary_variant_and_absolute_number(*raw)
+                .0
+                .variant
+                .opcode
+        })
+        .collect();
+
+    let mut missing: Vec<_> = all_opcodes.difference(&used).collect();
+    missing.sort_by_key(|el| format!("{:?}", el));
+    assert!(
+        missing.is_empty(),
+        "no conformance vector for {:?}",
+        missing
+    );
+}
+
+#[test]
+fn instruction_encoding_roundtrip() {
+    let instruction = Instruction::new(Opcode::Add(AddOpcode::Add))
+        .src0(1)
+        .src1(2)
+        .dst0(3)
+        .flag(SET_FLAGS_FLAG_IDX)
+        .condition(Condition::Eq);
+    let (decoded, _) =
+        EncodingModeProduction::parse_preliminary_variant_and_absolute_number(instruction.encode());
+
+    assert_eq!(decoded.variant.opcode, Opcode::Add(AddOpcode::Add));
+    assert!(decoded.variant.flags[SET_FLAGS_FLAG_IDX]);
+    assert!(matches!(decoded.condition, Condition::Eq));
+    assert_eq!(decoded.src0_reg_idx, 1);
+    assert_eq!(decoded.src1_reg_idx, 2);
+    assert_eq!(decoded.dst0_reg_idx, 3);
+}
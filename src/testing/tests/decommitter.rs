@@ -0,0 +1,105 @@
+use super::*;
+
+use crate::errors::BytecodeHashingError;
+use crate::reference_impls::decommitter::*;
+use zk_evm_abstractions::aux::Timestamp;
+use zk_evm_abstractions::queries::DecommittmentQuery;
+use zk_evm_abstractions::vm::DecommittmentProcessor;
+use zkevm_opcode_defs::{ContractCodeSha256, VersionedHashDef};
+
+fn query(hash: U256, page: u32) -> DecommittmentQuery {
+    DecommittmentQuery {
+        hash,
+        timestamp: Timestamp(1),
+        memory_page: MemoryPage(page),
+        decommitted_length: 0,
+        is_fresh: false,
+    }
+}
+
+#[test]
+fn hash_validates_bytecode_shape() {
+    assert_eq!(
+        hash_bytecode(&[0u8; 31]),
+        Err(BytecodeHashingError::LengthIsNotDivisibleBy32)
+    );
+    assert_eq!(
+        hash_bytecode(&[0u8; 64]),
+        Err(BytecodeHashingError::EvenNumberOfWords)
+    );
+
+    let hash = hash_bytecode(&[1u8; 96]).unwrap();
+    assert_eq!(code_length_in_words_from_hash(&hash), 3);
+    let mut buffer = [0u8; 32];
+    hash.to_big_endian(&mut buffer);
+    assert_eq!(buffer[0], ContractCodeSha256::VERSION_BYTE);
+    assert_eq!(buffer[1], ContractCodeSha256::CODE_AT_REST_MARKER);
+}
+
+#[test]
+fn decommitter_tracks_freshness_per_batch() {
+    let mut memory: SimpleMemory = SimpleMemory::new();
+    let mut decommitter = ContentAddressedDecommitter::<false>::new();
+    let hash = decommitter.add_bytecode(&[7u8; 32]).unwrap();
+
+    let (first, _) = decommitter
+        .decommit_into_memory(0, query(hash, 100), &mut memory)
+        .unwrap();
+    assert!(first.is_fresh);
+    assert_eq!(first.decommitted_length, 1);
+    let (second, _) = decommitter
+        .decommit_into_memory(1, query(hash, 200), &mut memory)
+        .unwrap();
+    assert!(!second.is_fresh);
+    assert_eq!(second.memory_page, MemoryPage(100));
+
+    assert!(decommitter
+        .decommit_into_memory(2, query(hash + U256::one(), 300), &mut memory)
+        .is_err());
+
+    let statistics = decommitter.finish_batch();
+    assert_eq!(statistics.fresh_decommittments, 1);
+    assert_eq!(statistics.repeated_decommittments, 1);
+    assert_eq!(statistics.unknown_hash_requests, 1);
+
+    let (next_batch, _) = decommitter
+        .decommit_into_memory(3, query(hash, 400), &mut memory)
+        .unwrap();
+    assert!(next_batch.is_fresh);
+}
+
+#[test]
+fn artifacts_decommitter_loads_lazily() {
+    use crate::reference_impls::artifacts_decommitter::*;
+
+    let dir = std::env::temp_dir().join(format!("zk_evm_artifacts_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let named_bytecode = [3u8; 96];
+    let named_hash = hash_bytecode(&named_bytecode).unwrap();
+    let mut buffer = [0u8; 32];
+    named_hash.to_big_endian(&mut buffer);
+    std::fs::write(dir.join(format!("0x{}.bin", hex::encode(buffer))), named_bytecode).unwrap();
+
+    let artifact_bytecode = [5u8; 32];
+    let artifact_hash = hash_bytecode(&artifact_bytecode).unwrap();
+    let artifact = format!("{{\"bytecode\": \"0x{}\"}}", hex::encode(artifact_bytecode));
+    std::fs::write(dir.join("Contract.json"), artifact).unwrap();
+
+    let mut memory: SimpleMemory = SimpleMemory::new();
+    let mut decommitter = ArtifactsDecommitter::<false>::from_directory(&dir);
+    let (named, _) = decommitter
+        .decommit_into_memory(0, query(named_hash, 100), &mut memory)
+        .unwrap();
+    assert_eq!(named.decommitted_length, 3);
+    let (from_artifact, _) = decommitter
+        .decommit_into_memory(1, query(artifact_hash, 200), &mut memory)
+        .unwrap();
+    assert_eq!(from_artifact.decommitted_length, 1);
+    assert_eq!(decommitter.cached_hashes().count(), 2);
+    assert!(decommitter
+        .decommit_into_memory(2, query(artifact_hash + U256::one(), 300), &mut memory)
+        .is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,63 @@
+use super::*;
+
+use crate::reference_impls::event_decoder::*;
+use zkevm_opcode_defs::system_params::ADDRESS_EVENT_WRITER;
+
+fn message(is_first: bool, tx_number_in_block: u16, key: U256, value: U256) -> EventMessage {
+    EventMessage {
+        shard_id: 0,
+        is_first,
+        tx_number_in_block,
+        address: Address::from_low_u64_be(ADDRESS_EVENT_WRITER as u64),
+        key,
+        value,
+    }
+}
+
+fn header(num_topics: u64, data_length: u64) -> U256 {
+    U256::from(num_topics | (data_length << 32))
+}
+
+#[test]
+fn merge_multi_word_event() {
+    let emitter = Address::from_low_u64_be(0x1234);
+    let emitter_topic = address_to_u256(&emitter);
+    let messages = vec![
+        // emitter + 2 topics, 40 bytes of data
+        message(true, 1, header(3, 40), emitter_topic),
+        message(false, 1, U256::from(1u64), U256::from(2u64)),
+        message(false, 1, U256::MAX, U256::MAX),
+        // next event without data
+        message(true, 2, header(1, 0), emitter_topic),
+    ];
+
+    let events = merge_events(&messages);
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].topics.len(), 3);
+    assert_eq!(events[0].data, vec![0xffu8; 40]);
+    assert_eq!(events[1].topics.len(), 1);
+    assert!(events[1].data.is_empty());
+
+    let log = events[0].to_ethereum_log(0).unwrap();
+    assert_eq!(log.address, emitter);
+    assert_eq!(log.topics.len(), 2);
+    assert_eq!(log.topics[1].to_low_u64_be(), 2);
+
+    let grouped = group_by_transaction(events, vec![]);
+    assert_eq!(grouped.len(), 2);
+    assert_eq!(grouped[0].tx_number_in_block, 1);
+    assert_eq!(grouped[1].ethereum_logs()[0].topics.len(), 0);
+}
+
+#[test]
+fn drop_incomplete_event() {
+    let messages = vec![
+        message(true, 0, header(4, 0), U256::zero()),
+        message(false, 0, U256::zero(), U256::zero()),
+        message(true, 0, header(1, 0), U256::zero()),
+    ];
+
+    let events = merge_events(&messages);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].topics.len(), 1);
+}
@@ -0,0 +1,112 @@
+use super::*;
+
+use std::time::Duration;
+
+use crate::errors::ExecutionLimitExceeded;
+use crate::testing::conformance::*;
+use crate::utils::GenericNoopTracer;
+use crate::vm_state::{ExecutionLimits, ExecutionStopReason};
+use crate::witness_trace::DummyTracer;
+
+fn run_with_limits(limits: ExecutionLimits) -> (ConformanceVm<DummyTracer>, ExecutionStopReason) {
+    let mut vm = prepare_conformance_vm(&vector_by_name("far_call_and_ret_ok"), DummyTracer);
+    vm.set_execution_limits(limits);
+    let reason = vm
+        .run(&mut GenericNoopTracer::<SimpleMemory>::new())
+        .unwrap();
+
+    (vm, reason)
+}
+
+#[test]
+fn cycles_limit_stops_between_cycles() {
+    let (mut vm, reason) = run_with_limits(ExecutionLimits {
+        max_cycles: Some(1),
+        ..ExecutionLimits::default()
+    });
+    assert_eq!(
+        reason,
+        ExecutionStopReason::LimitExceeded(ExecutionLimitExceeded::Cycles { limit: 1 })
+    );
+    assert_eq!(vm.watchdog.cycles_executed(), 1);
+    // stopped right after the far call
+    assert_eq!(vm.local_state.callstack.depth(), 2);
+    assert!(vm.local_state.pending_exception == false);
+
+    // execution continues from the same state once the limit is raised
+    vm.set_execution_limits(ExecutionLimits {
+        max_cycles: Some(2),
+        ..ExecutionLimits::default()
+    });
+    let reason = vm
+        .run(&mut GenericNoopTracer::<SimpleMemory>::new())
+        .unwrap();
+    assert_eq!(
+        reason,
+        ExecutionStopReason::LimitExceeded(ExecutionLimitExceeded::Cycles { limit: 2 })
+    );
+    assert_eq!(vm.local_state.callstack.depth(), 1);
+    assert_eq!(vm.local_state.registers[4].value, U256::one());
+}
+
+#[test]
+fn callstack_depth_limit() {
+    let (vm, reason) = run_with_limits(ExecutionLimits {
+        max_callstack_depth: Some(1),
+        ..ExecutionLimits::default()
+    });
+    assert_eq!(
+        reason,
+        ExecutionStopReason::LimitExceeded(ExecutionLimitExceeded::CallstackDepth {
+            limit: 1,
+            depth: 2
+        })
+    );
+    assert_eq!(vm.watchdog.cycles_executed(), 1);
+}
+
+#[test]
+fn reserved_memory_pages_limit() {
+    let vector = vector_by_name("far_call_and_ret_ok");
+    let mut vm = prepare_conformance_vm(&vector, DummyTracer);
+    let limit = vm.local_state.memory_page_counter;
+    vm.set_execution_limits(ExecutionLimits {
+        max_reserved_memory_pages: Some(limit),
+        ..ExecutionLimits::default()
+    });
+    let reason = vm
+        .run(&mut GenericNoopTracer::<SimpleMemory>::new())
+        .unwrap();
+
+    assert_eq!(
+        reason,
+        ExecutionStopReason::LimitExceeded(ExecutionLimitExceeded::MemoryPages {
+            limit,
+            reserved: vm.local_state.memory_page_counter
+        })
+    );
+    assert!(vm.local_state.memory_page_counter > limit);
+}
+
+#[test]
+fn wall_time_limit() {
+    let (vm, reason) = run_with_limits(ExecutionLimits {
+        max_wall_time: Some(Duration::ZERO),
+        ..ExecutionLimits::default()
+    });
+    assert!(matches!(
+        reason,
+        ExecutionStopReason::LimitExceeded(ExecutionLimitExceeded::WallTime { .. })
+    ));
+    assert_eq!(vm.watchdog.cycles_executed(), 0);
+}
+
+#[test]
+#[should_panic(expected = "above VM_MAX_STACK_DEPTH")]
+fn callstack_depth_limit_is_bounded_by_vm() {
+    let mut vm = prepare_conformance_vm(&vector_by_name("far_call_and_ret_ok"), DummyTracer);
+    vm.set_execution_limits(ExecutionLimits {
+        max_callstack_depth: Some(usize::MAX),
+        ..ExecutionLimits::default()
+    });
+}
@@ -0,0 +1,140 @@
+use super::*;
+
+use crate::testing::conformance::*;
+use crate::witness_trace::fat_pointers::*;
+use crate::witness_trace::{FatPointerEvent, PointerEventLocation, PointerTransfer};
+use zkevm_opcode_defs::{FatPointer, PtrOpcode};
+
+fn collect_events(name: &str) -> (u32, Vec<FatPointerEvent>) {
+    let vector = vector_by_name(name);
+    let mut vm = prepare_conformance_vm(&vector, FatPointerLineageTracer::new());
+    let base_page = vm
+        .local_state
+        .callstack
+        .get_current_stack()
+        .base_memory_page
+        .0;
+    run_conformance_cycles(&mut vm, vector.cycles).unwrap();
+    let events = vm
+        .witness_tracer
+        .events
+        .iter()
+        .map(|(_, event)| *event)
+        .collect();
+
+    (base_page, events)
+}
+
+fn fat_pointer(memory_page: u32, start: u32, length: u32, offset: u32) -> FatPointer {
+    FatPointer {
+        memory_page,
+        start,
+        length,
+        offset,
+    }
+}
+
+#[test]
+fn narrowing_is_reported() {
+    let (_, events) = collect_events("ptr_shrink");
+    assert_eq!(events.len(), 1);
+    let FatPointerEvent::Narrowed {
+        location,
+        opcode,
+        input,
+        output,
+    } = events[0]
+    else {
+        panic!("unexpected event {:?}", events[0]);
+    };
+    assert_eq!(location.pc, 0);
+    assert_eq!(opcode, PtrOpcode::Shrink);
+    assert_eq!(input, fat_pointer(5, 0, 64, 32));
+    assert_eq!(output, fat_pointer(5, 0, 48, 32));
+
+    // nothing is reported if the opcode panics
+    let (_, events) = collect_events("ptr_shrink_underflow_panics");
+    assert!(events.is_empty());
+}
+
+#[test]
+fn calldata_and_returndata_are_reported() {
+    let (caller_base_page, events) = collect_events("far_call_and_ret_ok");
+    assert_eq!(events.len(), 2);
+
+    let FatPointerEvent::Created {
+        location,
+        transfer: PointerTransfer::Calldata,
+        is_aux_heap: false,
+        pointer,
+        receiver_base_page: callee_base_page,
+    } = events[0]
+    else {
+        panic!("unexpected event {:?}", events[0]);
+    };
+    assert_eq!(location.base_memory_page, caller_base_page);
+    assert_eq!(pointer.memory_page, caller_base_page + 2);
+
+    let FatPointerEvent::Created {
+        location,
+        transfer: PointerTransfer::Returndata,
+        is_aux_heap: false,
+        pointer,
+        receiver_base_page,
+    } = events[1]
+    else {
+        panic!("unexpected event {:?}", events[1]);
+    };
+    assert_eq!(location.base_memory_page, callee_base_page);
+    assert_eq!(pointer.memory_page, callee_base_page + 2);
+    assert_eq!(receiver_base_page, caller_base_page);
+}
+
+#[test]
+fn lineage_of_forwarded_pointer() {
+    let location = |base_memory_page: u32, pc: u32| PointerEventLocation {
+        base_memory_page,
+        code_address: Address::from_low_u64_be(base_memory_page as u64),
+        pc,
+    };
+    let created = FatPointerEvent::Created {
+        location: location(8, 3),
+        transfer: PointerTransfer::Calldata,
+        is_aux_heap: false,
+        pointer: fat_pointer(10, 0, 64, 0),
+        receiver_base_page: 16,
+    };
+    let narrowed = FatPointerEvent::Narrowed {
+        location: location(16, 1),
+        opcode: PtrOpcode::Add,
+        input: fat_pointer(10, 0, 64, 0),
+        output: fat_pointer(10, 0, 64, 32),
+    };
+    let forwarded = FatPointerEvent::Forwarded {
+        location: location(16, 2),
+        transfer: PointerTransfer::Calldata,
+        original: fat_pointer(10, 0, 64, 32),
+        forwarded: fat_pointer(10, 32, 32, 0),
+        receiver_base_page: 24,
+    };
+    let other_page = FatPointerEvent::Created {
+        location: location(24, 5),
+        transfer: PointerTransfer::Returndata,
+        is_aux_heap: true,
+        pointer: fat_pointer(27, 0, 32, 0),
+        receiver_base_page: 16,
+    };
+    let tracer = FatPointerLineageTracer {
+        events: vec![(1, created), (2, narrowed), (3, forwarded), (4, other_page)],
+    };
+
+    assert_eq!(tracer.origin(10), Some((1, created)));
+    assert_eq!(
+        tracer.events_for_page(10),
+        vec![(1, created), (2, narrowed), (3, forwarded)]
+    );
+    assert_eq!(tracer.receivers(10), vec![16, 24]);
+    assert_eq!(tracer.origin(11), None);
+    assert_eq!(forwarded.pointer(), fat_pointer(10, 32, 32, 0));
+    assert_eq!(forwarded.location().pc, 2);
+}
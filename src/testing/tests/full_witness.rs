@@ -0,0 +1,203 @@
+use super::*;
+
+use crate::testing::conformance::*;
+use crate::vm_state::PrimitiveValue;
+use crate::witness_trace::chunks::*;
+use crate::witness_trace::full::*;
+use crate::witness_trace::CallstackTransition;
+use zkevm_opcode_defs::*;
+
+fn int(value: u64) -> PrimitiveValue {
+    PrimitiveValue::from_value(U256::from(value))
+}
+
+// near call writes into storage and panics, then the handler emits an event and reads the slot
+fn reverted_write_vector() -> ConformanceVector {
+    let program = [
+        Instruction::new(Opcode::NearCall(NearCallOpcode))
+            .src0(1)
+            .imm0(2)
+            .imm1(4),
+        Instruction::new(Opcode::Nop(NopOpcode)),
+        Instruction::new(Opcode::Log(LogOpcode::StorageWrite))
+            .src0(3)
+            .src1(4),
+        Instruction::new(Opcode::Ret(RetOpcode::Panic)),
+        Instruction::new(Opcode::Log(LogOpcode::Event))
+            .src0(3)
+            .src1(4)
+            .flag(FIRST_MESSAGE_FLAG_IDX),
+        Instruction::new(Opcode::Log(LogOpcode::StorageRead))
+            .src0(3)
+            .dst0(5),
+    ];
+
+    ConformanceVector {
+        name: "reverted_write".to_owned(),
+        program: program.iter().map(|el| el.encode()).collect(),
+        cycles: 5,
+        initial: InitialState {
+            registers: vec![(3, int(7)), (4, int(9))],
+            ..InitialState::default()
+        },
+        expected: ExpectedState::default(),
+    }
+}
+
+fn collect_witness(vector: &ConformanceVector) -> FullWitness {
+    let mut vm = prepare_conformance_vm(vector, FullWitnessTracer::new());
+    run_conformance_cycles(&mut vm, vector.cycles).unwrap();
+
+    vm.witness_tracer.into_witness()
+}
+
+#[test]
+fn rollbacks_of_panicked_frame_are_placed_into_queue() {
+    let witness = collect_witness(&reverted_write_vector());
+
+    let storage: Vec<_> = witness
+        .storage_queue
+        .iter()
+        .map(|(_, el)| (el.rw_flag, el.rollback, el.read_value, el.written_value))
+        .collect();
+    assert_eq!(
+        storage,
+        vec![
+            (true, false, U256::zero(), U256::from(9u64)),
+            (true, true, U256::zero(), U256::from(9u64)),
+            (false, false, U256::zero(), U256::zero()),
+        ]
+    );
+    // rollback is tagged with the cycle of the panic
+    assert_eq!(
+        witness.storage_queue[1].0,
+        witness.local_states[2].monotonic_cycle_counter
+    );
+    // root frame never finishes, so the event has no rollback in the queue
+    assert_eq!(witness.events_queue.len(), 1);
+    assert!(witness.l1_messages_queue.is_empty());
+    assert!(witness.precompiles_queue.is_empty());
+    assert_eq!(witness.storage_refunds.len(), 1);
+
+    assert_eq!(witness.local_states.len(), 5);
+    assert!(witness
+        .local_states
+        .windows(2)
+        .all(|el| el[0].monotonic_cycle_counter + 1 == el[1].monotonic_cycle_counter));
+    assert_eq!(witness.callstack_transitions.len(), 3);
+}
+
+#[test]
+fn near_call_panic_jumps_to_exception_handler() {
+    let witness = collect_witness(&reverted_write_vector());
+
+    let (caller, callee) = match witness.callstack_transitions[1].1 {
+        CallstackTransition::Push { caller, callee } => (caller, callee),
+        _ => panic!("near call must push a frame"),
+    };
+    assert!(callee.is_local_frame);
+    assert_eq!(caller.pc, 1);
+    assert_eq!(callee.pc, 2);
+    match witness.callstack_transitions[2].1 {
+        CallstackTransition::Pop {
+            popped,
+            restored,
+            panicked,
+            ergs_returned,
+            jumped_to_exception_handler,
+        } => {
+            assert!(popped.is_local_frame);
+            assert!(panicked && jumped_to_exception_handler);
+            assert_eq!(restored.pc, 4);
+            assert_eq!(ergs_returned, popped.ergs_remaining);
+            assert_eq!(
+                restored.ergs_remaining,
+                caller.ergs_remaining + ergs_returned
+            );
+            assert_eq!(restored.heap_bound, popped.heap_bound);
+        }
+        _ => panic!("panic must pop a frame"),
+    }
+}
+
+#[test]
+fn far_return_restores_caller() {
+    let witness = collect_witness(&vector_by_name("far_call_and_ret_ok"));
+
+    // bootloader frame, far call and return from it
+    assert_eq!(witness.callstack_transitions.len(), 3);
+    let (caller, callee) = match witness.callstack_transitions[1].1 {
+        CallstackTransition::Push { caller, callee } => (caller, callee),
+        _ => panic!("far call must push a frame"),
+    };
+    assert!(callee.is_local_frame == false);
+    match witness.callstack_transitions[2].1 {
+        CallstackTransition::Pop {
+            popped,
+            restored,
+            panicked,
+            jumped_to_exception_handler,
+            ..
+        } => {
+            assert_eq!(popped.code_page, callee.code_page);
+            assert_eq!(restored.base_memory_page, caller.base_memory_page);
+            assert_eq!(restored.pc, caller.pc);
+            assert!(panicked == false && jumped_to_exception_handler == false);
+        }
+        _ => panic!("return must pop a frame"),
+    }
+}
+
+#[test]
+fn witness_survives_json_roundtrip() {
+    let witness = collect_witness(&vector_by_name("far_call_and_ret_ok"));
+
+    assert_eq!(witness.decommittment_queue.len(), 1);
+    assert!(!witness.decommittment_queue[0].memory_witness.is_empty());
+    let sorted = witness.sorted_memory_queries();
+    assert_eq!(sorted.len(), witness.memory_queue.len());
+    assert!(sorted.windows(2).all(|el| {
+        (
+            el[0].location.page.0,
+            el[0].location.index.0,
+            el[0].timestamp.0,
+        ) <= (
+            el[1].location.page.0,
+            el[1].location.index.0,
+            el[1].timestamp.0,
+        )
+    }));
+
+    let json = serde_json::to_string(&witness).unwrap();
+    let restored: FullWitness = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored, witness);
+}
+
+#[test]
+fn witness_is_split_by_cycles() {
+    let witness = collect_witness(&reverted_write_vector());
+    let chunks = witness.split_into_chunks(2);
+
+    assert_eq!(
+        chunks.iter().map(|el| el.num_cycles).collect::<Vec<_>>(),
+        vec![2, 2, 1]
+    );
+    for (idx, chunk) in chunks.iter().enumerate() {
+        assert_eq!(chunk.initial_state, witness.local_states[idx * 2]);
+    }
+    // write, then rollback with the event, then read
+    assert_eq!(
+        chunks
+            .iter()
+            .map(|el| el.log_queries.len())
+            .collect::<Vec<_>>(),
+        vec![1, 2, 1]
+    );
+    let memory_queries: Vec<_> = chunks
+        .iter()
+        .flat_map(|el| el.memory_queries.iter().copied())
+        .collect();
+    assert_eq!(memory_queries, witness.memory_queue);
+
+    assert_eq!(witness.split_into_chunks(16).len(), 1);
+}
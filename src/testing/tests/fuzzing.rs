@@ -0,0 +1,67 @@
+use super::*;
+
+use crate::testing::conformance::*;
+use crate::testing::fuzzing::*;
+use proptest::prelude::*;
+use zkevm_opcode_defs::decoding::*;
+use zkevm_opcode_defs::*;
+
+#[test]
+fn empty_input_is_well_formed() {
+    let input = FuzzInput::from_bytes(&[]);
+
+    assert_eq!(input.program.len(), 1);
+    assert_eq!(input.callee_programs.len(), FUZZ_CALLEES_COUNT);
+    check_invariants(&input).unwrap();
+}
+
+#[test]
+fn far_call_and_return_keep_invariants() {
+    let callee = fuzz_callee_address(0);
+    let mut input = FuzzInput::from_bytes(&[]);
+    input.program = vec![
+        Instruction::new(Opcode::FarCall(FarCallOpcode::Normal))
+            .src1(2)
+            .imm0(1)
+            .encode(),
+        Instruction::new(Opcode::Ret(RetOpcode::Ok)).encode(),
+    ];
+    input.callee_programs[0] = vec![Instruction::new(Opcode::Ret(RetOpcode::Ok)).encode()];
+    input.registers = vec![(
+        2,
+        crate::vm_state::PrimitiveValue::from_value(address_to_u256(&callee)),
+    )];
+    input.ergs = 100_000;
+    input.cycles = 16;
+
+    // call, return from the callee and return from the root frame
+    assert_eq!(check_invariants(&input).unwrap(), 3);
+}
+
+fn decodes_into_valid_variant(raw: u64) -> bool {
+    let (decoded, _) = EncodingModeProduction::parse_preliminary_variant_and_absolute_number(raw);
+
+    !matches!(decoded.variant.opcode, Opcode::Invalid(_))
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn generated_programs_are_well_formed(data in proptest::collection::vec(any::<u8>(), 0..1024)) {
+        let input = FuzzInput::from_bytes(&data);
+        for program in std::iter::once(&input.program).chain(input.callee_programs.iter()) {
+            prop_assert!(!program.is_empty() && program.len() <= FUZZ_MAX_PROGRAM_LENGTH);
+            for raw in program.iter() {
+                prop_assert!(decodes_into_valid_variant(*raw), "0x{:016x}", raw);
+            }
+        }
+    }
+
+    #[test]
+    fn opcode_pipeline_keeps_invariants(data in proptest::collection::vec(any::<u8>(), 0..2048)) {
+        let input = FuzzInput::from_bytes(&data);
+        let result = check_invariants(&input);
+        prop_assert!(result.is_ok(), "{:?} for {:?}", result, input);
+    }
+}
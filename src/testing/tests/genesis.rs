@@ -0,0 +1,52 @@
+use super::*;
+
+use crate::errors::BytecodeHashingError;
+use crate::testing::genesis::*;
+
+#[test]
+fn genesis_writes_code_hashes_into_deployer_storage() {
+    let description = r#"{
+        "default_account": { "bytecode": "0x0101010101010101010101010101010101010101010101010101010101010101" },
+        "accounts": [
+            {
+                "address": "0x0000000000000000000000000000000000010000",
+                "code": { "bytecode": "0x0202020202020202020202020202020202020202020202020202020202020202" },
+                "storage": { "0x01": "0x2a" },
+                "balance": "0x64"
+            }
+        ]
+    }"#;
+    let genesis = GenesisDescription::from_json_str(description)
+        .unwrap()
+        .resolve(std::path::Path::new("."))
+        .unwrap();
+
+    let address = Address::from_low_u64_be(0x10000);
+    let code_hash = genesis.code_hash_of(&address).unwrap();
+    assert_eq!(genesis.bytecodes.len(), 2);
+    assert_ne!(code_hash, genesis.block_properties.default_aa_code_hash);
+
+    let mut tools = create_default_testing_tools();
+    genesis.apply_to(&mut tools);
+
+    assert_eq!(tools.storage.inner[0][&address][&U256::one()], U256::from(42u64));
+    let deployer_storage = &tools.storage.inner[0]
+        [&*zkevm_opcode_defs::system_params::DEPLOYER_SYSTEM_CONTRACT_ADDRESS];
+    assert_eq!(deployer_storage[&address_to_u256(&address)], code_hash);
+}
+
+#[test]
+fn genesis_rejects_even_length_bytecode() {
+    let description = format!(
+        r#"{{ "known_bytecodes": [{{ "bytecode": "0x{}" }}] }}"#,
+        "01".repeat(64)
+    );
+    let error = GenesisDescription::from_json_str(&description)
+        .unwrap()
+        .resolve(std::path::Path::new("."))
+        .unwrap_err();
+    assert_eq!(
+        error.downcast_ref::<BytecodeHashingError>(),
+        Some(&BytecodeHashingError::EvenNumberOfWords)
+    );
+}
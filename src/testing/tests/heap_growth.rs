@@ -0,0 +1,79 @@
+use super::*;
+
+use crate::testing::conformance::*;
+use crate::witness_trace::heap_growth::*;
+use crate::witness_trace::{HeapGrowthEvent, HeapGrowthSource};
+
+fn collect_events(name: &str) -> (Address, Vec<HeapGrowthEvent>) {
+    let vector = vector_by_name(name);
+    let mut vm = prepare_conformance_vm(&vector, HeapGrowthTracer::new());
+    let code_address = vm.local_state.callstack.get_current_stack().code_address;
+    run_conformance_cycles(&mut vm, vector.cycles).unwrap();
+    let events = vm
+        .witness_tracer
+        .events
+        .iter()
+        .map(|(_, event)| *event)
+        .collect();
+
+    (code_address, events)
+}
+
+#[test]
+fn uma_heap_growth_is_reported() {
+    let (code_address, events) = collect_events("heap_write_grows_memory");
+    assert_eq!(
+        events,
+        vec![HeapGrowthEvent {
+            source: HeapGrowthSource::Uma,
+            base_memory_page: events[0].base_memory_page,
+            code_address,
+            pc: 0,
+            is_aux_heap: false,
+            old_bound: 0,
+            new_bound: 96,
+            ergs_charged: 96,
+            out_of_range_penalty: false,
+        }]
+    );
+
+    let (_, events) = collect_events("heap_read_in_bounds_is_free");
+    assert!(events.is_empty());
+}
+
+#[test]
+fn out_of_range_penalty_is_reported() {
+    let (_, events) = collect_events("heap_read_beyond_u32_offset_panics");
+    assert_eq!(events.len(), 1);
+    assert!(events[0].out_of_range_penalty);
+    assert_eq!(events[0].ergs_charged, u32::MAX);
+}
+
+#[test]
+fn hotspots_are_sorted_by_ergs() {
+    let event = |pc: u32, old_bound: u32, new_bound: u32| HeapGrowthEvent {
+        source: HeapGrowthSource::Uma,
+        base_memory_page: 8,
+        code_address: Address::from_low_u64_be(1),
+        pc,
+        is_aux_heap: false,
+        old_bound,
+        new_bound,
+        ergs_charged: new_bound - old_bound,
+        out_of_range_penalty: false,
+    };
+    let tracer = HeapGrowthTracer {
+        events: vec![
+            (1, event(3, 0, 64)),
+            (2, event(7, 64, 96)),
+            (3, event(3, 96, 128)),
+        ],
+    };
+
+    let hotspots = tracer.hotspots();
+    assert_eq!(hotspots.len(), 2);
+    assert_eq!((hotspots[0].pc, hotspots[0].num_events), (3, 2));
+    assert_eq!(hotspots[0].bytes_grown, 96);
+    assert_eq!(hotspots[0].ergs_charged, 96);
+    assert_eq!((hotspots[1].pc, hotspots[1].ergs_charged), (7, 32));
+}
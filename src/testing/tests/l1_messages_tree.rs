@@ -0,0 +1,44 @@
+use super::*;
+
+use crate::reference_impls::event_decoder::L2ToL1Message;
+use crate::reference_impls::l1_messages_tree::*;
+
+fn message(i: u64) -> L2ToL1Message {
+    L2ToL1Message {
+        shard_id: 0,
+        is_service: true,
+        tx_number_in_block: i as u16,
+        sender: Address::from_low_u64_be(0x8008),
+        key: U256::from(i),
+        value: U256::from(i * 2),
+    }
+}
+
+#[test]
+fn inclusion_proofs_verify_against_root() {
+    let messages: Vec<_> = (0..5).map(message).collect();
+    let tree = L1MessagesMerkleTree::new_with_min_size(&messages, 4);
+    assert_eq!(tree.tree_size(), 8);
+
+    let root = tree.root();
+    for i in 0..messages.len() {
+        let proof = tree.inclusion_proof(i).unwrap();
+        assert_eq!(proof.leaf_hash, messages[i].leaf_hash());
+        assert_eq!(proof.path.len(), 3);
+        assert!(proof.verify(&root));
+    }
+    assert!(tree.inclusion_proof(5).is_none());
+
+    let mut forged = tree.inclusion_proof(1).unwrap();
+    forged.leaf_hash = message(7).leaf_hash();
+    assert!(!forged.verify(&root));
+}
+
+#[test]
+fn empty_tree_is_padded() {
+    let tree = L1MessagesMerkleTree::new(&[]);
+    assert_eq!(tree.tree_size(), DEFAULT_L2_TO_L1_LOGS_TREE_SIZE);
+    let small = L1MessagesMerkleTree::new_with_min_size(&[], 2);
+    assert_ne!(tree.root(), small.root());
+    assert_eq!(message(3).to_leaf_bytes()[3], 3);
+}
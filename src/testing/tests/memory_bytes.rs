@@ -0,0 +1,89 @@
+use super::*;
+
+use crate::reference_impls::paged_memory::PagedMemory;
+use crate::testing::conformance::*;
+use crate::witness_trace::DummyTracer;
+use zk_evm_abstractions::aux::Timestamp;
+use zk_evm_abstractions::vm::Memory;
+use zkevm_opcode_defs::{FatPointer, BOOTLOADER_BASE_PAGE};
+
+fn value() -> U256 {
+    U256::from_str_radix(
+        "0102030405060708091011121314151617181920212223242526272829303132",
+        16,
+    )
+    .unwrap()
+}
+
+fn value_bytes() -> Vec<u8> {
+    let mut buffer = [0u8; 32];
+    value().to_big_endian(&mut buffer);
+
+    buffer.to_vec()
+}
+
+fn current_heap_page(memory: &SimpleMemory) -> u32 {
+    memory.heaps.last().unwrap().0 .0
+}
+
+#[test]
+fn bytes_are_split_across_words() {
+    let mut memory: SimpleMemory = SimpleMemory::new_without_preallocations();
+    memory.start_global_frame(
+        MemoryPage(0),
+        MemoryPage(BOOTLOADER_BASE_PAGE),
+        FatPointer::empty(),
+        Timestamp(0),
+    );
+    let page = current_heap_page(&memory);
+    memory.write_bytes(page, 1, &value_bytes());
+
+    // same as the unaligned UMA write
+    assert_eq!(
+        memory.dump_page_content_as_u256_words(page, 0..2),
+        vec![value() >> 8, value() << 248]
+    );
+    assert_eq!(memory.read_bytes(page, 1, 32), value_bytes());
+
+    let mut expected = vec![0u8; 40];
+    expected[1..33].copy_from_slice(&value_bytes());
+    assert_eq!(memory.read_bytes(page, 0, 40), expected);
+    assert!(memory.read_bytes(page, 5, 0).is_empty());
+}
+
+#[test]
+fn uma_read_sees_written_bytes() {
+    let vector = vector_by_name("heap_read_unaligned");
+    let mut vm = prepare_conformance_vm(&vector, DummyTracer);
+    let page = current_heap_page(&vm.memory);
+    // overwrite everything the opcode reads
+    vm.memory.write_bytes(page, 1, &value_bytes());
+    run_conformance_cycles(&mut vm, vector.cycles).unwrap();
+
+    assert_eq!(vm.local_state.registers[2].value, value());
+}
+
+#[test]
+fn uma_write_is_read_as_bytes() {
+    let vector = vector_by_name("heap_write_and_read_back");
+    let mut vm = prepare_conformance_vm(&vector, DummyTracer);
+    run_conformance_cycles(&mut vm, vector.cycles).unwrap();
+
+    let page = current_heap_page(&vm.memory);
+    assert_eq!(vm.memory.read_bytes(page, 100, 32), value_bytes());
+    assert_eq!(vm.memory.read_bytes(page, 99, 1), vec![0]);
+}
+
+#[test]
+fn paged_memory_bytes_roundtrip() {
+    let mut memory = PagedMemory::new();
+    memory.write_bytes(100, 63, &value_bytes());
+    memory.write_bytes(100, 5, &[]);
+    assert!(memory.read_bytes(100, 5, 0).is_empty());
+
+    assert_eq!(memory.read_bytes(100, 63, 32), value_bytes());
+    assert_eq!(
+        memory.dump_page_content_as_u256_words(100, 1..3),
+        vec![value() >> 248, value() << 8]
+    );
+}
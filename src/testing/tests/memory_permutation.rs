@@ -0,0 +1,104 @@
+use super::*;
+
+use crate::errors::MemoryWitnessError;
+use crate::testing::conformance::*;
+use crate::witness_trace::full::*;
+use crate::witness_trace::memory_permutation::*;
+use zk_evm_abstractions::aux::{MemoryIndex, MemoryLocation, Timestamp};
+use zk_evm_abstractions::queries::MemoryQuery;
+use zk_evm_abstractions::vm::MemoryType;
+use zkevm_opcode_defs::BOOTLOADER_CODE_PAGE;
+
+fn heap_query(
+    index: u32,
+    timestamp: u32,
+    value: u64,
+    is_pointer: bool,
+    rw_flag: bool,
+) -> MemoryQuery {
+    MemoryQuery {
+        timestamp: Timestamp(timestamp),
+        location: MemoryLocation {
+            memory_type: MemoryType::Heap,
+            page: MemoryPage(100),
+            index: MemoryIndex(index),
+        },
+        value: U256::from(value),
+        value_is_pointer: is_pointer,
+        rw_flag,
+    }
+}
+
+#[test]
+fn queries_are_sorted_before_checking() {
+    let queries = [
+        heap_query(1, 9, 7, false, false),
+        heap_query(0, 5, 3, true, false),
+        heap_query(1, 2, 7, false, true),
+        heap_query(0, 1, 3, true, true),
+        heap_query(2, 3, 0, false, false),
+    ];
+
+    let sorted = check_memory_queries(&queries).unwrap();
+    assert_eq!(
+        sorted
+            .iter()
+            .map(|el| (el.location.index.0, el.timestamp.0))
+            .collect::<Vec<_>>(),
+        vec![(0, 1), (0, 5), (1, 2), (1, 9), (2, 3)]
+    );
+}
+
+#[test]
+fn first_inconsistency_is_reported() {
+    let queries = [
+        heap_query(0, 1, 3, true, true),
+        heap_query(0, 5, 3, false, false),
+        heap_query(1, 2, 4, false, false),
+    ];
+    assert_eq!(
+        check_memory_queries(&queries).unwrap_err(),
+        MemoryWitnessError::PointerFlagMismatch {
+            page: 100,
+            index: 0,
+            timestamp: 5,
+            written_at: Some(1),
+            expected: true,
+            actual: false,
+        }
+    );
+
+    assert_eq!(
+        check_memory_queries(&queries[2..]).unwrap_err(),
+        MemoryWitnessError::ReadValueMismatch {
+            page: 100,
+            index: 1,
+            timestamp: 2,
+            written_at: None,
+            expected: U256::zero(),
+            actual: U256::from(4u64),
+        }
+    );
+}
+
+#[test]
+fn memory_witness_of_default_vectors_is_consistent() {
+    // bootloader code and initial heaps are filled directly and are not a part of the witness
+    for vector in default_conformance_vectors()
+        .into_iter()
+        .filter(|el| el.initial.heap.is_empty() && el.initial.aux_heap.is_empty())
+    {
+        let mut vm = prepare_conformance_vm(&vector, FullWitnessTracer::new());
+        run_conformance_cycles(&mut vm, vector.cycles).unwrap();
+        let witness = vm.witness_tracer.into_witness();
+
+        let queries: Vec<_> = witness
+            .memory_permutation_queries()
+            .into_iter()
+            .filter(|el| el.location.page.0 != BOOTLOADER_CODE_PAGE)
+            .collect();
+        if let Err(error) = check_memory_queries(&queries) {
+            panic!("vector {}: {}", vector.name, error);
+        }
+    }
+}
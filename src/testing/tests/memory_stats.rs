@@ -0,0 +1,132 @@
+use super::*;
+
+use crate::reference_impls::memory_stats::*;
+use crate::vm_state::CallStackEntry;
+use zk_evm_abstractions::aux::{MemoryIndex, MemoryLocation, Timestamp};
+use zk_evm_abstractions::queries::MemoryQuery;
+use zk_evm_abstractions::vm::{Memory, MemoryType};
+use zkevm_opcode_defs::decoding::EncodingModeProduction;
+use zkevm_opcode_defs::{FatPointer, BOOTLOADER_BASE_PAGE, NEW_MEMORY_PAGES_PER_FAR_CALL};
+
+type Entry = CallStackEntry<8, EncodingModeProduction>;
+
+const ROOT: MemoryPage = MemoryPage(BOOTLOADER_BASE_PAGE);
+const CALLEE: MemoryPage = MemoryPage(BOOTLOADER_BASE_PAGE + NEW_MEMORY_PAGES_PER_FAR_CALL);
+const NEXT_CALLEE: MemoryPage =
+    MemoryPage(BOOTLOADER_BASE_PAGE + 2 * NEW_MEMORY_PAGES_PER_FAR_CALL);
+
+fn write(memory: &mut SimpleMemory, memory_type: MemoryType, base: MemoryPage, index: u32) {
+    let page = if memory_type == MemoryType::Heap {
+        Entry::heap_page_from_base(base)
+    } else {
+        Entry::aux_heap_page_from_base(base)
+    };
+    let query = MemoryQuery {
+        timestamp: Timestamp(0),
+        location: MemoryLocation {
+            memory_type,
+            page,
+            index: MemoryIndex(index),
+        },
+        value: U256::one(),
+        rw_flag: true,
+        value_is_pointer: false,
+    };
+    memory.execute_partial_query(0, query);
+}
+
+#[test]
+fn pages_and_heap_bounds_are_counted() {
+    let mut memory: SimpleMemory =
+        SimpleMemory::new_without_preallocations().with_frame_heap_bounds_history();
+    memory.start_global_frame(MemoryPage(0), ROOT, FatPointer::empty(), Timestamp(0));
+    write(&mut memory, MemoryType::Heap, ROOT, 3);
+
+    memory.start_global_frame(ROOT, CALLEE, heap_slice(ROOT, 1), Timestamp(0));
+    write(&mut memory, MemoryType::Heap, CALLEE, 0);
+    write(&mut memory, MemoryType::AuxHeap, CALLEE, 9);
+    memory.finish_global_frame(CALLEE, heap_slice(CALLEE, 1), Timestamp(0));
+
+    let stats = memory.memory_stats();
+    assert_eq!(
+        stats.allocated_pages,
+        PageCounts {
+            code: 1,
+            stack: 2,
+            heap: 2,
+            aux_heap: 2,
+            // bootloader's calldata and callee's returndata
+            extended_lifetime: 2,
+        }
+    );
+    assert_eq!(
+        stats.live_pages,
+        PageCounts {
+            code: 1,
+            stack: 1,
+            heap: 1,
+            aux_heap: 1,
+            extended_lifetime: 2,
+        }
+    );
+    assert_eq!(stats.peak_live_pages, 8);
+    assert_eq!(stats.max_heap_bound_in_words, 4);
+    assert_eq!(stats.max_aux_heap_bound_in_words, 10);
+    assert_eq!(
+        stats.frame_heap_bounds,
+        vec![
+            FrameHeapBounds {
+                heap_page: Entry::heap_page_from_base(ROOT).0,
+                aux_heap_page: Entry::aux_heap_page_from_base(ROOT).0,
+                heap_bound_in_words: 4,
+                aux_heap_bound_in_words: 0,
+            },
+            FrameHeapBounds {
+                heap_page: Entry::heap_page_from_base(CALLEE).0,
+                aux_heap_page: Entry::aux_heap_page_from_base(CALLEE).0,
+                heap_bound_in_words: 1,
+                aux_heap_bound_in_words: 10,
+            },
+        ]
+    );
+}
+
+#[test]
+fn finished_frames_are_only_kept_as_maxima_by_default() {
+    let mut memory: SimpleMemory = SimpleMemory::new_without_preallocations();
+    memory.start_global_frame(MemoryPage(0), ROOT, FatPointer::empty(), Timestamp(0));
+    write(&mut memory, MemoryType::Heap, ROOT, 3);
+    memory.start_global_frame(ROOT, CALLEE, FatPointer::empty(), Timestamp(0));
+    write(&mut memory, MemoryType::AuxHeap, CALLEE, 9);
+    memory.finish_global_frame(CALLEE, FatPointer::empty(), Timestamp(0));
+
+    let stats = memory.memory_stats();
+    assert_eq!(stats.max_heap_bound_in_words, 4);
+    assert_eq!(stats.max_aux_heap_bound_in_words, 10);
+    assert_eq!(
+        stats.frame_heap_bounds,
+        vec![FrameHeapBounds {
+            heap_page: Entry::heap_page_from_base(ROOT).0,
+            aux_heap_page: Entry::aux_heap_page_from_base(ROOT).0,
+            heap_bound_in_words: 4,
+            aux_heap_bound_in_words: 0,
+        }]
+    );
+}
+
+#[test]
+fn pool_pages_are_reused_after_return() {
+    // pools of this memory have two pages each
+    let mut memory: SimpleMemory = SimpleMemory::new_without_preallocations();
+    memory.start_global_frame(MemoryPage(0), ROOT, FatPointer::empty(), Timestamp(0));
+    memory.start_global_frame(ROOT, CALLEE, FatPointer::empty(), Timestamp(0));
+    memory.finish_global_frame(CALLEE, FatPointer::empty(), Timestamp(0));
+    memory.start_global_frame(ROOT, NEXT_CALLEE, FatPointer::empty(), Timestamp(0));
+
+    let stats = memory.memory_stats();
+    assert_eq!(stats.heap_pages_pool, PoolStats { hits: 4, misses: 2 });
+    assert_eq!(stats.stack_pages_pool, PoolStats { hits: 3, misses: 0 });
+    assert_eq!(stats.allocated_pages.heap, 3);
+    assert_eq!(stats.live_pages.heap, 2);
+    assert_eq!(stats.peak_live_pages, 8);
+}
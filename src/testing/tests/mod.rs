@@ -1,6 +1,64 @@
 use super::*;
 
+use crate::vm_state::CallStackEntry;
 use zk_evm_abstractions::aux::MemoryPage;
+use zk_evm_abstractions::precompiles::DefaultPrecompilesProcessor;
+use zkevm_opcode_defs::decoding::EncodingModeProduction;
+use zkevm_opcode_defs::FatPointer;
 
+/// Pointer to the first `words` words of the heap of the frame with the given base page
+fn heap_slice(base: MemoryPage, words: u32) -> FatPointer {
+    FatPointer {
+        offset: 0,
+        memory_page: CallStackEntry::<8, EncodingModeProduction>::heap_page_from_base(base).0,
+        start: 0,
+        length: words * 32,
+    }
+}
+
+#[cfg(test)]
+mod checked;
+#[cfg(test)]
+mod conformance;
+#[cfg(test)]
+mod decommitter;
+#[cfg(test)]
+mod event_decoder;
+#[cfg(test)]
+mod execution_limits;
+#[cfg(test)]
+mod fat_pointers;
+#[cfg(test)]
+mod full_witness;
+#[cfg(test)]
+mod fuzzing;
+#[cfg(test)]
+mod genesis;
+#[cfg(test)]
+mod heap_growth;
+#[cfg(test)]
+mod l1_messages_tree;
+#[cfg(test)]
+mod memory_bytes;
+#[cfg(test)]
+mod memory_permutation;
+#[cfg(test)]
+mod memory_stats;
+#[cfg(test)]
+mod page_lifetimes;
+#[cfg(test)]
+mod paged_memory;
+#[cfg(test)]
+mod portable;
+#[cfg(test)]
+mod precompile_calls;
+#[cfg(test)]
+mod precompile_registry;
 #[cfg(test)]
 mod precompiles;
+#[cfg(test)]
+mod receipts;
+#[cfg(test)]
+mod storage_deduplication;
+#[cfg(test)]
+mod trivial;
@@ -0,0 +1,105 @@
+use super::*;
+
+use crate::reference_impls::page_lifetimes::*;
+use crate::vm_state::CallStackEntry;
+use zk_evm_abstractions::aux::Timestamp;
+use zk_evm_abstractions::vm::Memory;
+use zkevm_opcode_defs::decoding::EncodingModeProduction;
+use zkevm_opcode_defs::{
+    FatPointer, BOOTLOADER_BASE_PAGE, BOOTLOADER_CALLDATA_PAGE, NEW_MEMORY_PAGES_PER_FAR_CALL,
+};
+
+type Entry = CallStackEntry<8, EncodingModeProduction>;
+
+fn base(depth: u32) -> MemoryPage {
+    MemoryPage(BOOTLOADER_BASE_PAGE + depth * NEW_MEMORY_PAGES_PER_FAR_CALL)
+}
+
+fn stack(depth: u32) -> u32 {
+    Entry::stack_page_from_base(base(depth)).0
+}
+
+fn heap(depth: u32) -> u32 {
+    Entry::heap_page_from_base(base(depth)).0
+}
+
+fn aux_heap(depth: u32) -> u32 {
+    Entry::aux_heap_page_from_base(base(depth)).0
+}
+
+#[test]
+fn forwarded_returndata_outlives_its_frame() {
+    let mut tracker = PageLifetimeTracker::new();
+    tracker.start_frame(base(0), 0);
+    tracker.start_frame(base(1), heap(0));
+    // calldata is forwarded further
+    tracker.start_frame(base(2), heap(0));
+    assert_eq!(
+        tracker.referencing_frames(heap(0)),
+        vec![base(1).0, base(2).0]
+    );
+
+    assert_eq!(
+        tracker.finish_frame(base(2), heap(2)),
+        vec![stack(2), aux_heap(2)]
+    );
+    assert_eq!(tracker.lifetime(heap(2)), PageLifetime::Extended);
+    assert_eq!(tracker.referencing_frames(heap(2)), vec![base(1).0]);
+    assert_eq!(
+        tracker.lifetime(heap(0)),
+        PageLifetime::OwnedByFrame {
+            base_page: base(0).0
+        }
+    );
+
+    // returndata of the callee is forwarded to the root frame
+    assert_eq!(
+        tracker.finish_frame(base(1), heap(2)),
+        vec![stack(1), heap(1), aux_heap(1)]
+    );
+    assert_eq!(tracker.referencing_frames(heap(2)), vec![base(0).0]);
+
+    assert_eq!(
+        tracker.finish_frame(base(0), 0),
+        vec![heap(2), stack(0), heap(0), aux_heap(0)]
+    );
+    assert_eq!(tracker.lifetime(heap(2)), PageLifetime::Unreachable);
+}
+
+#[test]
+fn returndata_of_root_frame_is_kept() {
+    let mut tracker = PageLifetimeTracker::new();
+    tracker.pin_page(BOOTLOADER_CALLDATA_PAGE);
+    tracker.start_frame(base(0), BOOTLOADER_CALLDATA_PAGE);
+
+    assert_eq!(
+        tracker.finish_frame(base(0), aux_heap(0)),
+        vec![stack(0), heap(0)]
+    );
+    assert_eq!(tracker.lifetime(aux_heap(0)), PageLifetime::Extended);
+    assert_eq!(tracker.referencing_frames(aux_heap(0)), vec![0]);
+    assert_eq!(
+        tracker.lifetime(BOOTLOADER_CALLDATA_PAGE),
+        PageLifetime::Pinned
+    );
+}
+
+#[test]
+fn unreachable_returndata_is_freed_by_simple_memory() {
+    let mut memory: SimpleMemory = SimpleMemory::new_without_preallocations();
+    memory.start_global_frame(MemoryPage(0), base(0), FatPointer::empty(), Timestamp(0));
+    for depth in [1, 2] {
+        memory.start_global_frame(base(0), base(depth), FatPointer::empty(), Timestamp(0));
+        memory.finish_global_frame(base(depth), heap_slice(base(depth), 1), Timestamp(0));
+    }
+    // root frame has overwritten the first returndata pointer, but still keeps the page
+    assert!(memory.pages_with_extended_lifetime.contains_key(&heap(1)));
+    assert!(memory.pages_with_extended_lifetime.contains_key(&heap(2)));
+
+    memory.finish_global_frame(base(0), FatPointer::empty(), Timestamp(0));
+    assert!(memory.pages_with_extended_lifetime.contains_key(&heap(1)) == false);
+    assert!(memory.pages_with_extended_lifetime.contains_key(&heap(2)) == false);
+    assert!(memory
+        .pages_with_extended_lifetime
+        .contains_key(&BOOTLOADER_CALLDATA_PAGE));
+}
@@ -0,0 +1,181 @@
+use super::*;
+
+use crate::reference_impls::paged_memory::*;
+use crate::vm_state::{CallStackEntry, PrimitiveValue};
+use zk_evm_abstractions::aux::{MemoryIndex, MemoryLocation, Timestamp};
+use zk_evm_abstractions::queries::MemoryQuery;
+use zk_evm_abstractions::vm::{Memory, MemoryType};
+use zkevm_opcode_defs::decoding::EncodingModeProduction;
+use zkevm_opcode_defs::{FatPointer, BOOTLOADER_BASE_PAGE, NEW_MEMORY_PAGES_PER_FAR_CALL};
+
+type Entry = CallStackEntry<8, EncodingModeProduction>;
+
+fn query(
+    memory_type: MemoryType,
+    page: MemoryPage,
+    index: u32,
+    value: Option<PrimitiveValue>,
+) -> MemoryQuery {
+    let value = value
+        .map(|el| (el, true))
+        .unwrap_or((PrimitiveValue::empty(), false));
+    MemoryQuery {
+        timestamp: Timestamp(0),
+        location: MemoryLocation {
+            memory_type,
+            page,
+            index: MemoryIndex(index),
+        },
+        value: value.0.value,
+        rw_flag: value.1,
+        value_is_pointer: value.0.is_pointer,
+    }
+}
+
+fn word(value: u64) -> Option<PrimitiveValue> {
+    Some(PrimitiveValue::from_value(U256::from(value)))
+}
+
+// root frame passes its heap to the callee, callee returns its own heap back. Returns
+// results of all the reads
+fn call_and_return<M: Memory>(memory: &mut M) -> Vec<MemoryQuery> {
+    let root = MemoryPage(BOOTLOADER_BASE_PAGE);
+    let callee = MemoryPage(BOOTLOADER_BASE_PAGE + NEW_MEMORY_PAGES_PER_FAR_CALL);
+    let mut reads = vec![];
+
+    memory.start_global_frame(MemoryPage(0), root, FatPointer::empty(), Timestamp(0));
+    let pointer = Some(PrimitiveValue {
+        value: U256::from(77u64),
+        is_pointer: true,
+    });
+    memory.execute_partial_query(
+        0,
+        query(
+            MemoryType::Stack,
+            Entry::stack_page_from_base(root),
+            1,
+            pointer,
+        ),
+    );
+    for idx in 0..4 {
+        memory.execute_partial_query(
+            0,
+            query(
+                MemoryType::Heap,
+                Entry::heap_page_from_base(root),
+                idx,
+                word(idx as u64 + 1),
+            ),
+        );
+    }
+
+    memory.start_global_frame(root, callee, heap_slice(root, 4), Timestamp(0));
+    for idx in 0..4 {
+        reads.push(memory.execute_partial_query(
+            0,
+            query(
+                MemoryType::FatPointer,
+                Entry::heap_page_from_base(root),
+                idx,
+                None,
+            ),
+        ));
+    }
+    for idx in 0..3 {
+        memory.execute_partial_query(
+            0,
+            query(
+                MemoryType::Heap,
+                Entry::heap_page_from_base(callee),
+                idx,
+                word(idx as u64 + 10),
+            ),
+        );
+    }
+    memory.finish_global_frame(callee, heap_slice(callee, 3), Timestamp(0));
+
+    for idx in [0, 1, 2, 100] {
+        reads.push(memory.execute_partial_query(
+            0,
+            query(
+                MemoryType::FatPointer,
+                Entry::heap_page_from_base(callee),
+                idx,
+                None,
+            ),
+        ));
+    }
+    reads.push(memory.execute_partial_query(
+        0,
+        query(
+            MemoryType::Stack,
+            Entry::stack_page_from_base(root),
+            1,
+            None,
+        ),
+    ));
+    reads.push(memory.execute_partial_query(
+        0,
+        query(
+            MemoryType::AuxHeap,
+            Entry::aux_heap_page_from_base(root),
+            5,
+            None,
+        ),
+    ));
+
+    reads
+}
+
+#[test]
+fn paged_memory_matches_simple_memory() {
+    let mut reference: SimpleMemory = SimpleMemory::new_without_preallocations();
+    let expected = call_and_return(&mut reference);
+    let mut memory = PagedMemory::new();
+    let reads = call_and_return(&mut memory);
+
+    assert_eq!(reads, expected);
+    assert!(reads[8].value_is_pointer);
+    assert_eq!(reads[8].value, U256::from(77u64));
+}
+
+#[test]
+fn pages_of_finished_frames_are_dropped() {
+    let mut memory = PagedMemory::new();
+    call_and_return(&mut memory);
+    // root's stack and heap, and callee's heap that became returndata
+    assert_eq!(memory.live_pages(), 3);
+
+    let root = MemoryPage(BOOTLOADER_BASE_PAGE);
+    memory.finish_global_frame(root, FatPointer::empty(), Timestamp(0));
+    assert_eq!(memory.live_pages(), 0);
+}
+
+#[test]
+fn unwritten_chunks_are_not_allocated() {
+    let mut memory = PagedMemory::new();
+    memory.write(100, 0, PrimitiveValue::from_value(U256::one()));
+    memory.write(100, 10_000, PrimitiveValue::from_value(U256::one()));
+
+    assert_eq!(memory.page(100).unwrap().allocated_chunks(), 2);
+    assert!(memory.page(101).is_none());
+    assert_eq!(
+        memory.dump_page_content_as_u256_words(100, 9_999..10_001),
+        vec![U256::zero(), U256::one()]
+    );
+}
+
+#[test]
+fn snapshot_is_not_affected_by_writes() {
+    let mut memory = PagedMemory::new();
+    memory.write(100, 3, PrimitiveValue::from_value(U256::one()));
+    let snapshot = memory.snapshot();
+
+    memory.write(100, 3, PrimitiveValue::from_value(U256::from(2u64)));
+    memory.write(101, 0, PrimitiveValue::from_value(U256::from(2u64)));
+    assert_eq!(snapshot.read(100, 3).value, U256::one());
+    assert!(snapshot.page(101).is_none());
+
+    memory = snapshot;
+    assert_eq!(memory.read(100, 3).value, U256::one());
+}
@@ -0,0 +1,49 @@
+use super::*;
+
+use crate::testing::conformance::*;
+use crate::testing::portable::*;
+
+#[test]
+fn portable_vector_survives_json_roundtrip() {
+    let vector =
+        PortableVector::from_conformance_vector(&vector_by_name("storage_write_and_read")).unwrap();
+    let json = vector.to_json_string().unwrap();
+    let restored = PortableVector::from_json_str(&json).unwrap();
+
+    assert_eq!(restored, vector);
+    restored.run_and_check().unwrap();
+    assert!(restored.expected.witness.log_queries.len() >= 2);
+}
+
+#[test]
+fn far_call_vector_records_decommittment() {
+    let vector =
+        PortableVector::from_conformance_vector(&vector_by_name("far_call_and_ret_ok")).unwrap();
+
+    assert_eq!(vector.expected.witness.decommittment_queries.len(), 1);
+    assert!(vector.expected.witness.decommittment_queries[0].is_fresh);
+    vector.run_and_check().unwrap();
+}
+
+#[test]
+fn tampered_expectation_is_reported() {
+    let mut vector =
+        PortableVector::from_conformance_vector(&vector_by_name("add_reg_reg")).unwrap();
+    vector.expected.local_state.registers[2].value = U256::from(6u64);
+
+    let error = vector.run_and_check().unwrap_err().to_string();
+    assert!(error.contains("local state"));
+}
+
+#[test]
+fn export_and_run_default_vectors() {
+    let dir = std::env::temp_dir().join(format!("zk_evm_vectors_{}", std::process::id()));
+    let vectors = default_conformance_vectors();
+
+    let paths = export_conformance_vectors(&vectors, &dir).unwrap();
+    assert_eq!(paths.len(), vectors.len());
+    let passed = run_portable_vectors_from_dir(&dir).unwrap();
+    assert_eq!(passed.len(), vectors.len());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
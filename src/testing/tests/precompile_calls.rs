@@ -0,0 +1,107 @@
+use super::*;
+
+use crate::reference_impls::precompiles::DefaultPrecompile;
+use crate::testing::conformance::*;
+use crate::vm_state::PrimitiveValue;
+use crate::witness_trace::precompile_calls::*;
+use crate::witness_trace::PrecompileCallEvent;
+use zkevm_opcode_defs::system_params::SHA256_ROUND_FUNCTION_PRECOMPILE_FORMAL_ADDRESS;
+use zkevm_opcode_defs::{LogOpcode, Opcode, PrecompileCallABI};
+
+const EXTRA_ERGS: u32 = 100;
+
+// single sha256 round over the first two words of the heap, result goes to the third one.
+// Offsets are in words for this precompile
+fn sha256_call_vector() -> ConformanceVector {
+    let abi = PrecompileCallABI {
+        input_memory_offset: 0,
+        input_memory_length: 0,
+        output_memory_offset: 2,
+        output_memory_length: 1,
+        memory_page_to_read: 0,
+        memory_page_to_write: 0,
+        precompile_interpreted_data: 1,
+    };
+    let program = [Instruction::new(Opcode::Log(LogOpcode::PrecompileCall))
+        .src0(1)
+        .src1(2)
+        .dst0(3)];
+
+    ConformanceVector {
+        name: "sha256_precompile_call".to_owned(),
+        program: program.iter().map(|el| el.encode()).collect(),
+        cycles: program.len(),
+        initial: InitialState {
+            this_address: *SHA256_ROUND_FUNCTION_PRECOMPILE_FORMAL_ADDRESS,
+            registers: vec![
+                (1, PrimitiveValue::from_value(abi.to_u256())),
+                (2, PrimitiveValue::from_value(U256::from(EXTRA_ERGS))),
+            ],
+            heap: vec![(0, U256::from(0xabcdu64)), (1, U256::MAX)],
+            heap_bound: 96,
+            ..InitialState::default()
+        },
+        expected: ExpectedState::default(),
+    }
+}
+
+fn word_bytes(word: U256) -> Vec<u8> {
+    let mut buffer = [0u8; 32];
+    word.to_big_endian(&mut buffer);
+
+    buffer.to_vec()
+}
+
+#[test]
+fn call_with_witness_is_recorded() {
+    let vector = sha256_call_vector();
+    let mut vm = prepare_conformance_vm(&vector, PrecompileCallTracer::new());
+    vm.precompiles_processor.register(
+        *SHA256_ROUND_FUNCTION_PRECOMPILE_FORMAL_ADDRESS,
+        Box::new(DefaultPrecompile::<true>),
+    );
+    let heap_page = vm.memory.heaps.last().unwrap().0 .0;
+    run_conformance_cycles(&mut vm, vector.cycles).unwrap();
+
+    let calls = vm
+        .witness_tracer
+        .calls_to(&SHA256_ROUND_FUNCTION_PRECOMPILE_FORMAL_ADDRESS);
+    assert_eq!(calls.len(), 1);
+    let record = calls[0];
+    assert_eq!(
+        record.call,
+        PrecompileCallEvent {
+            address: *SHA256_ROUND_FUNCTION_PRECOMPILE_FORMAL_ADDRESS,
+            input_memory_offset: 0,
+            input_memory_length: 0,
+            output_memory_offset: 2,
+            output_memory_length: 1,
+            memory_page_to_read: heap_page,
+            memory_page_to_write: heap_page,
+            precompile_interpreted_data: 1,
+            extra_ergs_cost: EXTRA_ERGS,
+        }
+    );
+    assert_eq!(
+        record.input,
+        [word_bytes(U256::from(0xabcdu64)), word_bytes(U256::MAX)].concat()
+    );
+    let heap = &vm.memory.heaps.last().unwrap().0 .1;
+    assert!(heap[2].is_zero() == false);
+    assert_eq!(record.output, word_bytes(heap[2]));
+    assert_eq!(record.num_rounds, Some(1));
+}
+
+#[test]
+fn call_without_witness_has_only_abi() {
+    let vector = sha256_call_vector();
+    let mut vm = prepare_conformance_vm(&vector, PrecompileCallTracer::new());
+    run_conformance_cycles(&mut vm, vector.cycles).unwrap();
+
+    assert_eq!(vm.witness_tracer.calls.len(), 1);
+    let record = &vm.witness_tracer.calls[0];
+    assert_eq!(record.call.extra_ergs_cost, EXTRA_ERGS);
+    assert!(record.input.is_empty());
+    assert!(record.output.is_empty());
+    assert_eq!(record.num_rounds, None);
+}
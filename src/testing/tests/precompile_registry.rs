@@ -0,0 +1,197 @@
+use super::*;
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use crate::reference_impls::paged_memory::PagedMemory;
+use crate::reference_impls::precompiles::*;
+use crate::testing::conformance::*;
+use crate::witness_trace::DummyTracer;
+use zk_evm_abstractions::aux::{MemoryIndex, MemoryLocation, Timestamp};
+use zk_evm_abstractions::queries::{LogQuery, MemoryQuery};
+use zk_evm_abstractions::vm::{Memory, MemoryType, PrecompilesProcessor};
+use zkevm_opcode_defs::system_params::{
+    ECRECOVER_INNER_FUNCTION_PRECOMPILE_FORMAL_ADDRESS,
+    KECCAK256_ROUND_FUNCTION_PRECOMPILE_FORMAL_ADDRESS, PRECOMPILE_AUX_BYTE,
+    SHA256_ROUND_FUNCTION_PRECOMPILE_FORMAL_ADDRESS,
+};
+use zkevm_opcode_defs::{LogOpcode, Opcode, PrecompileCallABI};
+
+const PAGE: u32 = 4;
+
+fn precompile_query(address: Address, abi: PrecompileCallABI) -> LogQuery {
+    LogQuery {
+        timestamp: Timestamp(1),
+        tx_number_in_block: 0,
+        shard_id: 0,
+        aux_byte: PRECOMPILE_AUX_BYTE,
+        address,
+        key: abi.to_u256(),
+        read_value: U256::zero(),
+        written_value: U256::zero(),
+        rw_flag: false,
+        rollback: false,
+        is_service: false,
+    }
+}
+
+#[derive(Debug)]
+struct CountingPrecompile {
+    calls: Rc<Cell<usize>>,
+}
+
+impl PrecompileHandler for CountingPrecompile {
+    fn execute(
+        &mut self,
+        _monotonic_cycle_counter: u32,
+        _query: LogQuery,
+        _memory: &mut dyn Memory,
+    ) -> PrecompileCallResult {
+        self.calls.set(self.calls.get() + 1);
+
+        None
+    }
+}
+
+// always reports failure by writing zero success marker at the output offset, same as
+// ecrecover does for invalid signatures
+#[derive(Debug)]
+struct FailingPrecompile;
+
+impl PrecompileHandler for FailingPrecompile {
+    fn execute(
+        &mut self,
+        monotonic_cycle_counter: u32,
+        query: LogQuery,
+        memory: &mut dyn Memory,
+    ) -> PrecompileCallResult {
+        let abi = PrecompileCallABI::from_u256(query.key);
+        let write = MemoryQuery {
+            timestamp: Timestamp(query.timestamp.0 + 1),
+            location: MemoryLocation {
+                page: MemoryPage(abi.memory_page_to_write),
+                index: MemoryIndex(abi.output_memory_offset),
+                memory_type: MemoryType::Heap,
+            },
+            value: U256::zero(),
+            value_is_pointer: false,
+            rw_flag: true,
+        };
+        memory.execute_partial_query(monotonic_cycle_counter, write);
+
+        None
+    }
+}
+
+#[test]
+fn default_precompiles_are_registered() {
+    let registry = PrecompilesRegistry::with_default_precompiles::<false>();
+    for address in [
+        *KECCAK256_ROUND_FUNCTION_PRECOMPILE_FORMAL_ADDRESS,
+        *SHA256_ROUND_FUNCTION_PRECOMPILE_FORMAL_ADDRESS,
+        *ECRECOVER_INNER_FUNCTION_PRECOMPILE_FORMAL_ADDRESS,
+    ] {
+        assert!(registry.is_registered(&address));
+        assert!(PrecompilesRegistry::new().is_registered(&address) == false);
+    }
+}
+
+#[test]
+fn default_precompile_matches_default_processor() {
+    // single sha256 round over two words, offsets are in words
+    let abi = PrecompileCallABI {
+        input_memory_offset: 0,
+        input_memory_length: 0,
+        output_memory_offset: 2,
+        output_memory_length: 1,
+        memory_page_to_read: PAGE,
+        memory_page_to_write: PAGE,
+        precompile_interpreted_data: 1,
+    };
+    let query = precompile_query(*SHA256_ROUND_FUNCTION_PRECOMPILE_FORMAL_ADDRESS, abi);
+    let mut memory = PagedMemory::new();
+    memory.populate_page(PAGE, vec![U256::from(0x1234u64), U256::MAX]);
+    let mut expected_memory = memory.snapshot();
+
+    let mut registry = PrecompilesRegistry::with_default_precompiles::<false>();
+    registry.execute_precompile(1, query, &mut memory);
+    DefaultPrecompilesProcessor::<false>.execute_precompile(1, query, &mut expected_memory);
+
+    let content = memory.dump_page_content_as_u256_words(PAGE, 0..3);
+    assert!(content[2].is_zero() == false);
+    assert_eq!(
+        content,
+        expected_memory.dump_page_content_as_u256_words(PAGE, 0..3)
+    );
+}
+
+#[test]
+fn mock_precompile_replaces_default_one() {
+    let address = *ECRECOVER_INNER_FUNCTION_PRECOMPILE_FORMAL_ADDRESS;
+    let abi = PrecompileCallABI {
+        input_memory_offset: 0,
+        input_memory_length: 4,
+        output_memory_offset: 4,
+        output_memory_length: 2,
+        memory_page_to_read: PAGE,
+        memory_page_to_write: PAGE,
+        precompile_interpreted_data: 0,
+    };
+    let mut memory = PagedMemory::new();
+    memory.populate_page(PAGE, vec![U256::one(); 5]);
+
+    let mut registry = PrecompilesRegistry::with_default_precompiles::<false>();
+    assert!(registry
+        .register(address, Box::new(FailingPrecompile))
+        .is_some());
+    registry.execute_precompile(1, precompile_query(address, abi), &mut memory);
+    assert_eq!(
+        memory.dump_page_content_as_u256_words(PAGE, 0..5),
+        vec![
+            U256::one(),
+            U256::one(),
+            U256::one(),
+            U256::one(),
+            U256::zero()
+        ]
+    );
+
+    // calls to unknown addresses do nothing
+    registry.unregister(&address);
+    assert!(registry
+        .execute_precompile(2, precompile_query(address, abi), &mut memory)
+        .is_none());
+}
+
+#[test]
+fn vm_calls_registered_precompile() {
+    let address = *KECCAK256_ROUND_FUNCTION_PRECOMPILE_FORMAL_ADDRESS;
+    let program = [Instruction::new(Opcode::Log(LogOpcode::PrecompileCall))
+        .src0(1)
+        .src1(2)
+        .dst0(3)];
+    let vector = ConformanceVector {
+        name: "precompile_call".to_owned(),
+        program: program.iter().map(|el| el.encode()).collect(),
+        cycles: program.len(),
+        initial: InitialState {
+            this_address: address,
+            ..InitialState::default()
+        },
+        expected: ExpectedState::default(),
+    };
+
+    let calls = Rc::new(Cell::new(0));
+    let mut vm = prepare_conformance_vm(&vector, DummyTracer);
+    vm.precompiles_processor.register(
+        address,
+        Box::new(CountingPrecompile {
+            calls: calls.clone(),
+        }),
+    );
+    let (_, panicked) = run_conformance_cycles(&mut vm, vector.cycles).unwrap();
+
+    assert!(panicked == false);
+    assert_eq!(calls.get(), 1);
+    assert_eq!(vm.local_state.registers[2].value, U256::one());
+}
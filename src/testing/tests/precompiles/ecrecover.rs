@@ -1,104 +1,132 @@
 use super::*;
 
-fn fill_memory<M: Memory>(hash: [u8; 32], r: [u8; 32], s: [u8; 32], v: bool, page: u32, memory: &mut M) -> u16 {
-    let mut location = MemoryLocation {page: MemoryPage(page), index: MemoryIndex(0)};
-    let query = MemoryQuery {
-        timestamp: Timestamp(0u32),
-        location,
-        value: U256::from_big_endian(&hash),
-        rw_flag: true
-    };
-    let _ = memory.execute_partial_query(query);
-
-    location.index.0 += 1;
-    let query = MemoryQuery {
-        timestamp: Timestamp(0u32),
-        location,
-        value: U256::from_big_endian(&r),
-        rw_flag: true
-    };
-    let _ = memory.execute_partial_query(query);
-
-    location.index.0 += 1;
-    let query = MemoryQuery {
-        timestamp: Timestamp(0u32),
-        location,
-        value: U256::from_big_endian(&s),
-        rw_flag: true
-    };
-    let _ = memory.execute_partial_query(query);
-
-    location.index.0 += 1;
-    let mut buffer = [0u8; 32];
+use zk_evm_abstractions::aux::*;
+use zk_evm_abstractions::queries::MemoryQuery;
+use zk_evm_abstractions::vm::Memory;
+use zk_evm_abstractions::vm::MemoryType;
+use zk_evm_abstractions::vm::PrecompilesProcessor;
+use zkevm_opcode_defs::PrecompileCallABI;
+
+// input is laid out as hash, v, r, s
+fn fill_memory<M: Memory>(
+    hash: [u8; 32],
+    r: [u8; 32],
+    s: [u8; 32],
+    v: bool,
+    page: u32,
+    memory: &mut M,
+) -> u32 {
+    let mut v_word = U256::zero();
     if v {
-        buffer[31] = 1;
+        v_word = U256::one();
+    }
+    let words = [
+        U256::from_big_endian(&hash),
+        v_word,
+        U256::from_big_endian(&r),
+        U256::from_big_endian(&s),
+    ];
+
+    for (index, value) in words.into_iter().enumerate() {
+        let location = MemoryLocation {
+            page: MemoryPage(page),
+            index: MemoryIndex(index as u32),
+            memory_type: MemoryType::Heap,
+        };
+        let query = MemoryQuery {
+            timestamp: Timestamp(0u32),
+            location,
+            value,
+            value_is_pointer: false,
+            rw_flag: true,
+        };
+        let _ = memory.execute_partial_query(1, query);
     }
-    let query = MemoryQuery {
-        timestamp: Timestamp(0u32),
-        location,
-        value: U256::from_big_endian(&buffer),
-        rw_flag: true
-    };
-    let _ = memory.execute_partial_query(query);
-
 
-    4 as u16
+    words.len() as u32
 }
 
-fn ecrecover_test_inner(hash: [u8; 32], r: [u8; 32], s: [u8; 32], v: bool, expect_ok: bool, expected_address: [u8; 20]) -> (Vec<[u8; 32]>, std::ops::Range<u16>) {
+fn ecrecover_test_inner(
+    hash: [u8; 32],
+    r: [u8; 32],
+    s: [u8; 32],
+    v: bool,
+    expect_ok: bool,
+    expected_address: [u8; 20],
+) -> (Vec<[u8; 32]>, std::ops::Range<u32>) {
     let mut memory = SimpleMemory::new();
+
+    let input_memory_page = 4u32;
+    let output_memory_page = 4u32;
+
+    memory.heaps.push((
+        (input_memory_page, vec![U256::zero(); 1 << 10]),
+        (0, vec![U256::zero(); 0]),
+    ));
+    memory.page_numbers_indirections.insert(
+        input_memory_page,
+        reference_impls::memory::Indirection::Heap(1),
+    );
     let mut precompiles_processor = DefaultPrecompilesProcessor::<false>;
 
     // fill the memory
-    let num_words_used = fill_memory(hash, r, s, v, 0u32, &mut memory);
-
-    let precompile_call_params = PrecompileCallParams {
-        input_location: MemoryLocation {page: MemoryPage(0u32), index: MemoryIndex(0u16)},
-        output_location: MemoryLocation {page: MemoryPage(0u32), index: MemoryIndex(num_words_used)},
-        timestamp_for_input_read: Timestamp(1u32),
-        timestamp_for_output_write: Timestamp(2u32),
+    let num_words_used = fill_memory(hash, r, s, v, input_memory_page, &mut memory);
+
+    // offsets are in words for this precompile
+    let precompile_abi = PrecompileCallABI {
+        input_memory_offset: 0,
+        input_memory_length: num_words_used,
+        output_memory_offset: num_words_used,
+        output_memory_length: 2,
+        memory_page_to_read: input_memory_page,
+        memory_page_to_write: output_memory_page,
+        precompile_interpreted_data: 0,
     };
 
-    let address = Address::from_low_u64_be(ECRECOVER_INNER_FUNCTION_PRECOMPILE_ADDRESS as u64);
+    let address =
+        *zkevm_opcode_defs::system_params::ECRECOVER_INNER_FUNCTION_PRECOMPILE_FORMAL_ADDRESS;
 
     let precompile_query = LogQuery {
-        timestamp: precompile_call_params.timestamp_for_input_read,
+        timestamp: Timestamp(1),
         tx_number_in_block: 0,
         shard_id: 0,
-        aux_byte: PRECOMPILE_AUX_BYTE,
+        aux_byte: zkevm_opcode_defs::system_params::PRECOMPILE_AUX_BYTE,
         address,
-        key: U256::zero(),
-        read_value: precompile_call_params.encode_into_log_value(),
+        key: precompile_abi.to_u256(),
+        read_value: U256::zero(),
         written_value: U256::zero(),
         rw_flag: false,
         rollback: false,
         is_service: false,
     };
 
-    let _ = precompiles_processor.execute_precompile(precompile_query, &mut memory);
+    let _ = precompiles_processor.execute_precompile(4, precompile_query, &mut memory);
 
-    let range = 0u16..(num_words_used + 2);
-    let content = memory.dump_page_content(0u32, range.clone());
+    let range = 0u32..(num_words_used + 2);
+    let content = memory.dump_page_content(output_memory_page, range.clone());
     let content_len = content.len();
-    let expected_output = content[content_len-1];
-    let ok_or_error_marker = content[content_len-2];
+    let output = content[content_len - 1];
+    let ok_or_error_marker = content[content_len - 2];
 
+    let mut buffer = [0u8; 32];
     if expect_ok {
-        let mut buffer = [0u8; 32];
         U256::one().to_big_endian(&mut buffer);
         assert!(ok_or_error_marker == buffer);
-        assert_eq!(&expected_output[12..], &expected_address);
+        assert_eq!(&output[12..], &expected_address);
     } else {
-        let mut buffer = [0u8; 32];
         U256::zero().to_big_endian(&mut buffer);
         assert!(ok_or_error_marker == buffer);
-        assert_eq!(&expected_output[..], &[0u8; 32]);
+        assert_eq!(&output[..], &[0u8; 32]);
     }
 
     (content, range)
 }
 
-fn ecrecover_test_inner_from_raw(raw_input: &str, raw_address: &str, expect_ok: bool) -> (Vec<[u8; 32]>, std::ops::Range<u16>) {
+fn ecrecover_test_inner_from_raw(
+    raw_input: &str,
+    raw_address: &str,
+    expect_ok: bool,
+) -> (Vec<[u8; 32]>, std::ops::Range<u32>) {
     let input_bytes = hex::decode(raw_input).unwrap();
     let hash: [u8; 32] = input_bytes[0..32].try_into().unwrap();
     let v_padded: [u8; 32] = input_bytes[32..64].try_into().unwrap();
@@ -109,16 +137,11 @@ fn ecrecover_test_inner_from_raw(raw_input: &str, raw_address: &str, expect_ok:
     let offset = address.len() - 20;
     let expected_address: [u8; 20] = address[offset..].try_into().unwrap();
 
-    let v = if v_padded[31] == 1 {
-        true
-    } else if v_padded[31] == 0 {
-        false
-    } else if v_padded[31] == 28 {
-        true
-    } else if v_padded[31] == 27 {
-        false
-    } else {
-        panic!("v = {}", v_padded[31]);
+    // precompile expects recovery id, not the Ethereum's `v`
+    let v = match v_padded[31] {
+        0 | 27 => false,
+        1 | 28 => true,
+        v => panic!("v = {}", v),
     };
 
     ecrecover_test_inner(hash, r, s, v, expect_ok, expected_address)
@@ -136,9 +159,9 @@ fn test_valid() {
 fn test_valid_large_s() {
     let raw_input = "38d18acb67d25c8bb9942764b62f18e17054f66a817bd4295423adf9ed98873e000000000000000000000000000000000000000000000000000000000000001b38d18acb67d25c8bb9942764b62f18e17054f66a817bd4295423adf9ed98873e7fffffffffffffffffffffffffffffff5d576e7357a4501ddfe92f46681b20a0";
     let raw_address = hex::encode(&vec![
-        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 88, 198, 174, 93, 17, 93, 119, 163, 216, 169, 239,
-        54, 214, 164, 45, 35, 105, 43, 170, 127,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 88, 198, 174, 93, 17, 93, 119, 163, 216, 169, 239, 54,
+        214, 164, 45, 35, 105, 43, 170, 127,
     ]);
     let (content, range) = ecrecover_test_inner_from_raw(raw_input, &raw_address, true);
     pretty_print_memory_dump(&content, range);
-}
\ No newline at end of file
+}
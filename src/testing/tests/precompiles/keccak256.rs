@@ -1,6 +1,6 @@
 use super::*;
 
-use zk_evm_abstractions::auxiliary::*;
+use zk_evm_abstractions::aux::*;
 use zk_evm_abstractions::queries::MemoryQuery;
 use zk_evm_abstractions::vm::Memory;
 use zk_evm_abstractions::vm::MemoryType;
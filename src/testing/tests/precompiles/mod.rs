@@ -1,8 +1,8 @@
 use super::*;
 
+mod ecrecover;
 mod keccak256;
-// mod sha256;
-// mod ecrecover;
+mod sha256;
 
 fn pretty_print_memory_dump(content: &Vec<[u8; 32]>, range: std::ops::Range<u32>) {
     println!("Memory dump:");
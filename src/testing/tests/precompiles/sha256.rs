@@ -1,8 +1,14 @@
 use super::*;
 
-use crate::precompiles::sha256::*;
-
-fn pad_and_fill_memory<M: Memory>(input: &[u8], page: u32, memory: &mut M) -> u16 {
+use zk_evm_abstractions::aux::*;
+use zk_evm_abstractions::queries::MemoryQuery;
+use zk_evm_abstractions::vm::Memory;
+use zk_evm_abstractions::vm::MemoryType;
+use zk_evm_abstractions::vm::PrecompilesProcessor;
+use zkevm_opcode_defs::PrecompileCallABI;
+
+// returns number of words written and number of rounds
+fn pad_and_fill_memory<M: Memory>(input: &[u8], page: u32, memory: &mut M) -> (u32, u32) {
     let mut padded = vec![];
     padded.extend_from_slice(input);
 
@@ -11,13 +17,12 @@ fn pad_and_fill_memory<M: Memory>(input: &[u8], page: u32, memory: &mut M) -> u1
     let message_bitlen = (padded.len() * 8) as u64;
     let last_block_size = padded.len() % block_size;
 
-    let (num_of_zero_bytes, _pad_overflowed) = if last_block_size <= (64 - 1 - 8) {
-        (64 - 1 - 8 - last_block_size, false)
-    }
-    else {
-        (128 - 1 - 8 - last_block_size, true)
+    let num_of_zero_bytes = if last_block_size <= (64 - 1 - 8) {
+        64 - 1 - 8 - last_block_size
+    } else {
+        128 - 1 - 8 - last_block_size
     };
-    
+
     padded.push(1u8 << 7);
     padded.extend(std::iter::repeat(0u8).take(num_of_zero_bytes));
 
@@ -26,91 +31,93 @@ fn pad_and_fill_memory<M: Memory>(input: &[u8], page: u32, memory: &mut M) -> u1
     padded.extend(repr.into_iter());
     assert_eq!(padded.len() % block_size, 0);
 
-    // number of words to put the data
-    let num_words = padded.len() / 32;
-
-    // number of rounds of invocation
+    // every round takes two words
     let num_rounds = padded.len() / block_size;
 
-    let mut num_rounds_u256 = U256::zero();
-    num_rounds_u256.0[0] = num_rounds as u64;
-    println!("Num rounds = {}", num_rounds_u256);
-    let mut location = MemoryLocation {page: MemoryPage(page), index: MemoryIndex(0)};
-    let num_rounds_query = MemoryQuery {
-        timestamp: Timestamp(0u32),
-        location,
-        value: num_rounds_u256,
-        rw_flag: true
-    };
-
-    let _ = memory.execute_partial_query(num_rounds_query);
-
-    let mut chunk_iter = padded.chunks_exact(32);
-    assert_eq!(chunk_iter.len(), num_words);
-
-    for word_bytes in &mut chunk_iter {
-        location.index.0 += 1;
-        let value = U256::from_big_endian(word_bytes);
+    let mut index = 0u32;
+    for word_bytes in padded.chunks_exact(32) {
+        let location = MemoryLocation {
+            page: MemoryPage(page),
+            index: MemoryIndex(index),
+            memory_type: MemoryType::Heap,
+        };
         let data_query = MemoryQuery {
             timestamp: Timestamp(0u32),
             location,
-            value,
-            rw_flag: true
+            value: U256::from_big_endian(word_bytes),
+            value_is_pointer: false,
+            rw_flag: true,
         };
 
-        let _ = memory.execute_partial_query(data_query);
+        let _ = memory.execute_partial_query(1, data_query);
+        index += 1;
     }
 
-    assert!(chunk_iter.remainder().len() == 0);
-
-    (1 + num_words) as u16
+    (index, num_rounds as u32)
 }
 
-use sha2::*;
+use sha2::Digest;
+use sha2::Sha256;
 
-fn run_sha256_test_inner(input: &[u8]) -> (Vec<[u8; 32]>, std::ops::Range<u16>) {
+fn run_sha256_test_inner(input: &[u8]) -> (Vec<[u8; 32]>, std::ops::Range<u32>) {
     let mut memory = SimpleMemory::new();
+
+    let input_memory_page = 4u32;
+    let output_memory_page = 4u32;
+
+    memory.heaps.push((
+        (input_memory_page, vec![U256::zero(); 1 << 10]),
+        (0, vec![U256::zero(); 0]),
+    ));
+    memory.page_numbers_indirections.insert(
+        input_memory_page,
+        reference_impls::memory::Indirection::Heap(1),
+    );
     let mut precompiles_processor = DefaultPrecompilesProcessor::<false>;
 
     let mut hasher = Sha256::default();
     hasher.update(input);
     let result = hasher.finalize();
-    let bytes: &[u8] = result.as_ref();
-    println!("{}", hex::encode(bytes));
+    let expected_output: &[u8] = result.as_ref();
 
     // fill the memory
-    let num_words_used = pad_and_fill_memory(input, 0u32, &mut memory);
-
-    let precompile_call_params = PrecompileCallParams {
-        input_location: MemoryLocation {page: MemoryPage(0u32), index: MemoryIndex(0u16)},
-        output_location: MemoryLocation {page: MemoryPage(0u32), index: MemoryIndex(num_words_used)},
-        timestamp_for_input_read: Timestamp(1u32),
-        timestamp_for_output_write: Timestamp(2u32),
+    let (num_words_used, num_rounds) = pad_and_fill_memory(input, input_memory_page, &mut memory);
+
+    // offsets are in words for this precompile
+    let precompile_abi = PrecompileCallABI {
+        input_memory_offset: 0,
+        input_memory_length: 0,
+        output_memory_offset: num_words_used,
+        output_memory_length: 1,
+        memory_page_to_read: input_memory_page,
+        memory_page_to_write: output_memory_page,
+        precompile_interpreted_data: num_rounds as u64,
     };
 
-    let address = Address::from_low_u64_be(SHA256_ROUND_FUNCTION_PRECOMPILE_ADDRESS as u64);
+    let address =
+        *zkevm_opcode_defs::system_params::SHA256_ROUND_FUNCTION_PRECOMPILE_FORMAL_ADDRESS;
 
     let precompile_query = LogQuery {
-        timestamp: precompile_call_params.timestamp_for_input_read,
+        timestamp: Timestamp(1),
         tx_number_in_block: 0,
         shard_id: 0,
-        aux_byte: PRECOMPILE_AUX_BYTE,
+        aux_byte: zkevm_opcode_defs::system_params::PRECOMPILE_AUX_BYTE,
         address,
-        key: U256::zero(),
-        read_value: precompile_call_params.encode_into_log_value(),
+        key: precompile_abi.to_u256(),
+        read_value: U256::zero(),
         written_value: U256::zero(),
         rw_flag: false,
         rollback: false,
         is_service: false,
     };
 
-    let _ = precompiles_processor.execute_precompile(precompile_query, &mut memory);
+    let _ = precompiles_processor.execute_precompile(4, precompile_query, &mut memory);
 
-    let range = 0u16..(num_words_used + 1);
-    let content = memory.dump_page_content(0u32, range.clone());
-    let expected_output = content.last().copied().unwrap();
+    let range = 0u32..(num_words_used + 1);
+    let content = memory.dump_page_content(output_memory_page, range.clone());
+    let output = content.last().copied().unwrap();
 
-    assert_eq!(&expected_output[..], bytes);
+    assert_eq!(&expected_output[..], &output[..]);
 
     (content, range)
 }
@@ -133,4 +140,4 @@ fn test_very_long_sha256() {
     let data = vec![255u8; 10_000];
     let (content, range) = run_sha256_test_inner(&data);
     pretty_print_memory_dump(&content, range);
-}
\ No newline at end of file
+}
@@ -0,0 +1,61 @@
+use super::*;
+
+use crate::reference_impls::receipts::*;
+use zk_evm_abstractions::aux::Timestamp;
+use zk_evm_abstractions::vm::EventSink;
+use zkevm_opcode_defs::system_params::{ADDRESS_EVENT_WRITER, EVENT_AUX_BYTE};
+
+fn event_query(timestamp: u32, tx_number_in_block: u16, emitter: Address) -> LogQuery {
+    LogQuery {
+        timestamp: Timestamp(timestamp),
+        tx_number_in_block,
+        aux_byte: EVENT_AUX_BYTE,
+        shard_id: 0,
+        address: Address::from_low_u64_be(ADDRESS_EVENT_WRITER as u64),
+        // single topic - emitter itself
+        key: U256::one(),
+        read_value: U256::zero(),
+        written_value: address_to_u256(&emitter),
+        rw_flag: true,
+        rollback: false,
+        is_service: true,
+    }
+}
+
+#[test]
+fn receipts_ignore_rolled_back_frames() {
+    let kept = Address::from_low_u64_be(0x10000);
+    let reverted = Address::from_low_u64_be(0x20000);
+
+    let mut sink = InMemoryEventSink::new();
+    sink.start_frame(Timestamp(0));
+    sink.add_partial_query(0, event_query(4, 0, kept));
+    sink.start_frame(Timestamp(5));
+    sink.add_partial_query(1, event_query(8, 0, reverted));
+    sink.finish_frame(true, Timestamp(12));
+    sink.finish_frame(false, Timestamp(16));
+
+    let results = vec![
+        TransactionResult {
+            tx_number_in_block: 0,
+            status: TransactionStatus::from_bootloader_result(U256::one()),
+            ergs_used: 100,
+        },
+        TransactionResult {
+            tx_number_in_block: 1,
+            status: TransactionStatus::from_bootloader_result(U256::zero()),
+            ergs_used: 50,
+        },
+    ];
+    let receipts = sink.into_receipts(&results);
+    assert_eq!(receipts.len(), 2);
+    assert!(receipts[0].status.is_success());
+    assert_eq!(receipts[0].logs.len(), 1);
+    assert_eq!(receipts[0].logs[0].address, kept);
+    assert!(receipts[0].logs_bloom.contains_input(kept.as_bytes()));
+    assert!(!receipts[0].logs_bloom.contains_input(reverted.as_bytes()));
+
+    assert!(!receipts[1].status.is_success());
+    assert!(receipts[1].logs_bloom.is_empty());
+    assert_eq!(block_logs_bloom(&receipts), receipts[0].logs_bloom);
+}
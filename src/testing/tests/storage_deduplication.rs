@@ -0,0 +1,133 @@
+use super::*;
+
+use crate::errors::StorageLogError;
+use crate::testing::conformance::*;
+use crate::witness_trace::full::*;
+use crate::witness_trace::storage_deduplication::*;
+use zk_evm_abstractions::aux::Timestamp;
+use zk_evm_abstractions::queries::LogQuery;
+use zk_evm_abstractions::vm::Storage;
+use zkevm_opcode_defs::system_params::STORAGE_AUX_BYTE;
+
+fn storage_query(key: u64, timestamp: u32, read: u64, written: Option<u64>) -> LogQuery {
+    LogQuery {
+        timestamp: Timestamp(timestamp),
+        tx_number_in_block: 0,
+        aux_byte: STORAGE_AUX_BYTE,
+        shard_id: 0,
+        address: Address::from_low_u64_be(0x8001),
+        key: U256::from(key),
+        read_value: U256::from(read),
+        written_value: U256::from(written.unwrap_or(read)),
+        rw_flag: written.is_some(),
+        rollback: false,
+        is_service: false,
+    }
+}
+
+fn rollback_of(query: LogQuery) -> LogQuery {
+    LogQuery {
+        rollback: true,
+        ..query
+    }
+}
+
+#[test]
+fn rolled_back_writes_are_cancelled() {
+    let reverted_write = storage_query(1, 3, 5, Some(6));
+    let queries = [
+        storage_query(1, 1, 0, Some(5)),
+        storage_query(2, 2, 7, Some(8)),
+        reverted_write,
+        // read inside of the frame that will panic
+        storage_query(1, 4, 6, None),
+        rollback_of(reverted_write),
+        storage_query(1, 5, 5, None),
+    ];
+
+    let log = deduplicate_storage_log(&queries, |_, _, key| *key == U256::from(2u64)).unwrap();
+    assert_eq!(
+        log.sorted_queries
+            .iter()
+            .map(|el| (el.key.low_u64(), el.timestamp.0))
+            .collect::<Vec<_>>(),
+        vec![(1, 1), (1, 4), (1, 5), (2, 2)]
+    );
+    assert_eq!(
+        log.slots
+            .iter()
+            .map(|el| (
+                el.key.low_u64(),
+                el.initial_value.low_u64(),
+                el.final_value.low_u64()
+            ))
+            .collect::<Vec<_>>(),
+        vec![(1, 0, 5), (2, 7, 8)]
+    );
+    assert_eq!(log.initial_writes, vec![log.slots[0]]);
+    assert_eq!(log.repeated_writes, vec![log.slots[1]]);
+}
+
+#[test]
+fn inconsistent_history_is_reported() {
+    let queries = [
+        storage_query(1, 1, 0, Some(5)),
+        storage_query(1, 2, 0, None),
+    ];
+    assert!(matches!(
+        deduplicate_storage_log(&queries, |_, _, _| false),
+        Err(StorageLogError::ReadValueMismatch { timestamp: 2, .. })
+    ));
+
+    let queries = [rollback_of(storage_query(1, 1, 0, Some(5)))];
+    assert!(matches!(
+        deduplicate_storage_log(&queries, |_, _, _| false),
+        Err(StorageLogError::RollbackWithoutWrite { timestamp: 1, .. })
+    ));
+}
+
+#[test]
+fn storage_log_of_default_vectors_matches_storage() {
+    for vector in default_conformance_vectors() {
+        let mut vm = prepare_conformance_vm(&vector, FullWitnessTracer::new());
+        run_conformance_cycles(&mut vm, vector.cycles).unwrap();
+        let witness = vm.witness_tracer.clone().into_witness();
+
+        let is_known_slot = |shard_id: u8, address: &Address, key: &U256| {
+            vector
+                .initial
+                .storage
+                .iter()
+                .any(|el| (el.0, el.1, el.2) == (shard_id, *address, *key))
+        };
+        let queries: Vec<_> = witness.storage_queue.iter().map(|(_, el)| *el).collect();
+        let log = deduplicate_storage_log(&queries, is_known_slot).unwrap();
+        log.check_against_storage(&vm.storage.inner).unwrap();
+
+        // oracle's own history gives the same summary
+        let mut storage = vm.storage.clone();
+        while storage.frames_stack.len() > 1 {
+            storage.finish_frame(Timestamp(vm.local_state.timestamp), false);
+        }
+        let (history, _) = storage.flatten_and_net_history();
+        let oracle_log = deduplicate_storage_log(&history, is_known_slot).unwrap();
+        assert_eq!(oracle_log.slots, log.slots, "vector {}", vector.name);
+    }
+}
+
+#[test]
+fn final_value_is_checked_against_storage() {
+    let log = deduplicate_storage_log(&[storage_query(1, 1, 0, Some(5))], |_, _, _| false).unwrap();
+    let mut storage: [HashMap<Address, HashMap<U256, U256>>; NUM_SHARDS] =
+        [(); NUM_SHARDS].map(|_| HashMap::new());
+    assert!(matches!(
+        log.check_against_storage(&storage),
+        Err(StorageLogError::FinalValueMismatch { actual, .. }) if actual.is_zero()
+    ));
+
+    storage[0]
+        .entry(Address::from_low_u64_be(0x8001))
+        .or_insert_with(HashMap::new)
+        .insert(U256::one(), U256::from(5u64));
+    log.check_against_storage(&storage).unwrap();
+}
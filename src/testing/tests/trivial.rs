@@ -1,75 +1,180 @@
 use super::*;
 
-use super::simple_tracer::*;
+use crate::block_properties::BlockProperties;
+use crate::ethereum_types::H256;
+use crate::reference_impls::event_decoder::{EthereumLikeLog, L2ToL1Message};
+use crate::reference_impls::receipts::TransactionStatus;
+use crate::testing::bootloader_harness::*;
+use crate::testing::conformance::{assemble, Instruction};
+use zkevm_opcode_defs::decoding::{EncodingModeProduction, VmEncodingMode};
+use zkevm_opcode_defs::*;
+
+fn default_block_properties() -> BlockProperties {
+    BlockProperties {
+        default_aa_code_hash: U256::zero(),
+        zkporter_is_available: false,
+    }
+}
+
+fn default_layout() -> BootloaderMemoryLayout {
+    BootloaderMemoryLayout {
+        transactions_offset_in_words: 16,
+        results_offset_in_words: 8,
+    }
+}
 
 #[test]
-fn run_dummy_log_and_unmapped_noop() {
-    let mut tools = create_default_testing_tools();
-    let block_properties = create_default_block_info();
-    let mut vm = create_initial_vm_state_for_basic_testing(&mut tools, &block_properties);
-
-    let tracing_closure = |state: &VmState<_, _, _, _, _, _>, aux: AuxTracingInformation, cycle_idx: u32| {
-        println!("------------------------------------------------------------");
-        println!("After the cycle {}", cycle_idx);
-        println!("Did skip cycle: {}", aux.skip_cycle);
-        println!("Executed opcode: {}", aux.final_masked_opcode);
-        println!("Registers:");
-        for (i, r) in state.registers.iter().enumerate() {
-            println!("r{} = {:x}", i, r);
-        }
-        println!("Flags = {:?}", state.flags);
-        println!("Resolved jump condition = {}", aux.resolved_jump_condition);
-        println!("Exceptions: {:?}", aux.error_flags_collection);
-        if state.execution_has_ended() {
-            println!("Execution has ended");
-        } else {
-            println!("Execution continues");
-        }
-        println!("Final context value: {:?}", state.callstack.get_current_stack());
-        println!("Pending state: {:?}", state.pending_port);
-        println!("Pending cycles left: {:?}", state.pending_cycles_left);
-    };
+fn run_bootloader_that_panics_immediately() {
+    let panic = EncodingModeProduction::exception_revert_encoding();
+    let config = BootloaderHarnessConfig::new(assemble(&[panic]), default_layout());
+    let transactions = vec![vec![U256::from(1u64)], vec![U256::from(2u64)]];
+
+    let result = run_bootloader(
+        create_default_testing_tools(),
+        default_block_properties(),
+        &config,
+        &transactions,
+    )
+    .unwrap();
+
+    assert_eq!(result.cycles_used, 1);
+    assert!(result.final_local_state.execution_has_ended());
+    assert_eq!(result.transactions.len(), 2);
+    for (i, outcome) in result.transactions.iter().enumerate() {
+        assert_eq!(outcome.tx_number_in_block, i as u16);
+        assert_eq!(outcome.status, TransactionStatus::Failure);
+        assert!(outcome.events.is_empty());
+        assert!(outcome.l1_messages.is_empty());
+        assert!(outcome.storage_diff.is_empty());
+    }
+    assert!(result.full_storage_history.is_empty());
+}
+
+#[test]
+fn nop_is_executed_before_panic() {
+    let nop = EncodingModeProduction::nop_encoding();
+    let panic = EncodingModeProduction::exception_revert_encoding();
+    let config = BootloaderHarnessConfig::new(assemble(&[nop, nop, panic]), default_layout());
 
-    let mut debug_tracer = ClosureBasedTracer::new(tracing_closure); 
-
-    vm.memory.populate(vec![
-        (BOOTLOADER_CODE_PAGE, vec![U256::from_str_radix("10", 10).unwrap()])
-    ]);
-
-    vm.cycle(&mut debug_tracer);
-    vm.cycle(&mut debug_tracer);
-    vm.cycle(&mut debug_tracer);
-    vm.cycle(&mut debug_tracer);
-
-    let (full_storage_access_history, storage_pre_shard, events_log_history, events, l1_messages, _) = get_final_net_states!(vm, tools);
-    
-    println!("------------------------------------------------------");
-    println!("Storage log access history:");
-    println!("{:?}", full_storage_access_history);
-    println!("Event log access history:");
-    println!("{:?}", events_log_history);
-
-    println!("------------------------------------------------------");
-    println!("Net events:");
-    println!("{:?}", events);
-    println!("Net L1 messages:");
-    println!("{:?}", l1_messages);
+    let result = run_bootloader(
+        create_default_testing_tools(),
+        default_block_properties(),
+        &config,
+        &[],
+    )
+    .unwrap();
+
+    assert_eq!(result.cycles_used, 3);
+    assert!(result.transactions.is_empty());
+}
+
+#[test]
+fn run_bootloader_respects_cycles_limit() {
+    let nop = EncodingModeProduction::nop_encoding();
+    let panic = EncodingModeProduction::exception_revert_encoding();
+    let mut config = BootloaderHarnessConfig::new(assemble(&[nop, panic]), default_layout());
+    config.max_cycles = 1;
+
+    let result = run_bootloader(
+        create_default_testing_tools(),
+        default_block_properties(),
+        &config,
+        &[vec![U256::zero()]],
+    );
+
+    assert!(result.is_err());
 }
 
 #[test]
-fn check_nop_moves_sp() {
-    let mut tools = create_default_testing_tools();
-    let block_properties = create_default_block_info();
-    let mut vm = create_initial_vm_state_for_basic_testing(&mut tools, &block_properties);
+fn run_bootloader_with_successful_transaction() {
+    let bootloader = Address::from_low_u64_be(system_params::ADDRESS_BOOTLOADER as u64);
+    let results_offset_in_bytes = default_layout().results_offset_in_words as u16 * 32;
+    let add_imm = |imm: u16, dst: u8| {
+        Instruction::new(Opcode::Add(AddOpcode::Add))
+            .src0_imm(imm)
+            .dst0(dst)
+    };
+    let log = |variant: LogOpcode, src0: u8, src1: u8| {
+        Instruction::new(Opcode::Log(variant)).src0(src0).src1(src1)
+    };
+    // first transaction writes storage, emits an event and L1 message and reports success,
+    // second one writes and restores a slot and reports nothing
+    let first_tx = [
+        add_imm(1, 1),
+        add_imm(5, 2),
+        log(LogOpcode::StorageWrite, 1, 2),
+        log(LogOpcode::Event, 1, 2).flag(FIRST_MESSAGE_FLAG_IDX),
+        log(LogOpcode::ToL1Message, 1, 2).flag(FIRST_MESSAGE_FLAG_IDX),
+        add_imm(results_offset_in_bytes, 3),
+        Instruction::new(Opcode::UMA(UMAOpcode::HeapWrite))
+            .src0(3)
+            .src1(1),
+        Instruction::new(Opcode::Context(ContextOpcode::IncrementTxNumber)),
+    ];
+    let second_tx = [
+        add_imm(2, 4),
+        log(LogOpcode::StorageWrite, 4, 2),
+        log(LogOpcode::StorageWrite, 4, 0),
+        Instruction::new(Opcode::Ret(RetOpcode::Ok)),
+    ];
+    let program: Vec<_> = first_tx
+        .iter()
+        .chain(second_tx.iter())
+        .map(|el| el.encode())
+        .collect();
+    let config = BootloaderHarnessConfig::new(assemble(&program), default_layout());
 
-    let default_tracing_tool = DefaultTracingClosure::new();
-    let mut debug_tracer = ClosureBasedTracer::new(|a, b, c| default_tracing_tool.trace(a, b, c)); 
+    let result = run_bootloader(
+        create_default_testing_tools(),
+        default_block_properties(),
+        &config,
+        &[vec![U256::from(1u64)], vec![U256::from(2u64)]],
+    )
+    .unwrap();
+
+    assert_eq!(result.cycles_used, program.len());
+    let [first, second] = result.transactions.as_slice() else {
+        panic!("expected two transactions, got {:?}", result.transactions);
+    };
 
-    // manually encode LE
-    let opcode = "000100005000002000";
-    vm.memory.populate(vec![
-        (BOOTLOADER_CODE_PAGE, vec![U256::from_little_endian(&hex::decode(opcode).unwrap())])
-    ]);
+    assert_eq!(first.status, TransactionStatus::Success);
+    assert!(first.ergs_used >= first_tx.iter().map(|el| el.ergs_price()).sum::<u32>());
+    assert_eq!(
+        first.events,
+        vec![EthereumLikeLog {
+            tx_number_in_block: 0,
+            log_index_in_tx: 0,
+            address: bootloader,
+            topics: vec![H256::from_low_u64_be(5)],
+            data: vec![],
+        }]
+    );
+    assert_eq!(
+        first.l1_messages,
+        vec![L2ToL1Message {
+            shard_id: 0,
+            is_service: true,
+            tx_number_in_block: 0,
+            sender: bootloader,
+            key: U256::from(1u64),
+            value: U256::from(5u64),
+        }]
+    );
+    assert_eq!(
+        first.storage_diff,
+        vec![StorageDiffEntry {
+            shard_id: 0,
+            address: bootloader,
+            key: U256::from(1u64),
+            initial_value: U256::zero(),
+            final_value: U256::from(5u64),
+        }]
+    );
 
-    vm.cycle(&mut debug_tracer);
+    assert_eq!(second.status, TransactionStatus::Failure);
+    assert!(second.ergs_used >= second_tx.iter().map(|el| el.ergs_price()).sum::<u32>());
+    assert!(second.events.is_empty());
+    assert!(second.l1_messages.is_empty());
+    // slot was restored within the transaction
+    assert!(second.storage_diff.is_empty());
 }
@@ -47,6 +47,55 @@ pub fn u256_to_address_unchecked(integer: &U256) -> crate::Address {
     crate::Address::from_slice(&buffer[12..32])
 }
 
+// Heaps are word addressed, while UMA opcodes address them by bytes. Byte `i` is the byte
+// number `i % 32` of the big-endian encoding of the word `i / 32`
+
+/// Range of words that contains `len` bytes starting at the byte `offset`
+pub fn words_range_for_bytes(offset: u32, len: u32) -> std::ops::Range<u32> {
+    let first_word = offset / 32;
+    if len == 0 {
+        return first_word..first_word;
+    }
+    let end = offset as u64 + len as u64;
+
+    first_word..((end + 31) / 32) as u32
+}
+
+/// Extracts `len` bytes starting at the byte `offset` from words that were taken from
+/// `words_range_for_bytes(offset, len)`
+pub fn bytes_from_words(words: &[U256], offset: u32, len: u32) -> Vec<u8> {
+    assert_eq!(words.len(), words_range_for_bytes(offset, len).len());
+    if len == 0 {
+        return vec![];
+    }
+    let mut buffer = vec![0u8; words.len() * 32];
+    for (word, dst) in words.iter().zip(buffer.chunks_mut(32)) {
+        word.to_big_endian(dst);
+    }
+    let start = (offset % 32) as usize;
+
+    buffer[start..(start + len as usize)].to_vec()
+}
+
+/// Places bytes starting at the byte `offset` into words that were taken from
+/// `words_range_for_bytes(offset, bytes.len())`, bytes outside of the range are kept
+pub fn write_bytes_into_words(words: &mut [U256], offset: u32, bytes: &[u8]) {
+    let len = u32::try_from(bytes.len()).expect("too many bytes to write");
+    assert_eq!(words.len(), words_range_for_bytes(offset, len).len());
+    if len == 0 {
+        return;
+    }
+    let mut buffer = vec![0u8; words.len() * 32];
+    for (word, dst) in words.iter().zip(buffer.chunks_mut(32)) {
+        word.to_big_endian(dst);
+    }
+    let start = (offset % 32) as usize;
+    buffer[start..(start + bytes.len())].copy_from_slice(bytes);
+    for (word, src) in words.iter_mut().zip(buffer.chunks(32)) {
+        *word = U256::from_big_endian(src);
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct GenericNoopTracer<M: Memory> {
     _marker: std::marker::PhantomData<M>,
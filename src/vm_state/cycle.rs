@@ -380,6 +380,11 @@ impl<
             && src0.is_pointer
             && !is_kernel_mode
         {
+            self.report_erased_fat_pointer(
+                after_masking_decoded.inner.variant.opcode,
+                0,
+                src0.value,
+            );
             erase_fat_pointer_metadata(&mut src0.value);
             src0.is_pointer = false;
         }
@@ -391,6 +396,11 @@ impl<
             && src1.is_pointer
             && !is_kernel_mode
         {
+            self.report_erased_fat_pointer(
+                after_masking_decoded.inner.variant.opcode,
+                1,
+                src1.value,
+            );
             erase_fat_pointer_metadata(&mut src1.value);
             src1.is_pointer = false;
         }
@@ -409,6 +419,7 @@ impl<
             self.increment_timestamp_after_cycle();
         }
         self.local_state.monotonic_cycle_counter += 1;
+        self.watchdog.count_cycle();
 
         self.witness_tracer.end_execution_cycle(&self.local_state);
 
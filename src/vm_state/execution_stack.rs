@@ -23,7 +23,11 @@ pub struct CallStackEntry<const N: usize = 8, E: VmEncodingMode<N> = EncodingMod
     pub aux_heap_bound: u32,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(bound(
+    serialize = "CallStackEntry<N, E>: serde::Serialize",
+    deserialize = "CallStackEntry<N, E>: serde::de::DeserializeOwned"
+))]
 pub struct Callstack<const N: usize = 8, E: VmEncodingMode<N> = EncodingModeProduction> {
     pub current: CallStackEntry<N, E>,
     pub inner: Vec<CallStackEntry<N, E>>,
@@ -1,11 +1,12 @@
 use crate::opcodes::DecodedOpcode;
+use crate::witness_trace::{CallstackTransition, FatPointerEvent, PointerEventLocation};
 
 use super::*;
 
 use zk_evm_abstractions::aux::{MemoryKey, MemoryLocation};
 use zk_evm_abstractions::queries::{DecommittmentQuery, LogQuery, MemoryQuery};
 use zk_evm_abstractions::vm::RefundType;
-use zkevm_opcode_defs::UNMAPPED_PAGE;
+use zkevm_opcode_defs::{Opcode, UNMAPPED_PAGE};
 
 pub fn read_code<
     const N: usize,
@@ -50,6 +51,34 @@ impl<
         E: VmEncodingMode<N>,
     > VmState<S, M, EV, PP, DP, WT, N, E>
 {
+    /// Location of the current opcode for fat pointer events, must be taken before the opcode
+    /// updates the pc
+    pub(crate) fn pointer_event_location(&self) -> PointerEventLocation {
+        let current = self.local_state.callstack.get_current_stack();
+
+        PointerEventLocation {
+            base_memory_page: current.base_memory_page.0,
+            code_address: current.code_address,
+            pc: current.pc.as_u64() as u32,
+        }
+    }
+
+    pub(crate) fn report_erased_fat_pointer(
+        &mut self,
+        opcode: Opcode,
+        operand_idx: usize,
+        value: U256,
+    ) {
+        let event = FatPointerEvent::Erased {
+            location: self.pointer_event_location(),
+            opcode,
+            operand_idx,
+            pointer: FatPointer::from_u256(value),
+        };
+        self.witness_tracer
+            .add_fat_pointer_event(self.local_state.monotonic_cycle_counter, &event);
+    }
+
     pub fn read_memory(&mut self, monotonic_cycle_counter: u32, key: MemoryKey) -> MemoryQuery {
         let MemoryKey {
             location,
@@ -239,6 +268,13 @@ impl<
             previous_context,
             &context_entry,
         );
+        self.witness_tracer.add_callstack_transition(
+            monotonic_cycle_counter,
+            &CallstackTransition::Push {
+                caller: *previous_context,
+                callee: context_entry,
+            },
+        );
         #[allow(dropping_references)]
         drop(previous_context);
 
@@ -0,0 +1,141 @@
+use std::time::{Duration, Instant};
+
+use super::*;
+use crate::errors::ExecutionLimitExceeded;
+use zkevm_opcode_defs::system_params::VM_MAX_STACK_DEPTH;
+
+// Limits on top of ergs, so a buggy or malicious bootloader can not run forever. They are
+// checked between cycles, so the VM stops in a consistent state that can be inspected or
+// snapshotted, and can continue after the limits are raised.
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExecutionLimits {
+    pub max_cycles: Option<u64>,
+    pub max_wall_time: Option<Duration>,
+    // budget on `memory_page_counter`, that only grows as every far call reserves pages for
+    // the new frame. So it bounds the number of far calls rather than pages that are alive,
+    // see `MemoryStatistics` for the latter
+    pub max_reserved_memory_pages: Option<u32>,
+    // near calls are counted too. Can not exceed `VM_MAX_STACK_DEPTH`
+    pub max_callstack_depth: Option<usize>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExecutionStopReason {
+    Finished,
+    LimitExceeded(ExecutionLimitExceeded),
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ExecutionWatchdog {
+    limits: ExecutionLimits,
+    cycles_executed: u64,
+    // wall time is measured from the first check
+    started_at: Option<Instant>,
+}
+
+impl ExecutionWatchdog {
+    pub fn new(limits: ExecutionLimits) -> Self {
+        if let Some(depth) = limits.max_callstack_depth {
+            assert!(
+                depth <= VM_MAX_STACK_DEPTH as usize,
+                "callstack depth limit {} is above VM_MAX_STACK_DEPTH",
+                depth
+            );
+        }
+
+        Self {
+            limits,
+            cycles_executed: 0,
+            started_at: None,
+        }
+    }
+
+    pub fn limits(&self) -> &ExecutionLimits {
+        &self.limits
+    }
+
+    pub fn cycles_executed(&self) -> u64 {
+        self.cycles_executed
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started_at
+            .map(|el| el.elapsed())
+            .unwrap_or(Duration::ZERO)
+    }
+
+    pub(crate) fn count_cycle(&mut self) {
+        self.cycles_executed += 1;
+    }
+
+    pub fn check<const N: usize, E: VmEncodingMode<N>>(
+        &mut self,
+        local_state: &VmLocalState<N, E>,
+    ) -> Result<(), ExecutionLimitExceeded> {
+        let started_at = *self.started_at.get_or_insert_with(Instant::now);
+
+        if let Some(limit) = self.limits.max_cycles {
+            if self.cycles_executed >= limit {
+                return Err(ExecutionLimitExceeded::Cycles { limit });
+            }
+        }
+        if let Some(limit) = self.limits.max_wall_time {
+            let elapsed = started_at.elapsed();
+            if elapsed >= limit {
+                return Err(ExecutionLimitExceeded::WallTime { limit, elapsed });
+            }
+        }
+        if let Some(limit) = self.limits.max_reserved_memory_pages {
+            let reserved = local_state.memory_page_counter;
+            if reserved > limit {
+                return Err(ExecutionLimitExceeded::MemoryPages { limit, reserved });
+            }
+        }
+        if let Some(limit) = self.limits.max_callstack_depth {
+            let depth = local_state.callstack.depth();
+            if depth > limit {
+                return Err(ExecutionLimitExceeded::CallstackDepth { limit, depth });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<
+        S: zk_evm_abstractions::vm::Storage,
+        M: zk_evm_abstractions::vm::Memory,
+        EV: zk_evm_abstractions::vm::EventSink,
+        PP: zk_evm_abstractions::vm::PrecompilesProcessor,
+        DP: zk_evm_abstractions::vm::DecommittmentProcessor,
+        WT: crate::witness_trace::VmWitnessTracer<N, E>,
+        const N: usize,
+        E: VmEncodingMode<N>,
+    > VmState<S, M, EV, PP, DP, WT, N, E>
+{
+    /// Replaces the limits and restarts counting of cycles and wall time
+    pub fn set_execution_limits(&mut self, limits: ExecutionLimits) {
+        self.watchdog = ExecutionWatchdog::new(limits);
+    }
+
+    pub fn check_execution_limits(&mut self) -> Result<(), ExecutionLimitExceeded> {
+        self.watchdog.check(&self.local_state)
+    }
+
+    /// Runs until the execution ends or a limit is exceeded. Without limits it's only
+    /// bounded by ergs
+    pub fn run<DT: crate::tracing::Tracer<N, E, SupportedMemory = M>>(
+        &mut self,
+        tracer: &mut DT,
+    ) -> anyhow::Result<ExecutionStopReason> {
+        while self.execution_has_ended() == false {
+            if let Err(reason) = self.check_execution_limits() {
+                return Ok(ExecutionStopReason::LimitExceeded(reason));
+            }
+            self.cycle(tracer)?;
+        }
+
+        Ok(ExecutionStopReason::Finished)
+    }
+}
@@ -11,11 +11,13 @@ use zkevm_opcode_defs::decoding::AllowedPcOrImm;
 pub mod cycle;
 pub mod execution_stack;
 pub mod helpers;
+pub mod limits;
 pub mod mem_ops;
 
 pub use self::cycle::*;
 pub use self::execution_stack::*;
 pub use self::helpers::*;
+pub use self::limits::*;
 pub use self::mem_ops::*;
 
 pub const SUPPORTED_ISA_VERSION: ISAVersion = ISAVersion(1);
@@ -28,7 +30,9 @@ const _: () = if SUPPORTED_ISA_VERSION.0 != zkevm_opcode_defs::DEFAULT_ISA_VERSI
 
 use zkevm_opcode_defs::{STARTING_BASE_PAGE, STARTING_TIMESTAMP};
 
-#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(
+    Default, Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
 pub struct PrimitiveValue {
     pub value: U256,
     pub is_pointer: bool,
@@ -50,7 +54,11 @@ impl PrimitiveValue {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(bound(
+    serialize = "Callstack<N, E>: serde::Serialize, E::PcOrImm: serde::Serialize",
+    deserialize = "Callstack<N, E>: serde::de::DeserializeOwned, E::PcOrImm: serde::de::DeserializeOwned"
+))]
 pub struct VmLocalState<const N: usize = 8, E: VmEncodingMode<N> = EncodingModeProduction> {
     pub previous_code_word: U256,
     pub previous_code_memory_page: MemoryPage,
@@ -172,6 +180,7 @@ pub struct VmState<
     pub precompiles_processor: PP,
     pub decommittment_processor: DP,
     pub witness_tracer: WT,
+    pub watchdog: ExecutionWatchdog,
 }
 
 impl<
@@ -203,6 +212,7 @@ impl<
             decommittment_processor,
             witness_tracer,
             block_properties,
+            watchdog: ExecutionWatchdog::default(),
         }
     }
     pub fn reset_flags(&mut self) {
@@ -0,0 +1,85 @@
+use super::*;
+
+use super::full::{DecommittmentWitness, FullWitness};
+use zkevm_opcode_defs::decoding::EncodingModeProduction;
+
+// Main VM circuit instance covers a fixed number of cycles. Chunk contains everything that is
+// needed to prepare such an instance: the local state before its first cycle and the queries
+// produced by its cycles. Queries produced while setting up the VM (before the first recorded
+// cycle) belong to the first chunk.
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(bound(
+    serialize = "VmLocalState<N, E>: serde::Serialize",
+    deserialize = "VmLocalState<N, E>: serde::de::DeserializeOwned"
+))]
+pub struct WitnessChunk<const N: usize = 8, E: VmEncodingMode<N> = EncodingModeProduction> {
+    pub initial_state: VmLocalState<N, E>,
+    pub num_cycles: usize,
+    pub memory_queries: Vec<(u32, MemoryQuery)>,
+    pub log_queries: Vec<(u32, LogQuery)>,
+    pub decommittment_queries: Vec<DecommittmentWitness>,
+}
+
+// splits a queue ordered by cycle into parts that start at the given cycles
+fn split_by_starting_cycles<T: Clone>(
+    queue: &[T],
+    starting_cycles: &[u32],
+    cycle_of: impl Fn(&T) -> u32,
+) -> Vec<Vec<T>> {
+    let mut result = Vec::with_capacity(starting_cycles.len());
+    let mut rest = queue;
+    for next_start in starting_cycles.iter().skip(1) {
+        let len = rest.partition_point(|el| cycle_of(el) < *next_start);
+        let (current, next) = rest.split_at(len);
+        result.push(current.to_vec());
+        rest = next;
+    }
+    result.push(rest.to_vec());
+
+    result
+}
+
+impl<const N: usize, E: VmEncodingMode<N>> FullWitness<N, E> {
+    /// Splits recorded witness into chunks of `cycles_per_chunk` cycles, the last one may
+    /// be shorter
+    pub fn split_into_chunks(&self, cycles_per_chunk: usize) -> Vec<WitnessChunk<N, E>> {
+        assert!(
+            cycles_per_chunk > 0,
+            "chunk must contain at least one cycle"
+        );
+        if self.local_states.is_empty() {
+            return vec![];
+        }
+
+        let starting_states: Vec<_> = self.local_states.chunks(cycles_per_chunk).collect();
+        let starting_cycles: Vec<u32> = starting_states
+            .iter()
+            .map(|el| el[0].monotonic_cycle_counter)
+            .collect();
+
+        let memory_queries =
+            split_by_starting_cycles(&self.memory_queue, &starting_cycles, |el| el.0);
+        let log_queries = split_by_starting_cycles(&self.log_queue, &starting_cycles, |el| el.0);
+        let decommittment_queries =
+            split_by_starting_cycles(&self.decommittment_queue, &starting_cycles, |el| {
+                el.monotonic_cycle_counter
+            });
+
+        starting_states
+            .into_iter()
+            .zip(memory_queries)
+            .zip(log_queries)
+            .zip(decommittment_queries)
+            .map(
+                |(((states, memory_queries), log_queries), decommittment_queries)| WitnessChunk {
+                    initial_state: states[0].clone(),
+                    num_cycles: states.len(),
+                    memory_queries,
+                    log_queries,
+                    decommittment_queries,
+                },
+            )
+            .collect()
+    }
+}
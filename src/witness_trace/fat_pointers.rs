@@ -0,0 +1,64 @@
+use super::*;
+
+// Collects fat pointer events, so one can follow a pointer from the frame and heap it was
+// created from, through all the frames it was forwarded to and the narrowings on the way,
+// up to the place where its metadata was erased.
+
+#[derive(Clone, Debug, Default)]
+pub struct FatPointerLineageTracer {
+    pub events: Vec<(u32, FatPointerEvent)>,
+}
+
+impl FatPointerLineageTracer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Events of pointers into the page, in execution order
+    pub fn events_for_page(&self, page: u32) -> Vec<(u32, FatPointerEvent)> {
+        self.events
+            .iter()
+            .filter(|(_, el)| el.pointer().memory_page == page)
+            .copied()
+            .collect()
+    }
+
+    /// Event that has passed the page to another frame for the first time. Pages that are
+    /// never passed by far call or ret (e.g. bootloader's calldata) have no origin
+    pub fn origin(&self, page: u32) -> Option<(u32, FatPointerEvent)> {
+        self.events
+            .iter()
+            .find(|(_, el)| {
+                matches!(el, FatPointerEvent::Created { .. }) && el.pointer().memory_page == page
+            })
+            .copied()
+    }
+
+    /// Frames that have received pointers into the page, in the order they got them
+    pub fn receivers(&self, page: u32) -> Vec<u32> {
+        let mut result = vec![];
+        for (_, event) in self.events.iter() {
+            let receiver_base_page = match event {
+                FatPointerEvent::Created {
+                    receiver_base_page, ..
+                }
+                | FatPointerEvent::Forwarded {
+                    receiver_base_page, ..
+                } => *receiver_base_page,
+                _ => continue,
+            };
+            if event.pointer().memory_page == page && result.contains(&receiver_base_page) == false
+            {
+                result.push(receiver_base_page);
+            }
+        }
+
+        result
+    }
+}
+
+impl<const N: usize, E: VmEncodingMode<N>> VmWitnessTracer<N, E> for FatPointerLineageTracer {
+    fn add_fat_pointer_event(&mut self, monotonic_cycle_counter: u32, event: &FatPointerEvent) {
+        self.events.push((monotonic_cycle_counter, *event));
+    }
+}
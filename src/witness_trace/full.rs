@@ -0,0 +1,253 @@
+use super::*;
+
+use super::memory_permutation::sort_memory_queries;
+use crate::reference_impls::event_sink::ApplicationData;
+use zkevm_opcode_defs::decoding::EncodingModeProduction;
+use zkevm_opcode_defs::system_params::{
+    EVENT_AUX_BYTE, L1_MESSAGE_AUX_BYTE, PRECOMPILE_AUX_BYTE, STORAGE_AUX_BYTE,
+};
+
+// Reference witness tracer that keeps everything the circuits need to prove the execution.
+// Every entry is tagged with the monotonic cycle counter at which it was produced.
+//
+// Log queries are demultiplexed by aux byte into separate queues. Every write-like query
+// (`rw_flag == true`) gets a rollback counterpart in the frame that produced it: if the frame
+// panics then the rollbacks are appended to the forward queue in reverse order (and are tagged
+// with the cycle of the return), otherwise they are carried to the parent frame, same as the
+// reference storage and event sink do.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct StorageRefund {
+    pub monotonic_cycle_counter: u32,
+    // partial query as it was before the storage has executed it
+    pub query: LogQuery,
+    pub pubdata_refund: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DecommittmentWitness {
+    pub monotonic_cycle_counter: u32,
+    pub query: DecommittmentQuery,
+    pub memory_witness: Vec<U256>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PrecompileCallWitness {
+    pub monotonic_cycle_counter: u32,
+    pub call_params: LogQuery,
+    pub memory_reads: Vec<MemoryQuery>,
+    pub memory_writes: Vec<MemoryQuery>,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(bound(
+    serialize = "VmLocalState<N, E>: serde::Serialize, CallstackTransition<N, E>: serde::Serialize",
+    deserialize = "VmLocalState<N, E>: serde::de::DeserializeOwned, CallstackTransition<N, E>: serde::de::DeserializeOwned"
+))]
+pub struct FullWitness<const N: usize = 8, E: VmEncodingMode<N> = EncodingModeProduction> {
+    // state at the beginning of every cycle
+    pub local_states: Vec<VmLocalState<N, E>>,
+    // in execution order, as the main VM circuit produces it
+    pub memory_queue: Vec<(u32, MemoryQuery)>,
+    pub log_queue: Vec<(u32, LogQuery)>,
+    // log queue demultiplexed by aux byte
+    pub storage_queue: Vec<(u32, LogQuery)>,
+    pub events_queue: Vec<(u32, LogQuery)>,
+    pub l1_messages_queue: Vec<(u32, LogQuery)>,
+    pub precompiles_queue: Vec<(u32, LogQuery)>,
+    pub storage_refunds: Vec<StorageRefund>,
+    pub decommittment_queue: Vec<DecommittmentWitness>,
+    pub precompile_calls: Vec<PrecompileCallWitness>,
+    pub callstack_transitions: Vec<(u32, CallstackTransition<N, E>)>,
+}
+
+impl<const N: usize, E: VmEncodingMode<N>> FullWitness<N, E> {
+    /// Memory queries in the order of the RAM permutation: by page, index and timestamp
+    pub fn sorted_memory_queries(&self) -> Vec<MemoryQuery> {
+        let queries: Vec<_> = self.memory_queue.iter().map(|(_, el)| *el).collect();
+
+        sort_memory_queries(&queries)
+    }
+
+    /// Decommittment queries in the order of the code decommitter: by hash and timestamp
+    pub fn sorted_decommittment_queries(&self) -> Vec<DecommittmentQuery> {
+        let mut result: Vec<_> = self.decommittment_queue.iter().map(|el| el.query).collect();
+        result.sort_by_key(|el| (el.hash, el.timestamp.0));
+
+        result
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FullWitnessTracer<const N: usize = 8, E: VmEncodingMode<N> = EncodingModeProduction> {
+    pub witness: FullWitness<N, E>,
+    // round witnesses are opaque, so we keep them aside from the serializable part
+    pub precompile_round_witnesses: Vec<(u32, PrecompileCyclesWitness)>,
+    log_frames: Vec<ApplicationData<(u32, LogQuery)>>,
+}
+
+impl<const N: usize, E: VmEncodingMode<N>> FullWitnessTracer<N, E> {
+    pub fn new() -> Self {
+        Self {
+            witness: FullWitness {
+                local_states: vec![],
+                memory_queue: vec![],
+                log_queue: vec![],
+                storage_queue: vec![],
+                events_queue: vec![],
+                l1_messages_queue: vec![],
+                precompiles_queue: vec![],
+                storage_refunds: vec![],
+                decommittment_queue: vec![],
+                precompile_calls: vec![],
+                callstack_transitions: vec![],
+            },
+            precompile_round_witnesses: vec![],
+            // we add single frame that will serve as a last one
+            log_frames: vec![ApplicationData::empty()],
+        }
+    }
+
+    /// Finalizes the log queues. Frames that are still running are treated as if they have
+    /// finished successfully, rollbacks that were never applied are dropped
+    pub fn into_witness(mut self) -> FullWitness<N, E> {
+        while self.log_frames.len() > 1 {
+            self.merge_current_log_frame(0, false);
+        }
+        let ApplicationData {
+            forward,
+            rollbacks: _,
+        } = self.log_frames.pop().unwrap();
+        for (cycle, query) in forward.iter().copied() {
+            let queue = match query.aux_byte {
+                STORAGE_AUX_BYTE => &mut self.witness.storage_queue,
+                EVENT_AUX_BYTE => &mut self.witness.events_queue,
+                L1_MESSAGE_AUX_BYTE => &mut self.witness.l1_messages_queue,
+                PRECOMPILE_AUX_BYTE => &mut self.witness.precompiles_queue,
+                a => panic!("unknown aux byte {} in log query", a),
+            };
+            queue.push((cycle, query));
+        }
+        self.witness.log_queue = forward;
+
+        self.witness
+    }
+
+    fn merge_current_log_frame(&mut self, monotonic_cycle_counter: u32, panicked: bool) {
+        let ApplicationData { forward, rollbacks } = self
+            .log_frames
+            .pop()
+            .expect("frame must be started before finishing");
+        let parent_data = self.log_frames.last_mut().expect("parent_frame_must_exist");
+        parent_data.forward.extend(forward);
+        if panicked {
+            parent_data.forward.extend(
+                rollbacks
+                    .into_iter()
+                    .rev()
+                    .map(|(_, query)| (monotonic_cycle_counter, query)),
+            );
+        } else {
+            parent_data.rollbacks.extend(rollbacks);
+        }
+    }
+}
+
+impl<const N: usize, E: VmEncodingMode<N>> Default for FullWitnessTracer<N, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize, E: VmEncodingMode<N>> VmWitnessTracer<N, E> for FullWitnessTracer<N, E> {
+    fn start_new_execution_cycle(&mut self, current_state: &VmLocalState<N, E>) {
+        self.witness.local_states.push(current_state.clone());
+    }
+
+    fn add_memory_query(&mut self, monotonic_cycle_counter: u32, memory_query: MemoryQuery) {
+        self.witness
+            .memory_queue
+            .push((monotonic_cycle_counter, memory_query));
+    }
+
+    fn record_refund_for_query(
+        &mut self,
+        monotonic_cycle_counter: u32,
+        log_query: LogQuery,
+        refund: RefundType,
+    ) {
+        self.witness.storage_refunds.push(StorageRefund {
+            monotonic_cycle_counter,
+            query: log_query,
+            pubdata_refund: refund.pubdata_refund(),
+        });
+    }
+
+    fn add_log_query(&mut self, monotonic_cycle_counter: u32, log_query: LogQuery) {
+        let frame_data = self.log_frames.last_mut().expect("frame must be started");
+        frame_data
+            .forward
+            .push((monotonic_cycle_counter, log_query));
+        if log_query.rw_flag {
+            let mut rollback = log_query;
+            rollback.rollback = true;
+            frame_data
+                .rollbacks
+                .push((monotonic_cycle_counter, rollback));
+        }
+    }
+
+    fn add_decommittment(
+        &mut self,
+        monotonic_cycle_counter: u32,
+        decommittment_query: DecommittmentQuery,
+        mem_witness: Vec<U256>,
+    ) {
+        self.witness.decommittment_queue.push(DecommittmentWitness {
+            monotonic_cycle_counter,
+            query: decommittment_query,
+            memory_witness: mem_witness,
+        });
+    }
+
+    fn add_precompile_call_result(
+        &mut self,
+        monotonic_cycle_counter: u32,
+        call_params: LogQuery,
+        mem_witness_in: Vec<MemoryQuery>,
+        memory_witness_out: Vec<MemoryQuery>,
+        round_witness: PrecompileCyclesWitness,
+    ) {
+        self.witness.precompile_calls.push(PrecompileCallWitness {
+            monotonic_cycle_counter,
+            call_params,
+            memory_reads: mem_witness_in,
+            memory_writes: memory_witness_out,
+        });
+        self.precompile_round_witnesses
+            .push((monotonic_cycle_counter, round_witness));
+    }
+
+    fn start_new_execution_context(
+        &mut self,
+        _monotonic_cycle_counter: u32,
+        _previous_context: &CallStackEntry<N, E>,
+        _new_context: &CallStackEntry<N, E>,
+    ) {
+        self.log_frames.push(ApplicationData::empty());
+    }
+
+    fn finish_execution_context(&mut self, monotonic_cycle_counter: u32, panicked: bool) {
+        self.merge_current_log_frame(monotonic_cycle_counter, panicked);
+    }
+
+    fn add_callstack_transition(
+        &mut self,
+        monotonic_cycle_counter: u32,
+        transition: &CallstackTransition<N, E>,
+    ) {
+        self.witness
+            .callstack_transitions
+            .push((monotonic_cycle_counter, transition.clone()));
+    }
+}
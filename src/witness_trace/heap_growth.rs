@@ -0,0 +1,66 @@
+use super::*;
+
+use std::collections::HashMap;
+
+// Collects heap growth events, so one can see which code pays for the memory growth the most.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HeapGrowthHotspot {
+    pub code_address: Address,
+    pub pc: u32,
+    pub num_events: usize,
+    pub bytes_grown: u64,
+    pub ergs_charged: u64,
+    pub num_out_of_range_penalties: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct HeapGrowthTracer {
+    pub events: Vec<(u32, HeapGrowthEvent)>,
+}
+
+impl HeapGrowthTracer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Events grouped by the opcode that has caused them, most expensive first
+    pub fn hotspots(&self) -> Vec<HeapGrowthHotspot> {
+        let mut hotspots = HashMap::<(Address, u32), HeapGrowthHotspot>::new();
+        for (_, event) in self.events.iter() {
+            let hotspot =
+                hotspots
+                    .entry((event.code_address, event.pc))
+                    .or_insert(HeapGrowthHotspot {
+                        code_address: event.code_address,
+                        pc: event.pc,
+                        num_events: 0,
+                        bytes_grown: 0,
+                        ergs_charged: 0,
+                        num_out_of_range_penalties: 0,
+                    });
+            hotspot.num_events += 1;
+            hotspot.bytes_grown += event.new_bound.wrapping_sub(event.old_bound) as u64;
+            hotspot.ergs_charged += event.ergs_charged as u64;
+            if event.out_of_range_penalty {
+                hotspot.num_out_of_range_penalties += 1;
+            }
+        }
+
+        let mut result: Vec<_> = hotspots.into_values().collect();
+        result.sort_by(|a, b| {
+            b.ergs_charged
+                .cmp(&a.ergs_charged)
+                .then(a.code_address.cmp(&b.code_address))
+                .then(a.pc.cmp(&b.pc))
+        });
+
+        result
+    }
+}
+
+impl<const N: usize, E: VmEncodingMode<N>> VmWitnessTracer<N, E> for HeapGrowthTracer {
+    fn add_heap_growth(&mut self, monotonic_cycle_counter: u32, event: &HeapGrowthEvent) {
+        self.events.push((monotonic_cycle_counter, *event));
+    }
+}
@@ -0,0 +1,109 @@
+use super::*;
+
+use super::full::FullWitness;
+use crate::errors::MemoryWitnessError;
+use zk_evm_abstractions::aux::{MemoryIndex, MemoryLocation};
+use zk_evm_abstractions::vm::MemoryType;
+
+// Out of circuit mirror of the RAM permutation argument: memory starts zeroed out, and after
+// sorting by (page, index, timestamp) every read must return what the last write to the same
+// cell has written, including the pointer bit. Pages that are filled before the execution
+// starts (like the bootloader code) are not part of the argument and must be filtered out
+
+/// Sorts queries as the RAM permutation circuit does. Sorting is stable, so queries with
+/// the same key keep their execution order
+pub fn sort_memory_queries(queries: &[MemoryQuery]) -> Vec<MemoryQuery> {
+    let mut result = queries.to_vec();
+    result.sort_by_key(|el| (el.location.page.0, el.location.index.0, el.timestamp.0));
+
+    result
+}
+
+/// Checks queries that are already sorted by `sort_memory_queries`
+pub fn check_sorted_memory_queries(sorted: &[MemoryQuery]) -> Result<(), MemoryWitnessError> {
+    // (page, index) of the cell, timestamp of the last write and the written value
+    let mut last_write: Option<((u32, u32), Option<u32>, U256, bool)> = None;
+    for query in sorted.iter() {
+        let cell = (query.location.page.0, query.location.index.0);
+        let (written_at, value, value_is_pointer) = match last_write {
+            Some((last_cell, written_at, value, value_is_pointer)) if last_cell == cell => {
+                (written_at, value, value_is_pointer)
+            }
+            _ => (None, U256::zero(), false),
+        };
+
+        if query.rw_flag {
+            last_write = Some((
+                cell,
+                Some(query.timestamp.0),
+                query.value,
+                query.value_is_pointer,
+            ));
+            continue;
+        }
+
+        if query.value != value {
+            return Err(MemoryWitnessError::ReadValueMismatch {
+                page: cell.0,
+                index: cell.1,
+                timestamp: query.timestamp.0,
+                written_at,
+                expected: value,
+                actual: query.value,
+            });
+        }
+        if query.value_is_pointer != value_is_pointer {
+            return Err(MemoryWitnessError::PointerFlagMismatch {
+                page: cell.0,
+                index: cell.1,
+                timestamp: query.timestamp.0,
+                written_at,
+                expected: value_is_pointer,
+                actual: query.value_is_pointer,
+            });
+        }
+        last_write = Some((cell, written_at, value, value_is_pointer));
+    }
+
+    Ok(())
+}
+
+/// Sorts queries and checks them, returns the sorted sequence
+pub fn check_memory_queries(
+    queries: &[MemoryQuery],
+) -> Result<Vec<MemoryQuery>, MemoryWitnessError> {
+    let sorted = sort_memory_queries(queries);
+    check_sorted_memory_queries(&sorted)?;
+
+    Ok(sorted)
+}
+
+impl<const N: usize, E: VmEncodingMode<N>> FullWitness<N, E> {
+    /// All queries that take part in the RAM permutation: ones made by the VM itself,
+    /// writes of decommitted code and reads and writes of precompiles
+    pub fn memory_permutation_queries(&self) -> Vec<MemoryQuery> {
+        let mut result: Vec<_> = self.memory_queue.iter().map(|(_, el)| *el).collect();
+        for decommittment in self.decommittment_queue.iter() {
+            let query = decommittment.query;
+            for (idx, word) in decommittment.memory_witness.iter().enumerate() {
+                result.push(MemoryQuery {
+                    timestamp: query.timestamp,
+                    location: MemoryLocation {
+                        memory_type: MemoryType::Code,
+                        page: query.memory_page,
+                        index: MemoryIndex(idx as u32),
+                    },
+                    value: *word,
+                    value_is_pointer: false,
+                    rw_flag: true,
+                });
+            }
+        }
+        for call in self.precompile_calls.iter() {
+            result.extend(call.memory_reads.iter().copied());
+            result.extend(call.memory_writes.iter().copied());
+        }
+
+        result
+    }
+}
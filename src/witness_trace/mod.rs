@@ -2,11 +2,168 @@ use zk_evm_abstractions::{
     queries::{DecommittmentQuery, LogQuery, MemoryQuery},
     vm::{PrecompileCyclesWitness, RefundType},
 };
-use zkevm_opcode_defs::decoding::VmEncodingMode;
+use zkevm_opcode_defs::decoding::{EncodingModeProduction, VmEncodingMode};
+use zkevm_opcode_defs::{FatPointer, Opcode, PtrOpcode};
 
 use super::*;
 use crate::vm_state::{CallStackEntry, VmLocalState};
 
+pub mod chunks;
+pub mod fat_pointers;
+pub mod full;
+pub mod heap_growth;
+pub mod memory_permutation;
+pub mod precompile_calls;
+pub mod storage_deduplication;
+
+// Callstack change as the callstack sponge sees it. Entries are taken after the opcode has
+// fully applied its changes, so `caller` already has the return pc and ergs left after
+// passing, and `restored` already has returned ergs, heap bounds grown by the near call
+// and the pc to continue from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(bound(
+    serialize = "CallStackEntry<N, E>: serde::Serialize",
+    deserialize = "CallStackEntry<N, E>: serde::de::DeserializeOwned"
+))]
+pub enum CallstackTransition<const N: usize = 8, E: VmEncodingMode<N> = EncodingModeProduction> {
+    Push {
+        caller: CallStackEntry<N, E>,
+        // `is_local_frame` distinguishes near calls from far calls
+        callee: CallStackEntry<N, E>,
+    },
+    Pop {
+        popped: CallStackEntry<N, E>,
+        restored: CallStackEntry<N, E>,
+        panicked: bool,
+        ergs_returned: u32,
+        // pc of `restored` is the exception handler of `popped`
+        jumped_to_exception_handler: bool,
+    },
+}
+
+impl<const N: usize, E: VmEncodingMode<N>> CallstackTransition<N, E> {
+    pub fn is_local_frame(&self) -> bool {
+        match self {
+            Self::Push { callee, .. } => callee.is_local_frame,
+            Self::Pop { popped, .. } => popped.is_local_frame,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum HeapGrowthSource {
+    Uma,
+    // heap slice is passed as calldata
+    FarCall,
+    // heap slice is passed as returndata, the grown bound is not saved as the frame ends
+    Ret,
+}
+
+// Growth of the heap or aux heap bound of the current frame. It's reported if the bound has
+// grown or if the out of range penalty was applied. The frame may not have enough ergs to pay
+// for the growth, in this case the opcode panics
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct HeapGrowthEvent {
+    pub source: HeapGrowthSource,
+    pub base_memory_page: u32,
+    pub code_address: Address,
+    // pc of the opcode that grows the heap
+    pub pc: u32,
+    pub is_aux_heap: bool,
+    pub old_bound: u32,
+    pub new_bound: u32,
+    pub ergs_charged: u32,
+    // `DEREF_BEYOND_HEAP_RANGE` was triggered and the frame is charged for the whole range
+    pub out_of_range_penalty: bool,
+}
+
+// Precompile call with the ABI as the precompile gets it. It's reported before the call, so
+// `add_precompile_call_result` for it (if the processor reports witness) comes right after
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PrecompileCallEvent {
+    pub address: Address,
+    // precompiles interpret offsets and lengths either in bytes or in words
+    pub input_memory_offset: u32,
+    pub input_memory_length: u32,
+    pub output_memory_offset: u32,
+    pub output_memory_length: u32,
+    // page 0 in the ABI is replaced by the heap of the caller
+    pub memory_page_to_read: u32,
+    pub memory_page_to_write: u32,
+    pub precompile_interpreted_data: u64,
+    // taken from `src1` of the opcode
+    pub extra_ergs_cost: u32,
+}
+
+// Frame and opcode that have produced a fat pointer event
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PointerEventLocation {
+    pub base_memory_page: u32,
+    pub code_address: Address,
+    pub pc: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PointerTransfer {
+    Calldata,
+    Returndata,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FatPointerEvent {
+    // slice of the frame's heap or aux heap is passed to another frame
+    Created {
+        location: PointerEventLocation,
+        transfer: PointerTransfer,
+        is_aux_heap: bool,
+        pointer: FatPointer,
+        receiver_base_page: u32,
+    },
+    // pointer is passed further, its offset is folded into the start
+    Forwarded {
+        location: PointerEventLocation,
+        transfer: PointerTransfer,
+        original: FatPointer,
+        forwarded: FatPointer,
+        receiver_base_page: u32,
+    },
+    // `ptr.add`, `ptr.sub`, `ptr.shrink` or `ptr.pack`
+    Narrowed {
+        location: PointerEventLocation,
+        opcode: PtrOpcode,
+        input: FatPointer,
+        output: FatPointer,
+    },
+    // pointer is passed to the opcode that can not take pointers in user mode, so the
+    // metadata is erased and only the offset is left
+    Erased {
+        location: PointerEventLocation,
+        opcode: Opcode,
+        operand_idx: usize,
+        pointer: FatPointer,
+    },
+}
+
+impl FatPointerEvent {
+    pub fn location(&self) -> &PointerEventLocation {
+        match self {
+            Self::Created { location, .. }
+            | Self::Forwarded { location, .. }
+            | Self::Narrowed { location, .. }
+            | Self::Erased { location, .. } => location,
+        }
+    }
+
+    /// Pointer as it is after the event
+    pub fn pointer(&self) -> FatPointer {
+        match self {
+            Self::Created { pointer, .. } | Self::Erased { pointer, .. } => *pointer,
+            Self::Forwarded { forwarded, .. } => *forwarded,
+            Self::Narrowed { output, .. } => *output,
+        }
+    }
+}
+
 #[allow(unused_variables)]
 pub trait VmWitnessTracer<const N: usize, E: VmEncodingMode<N>>: Clone + std::fmt::Debug {
     #[inline]
@@ -69,6 +226,23 @@ pub trait VmWitnessTracer<const N: usize, E: VmEncodingMode<N>>: Clone + std::fm
 
     #[inline]
     fn finish_execution_context(&mut self, monotonic_cycle_counter: u32, panicked: bool) {}
+
+    #[inline]
+    fn add_callstack_transition(
+        &mut self,
+        monotonic_cycle_counter: u32,
+        transition: &CallstackTransition<N, E>,
+    ) {
+    }
+
+    #[inline]
+    fn add_heap_growth(&mut self, monotonic_cycle_counter: u32, event: &HeapGrowthEvent) {}
+
+    #[inline]
+    fn add_precompile_call(&mut self, monotonic_cycle_counter: u32, event: &PrecompileCallEvent) {}
+
+    #[inline]
+    fn add_fat_pointer_event(&mut self, monotonic_cycle_counter: u32, event: &FatPointerEvent) {}
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -0,0 +1,79 @@
+use super::*;
+
+// Collects precompile calls together with the data they have read and written. Memory and
+// rounds are only known if the precompiles processor reports the witness, e.g.
+// `DefaultPrecompilesProcessor<true>`, otherwise just the ABI is recorded.
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrecompileCallRecord {
+    pub monotonic_cycle_counter: u32,
+    pub call: PrecompileCallEvent,
+    // big-endian bytes of the words read and written by the precompile, in access order
+    pub input: Vec<u8>,
+    pub output: Vec<u8>,
+    pub num_rounds: Option<usize>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PrecompileCallTracer {
+    pub calls: Vec<PrecompileCallRecord>,
+}
+
+impl PrecompileCallTracer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Calls to the precompile with the given address, in execution order
+    pub fn calls_to(&self, address: &Address) -> Vec<&PrecompileCallRecord> {
+        self.calls
+            .iter()
+            .filter(|el| &el.call.address == address)
+            .collect()
+    }
+}
+
+fn words_to_bytes(queries: &[MemoryQuery]) -> Vec<u8> {
+    let mut result = vec![0u8; queries.len() * 32];
+    for (query, dst) in queries.iter().zip(result.chunks_mut(32)) {
+        query.value.to_big_endian(dst);
+    }
+
+    result
+}
+
+impl<const N: usize, E: VmEncodingMode<N>> VmWitnessTracer<N, E> for PrecompileCallTracer {
+    fn add_precompile_call(&mut self, monotonic_cycle_counter: u32, event: &PrecompileCallEvent) {
+        self.calls.push(PrecompileCallRecord {
+            monotonic_cycle_counter,
+            call: *event,
+            input: vec![],
+            output: vec![],
+            num_rounds: None,
+        });
+    }
+
+    fn add_precompile_call_result(
+        &mut self,
+        monotonic_cycle_counter: u32,
+        call_params: LogQuery,
+        mem_witness_in: Vec<MemoryQuery>,
+        memory_witness_out: Vec<MemoryQuery>,
+        round_witness: PrecompileCyclesWitness,
+    ) {
+        let record = self
+            .calls
+            .last_mut()
+            .expect("precompile call must be reported before its result");
+        assert_eq!(record.monotonic_cycle_counter, monotonic_cycle_counter);
+        assert_eq!(record.call.address, call_params.address);
+
+        record.input = words_to_bytes(&mem_witness_in);
+        record.output = words_to_bytes(&memory_witness_out);
+        record.num_rounds = Some(match round_witness {
+            PrecompileCyclesWitness::Sha256(el) => el.len(),
+            PrecompileCyclesWitness::Keccak256(el) => el.len(),
+            PrecompileCyclesWitness::ECRecover(el) => el.len(),
+        });
+    }
+}
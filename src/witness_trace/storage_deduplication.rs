@@ -0,0 +1,174 @@
+use super::*;
+
+use crate::errors::StorageLogError;
+use std::collections::{HashMap, HashSet};
+
+// Out of circuit mirror of the storage deduplication. Storage queries come in the forward
+// order (as `FullWitness::storage_queue` or `InMemoryStorage::flatten_and_net_history` give
+// them), where rollbacks of panicked frames are placed right after the frame's queries. They
+// are replayed in this order to check that every query has read the actual value, then sorted
+// by (shard, address, key, timestamp) with rolled back writes and their rollbacks removed, and
+// collapsed into one entry per slot.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StorageSlotSummary {
+    pub shard_id: u8,
+    pub address: Address,
+    pub key: U256,
+    pub initial_value: U256,
+    pub final_value: U256,
+    // slot has at least one write that was not rolled back, even if it has written the
+    // initial value back
+    pub is_written: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeduplicatedStorageLog {
+    // rolled back writes and rollbacks are removed
+    pub sorted_queries: Vec<LogQuery>,
+    // one entry per touched slot, sorted by (shard, address, key)
+    pub slots: Vec<StorageSlotSummary>,
+    // written slots that did not exist before
+    pub initial_writes: Vec<StorageSlotSummary>,
+    pub repeated_writes: Vec<StorageSlotSummary>,
+}
+
+type SlotKey = (u8, Address, U256);
+
+fn slot_of(query: &LogQuery) -> SlotKey {
+    (query.shard_id, query.address, query.key)
+}
+
+/// Deduplicates storage queries. `is_known_slot` tells whether the slot already existed
+/// before the execution, so its writes are repeated ones
+pub fn deduplicate_storage_log(
+    queries: &[LogQuery],
+    is_known_slot: impl Fn(u8, &Address, &U256) -> bool,
+) -> Result<DeduplicatedStorageLog, StorageLogError> {
+    let mut initial_values = HashMap::<SlotKey, U256>::new();
+    let mut current_values = HashMap::<SlotKey, U256>::new();
+    let mut pending_writes = HashSet::<(SlotKey, u32)>::new();
+    let mut rolled_back_writes = HashSet::<(SlotKey, u32)>::new();
+
+    for query in queries.iter() {
+        let slot = slot_of(query);
+        let (shard_id, address, key) = slot;
+        let timestamp = query.timestamp.0;
+        initial_values.entry(slot).or_insert(query.read_value);
+        let current_value = current_values.entry(slot).or_insert(query.read_value);
+
+        if query.rollback {
+            if pending_writes.remove(&(slot, timestamp)) == false {
+                return Err(StorageLogError::RollbackWithoutWrite {
+                    shard_id,
+                    address,
+                    key,
+                    timestamp,
+                });
+            }
+            // rollbacks are applied in reverse order, so we must see the value it has written
+            if *current_value != query.written_value {
+                return Err(StorageLogError::ReadValueMismatch {
+                    shard_id,
+                    address,
+                    key,
+                    timestamp,
+                    expected: *current_value,
+                    actual: query.written_value,
+                });
+            }
+            *current_value = query.read_value;
+            rolled_back_writes.insert((slot, timestamp));
+            continue;
+        }
+
+        if *current_value != query.read_value {
+            return Err(StorageLogError::ReadValueMismatch {
+                shard_id,
+                address,
+                key,
+                timestamp,
+                expected: *current_value,
+                actual: query.read_value,
+            });
+        }
+        if query.rw_flag {
+            *current_value = query.written_value;
+            pending_writes.insert((slot, timestamp));
+        }
+    }
+
+    let mut sorted_queries: Vec<_> = queries
+        .iter()
+        .filter(|el| {
+            el.rollback == false
+                && (el.rw_flag == false
+                    || rolled_back_writes.contains(&(slot_of(el), el.timestamp.0)) == false)
+        })
+        .copied()
+        .collect();
+    sorted_queries.sort_by_key(|el| (slot_of(el), el.timestamp.0));
+
+    let mut slots: Vec<_> = initial_values
+        .into_iter()
+        .map(|(slot, initial_value)| {
+            let (shard_id, address, key) = slot;
+            StorageSlotSummary {
+                shard_id,
+                address,
+                key,
+                initial_value,
+                final_value: current_values[&slot],
+                is_written: false,
+            }
+        })
+        .collect();
+    slots.sort_by_key(|el| (el.shard_id, el.address, el.key));
+    for query in sorted_queries.iter().filter(|el| el.rw_flag) {
+        let idx = slots
+            .binary_search_by_key(&slot_of(query), |el| (el.shard_id, el.address, el.key))
+            .unwrap();
+        slots[idx].is_written = true;
+    }
+
+    let (repeated_writes, initial_writes): (Vec<_>, Vec<_>) = slots
+        .iter()
+        .filter(|el| el.is_written)
+        .copied()
+        .partition(|el| is_known_slot(el.shard_id, &el.address, &el.key));
+
+    Ok(DeduplicatedStorageLog {
+        sorted_queries,
+        slots,
+        initial_writes,
+        repeated_writes,
+    })
+}
+
+impl DeduplicatedStorageLog {
+    /// Checks that final values of all touched slots are the ones the storage has, e.g.
+    /// `InMemoryStorage::inner` after the execution. Absent slots are zeroes
+    pub fn check_against_storage(
+        &self,
+        storage: &[HashMap<Address, HashMap<U256, U256>>],
+    ) -> Result<(), StorageLogError> {
+        for slot in self.slots.iter() {
+            let actual = storage[slot.shard_id as usize]
+                .get(&slot.address)
+                .and_then(|el| el.get(&slot.key))
+                .copied()
+                .unwrap_or(U256::zero());
+            if actual != slot.final_value {
+                return Err(StorageLogError::FinalValueMismatch {
+                    shard_id: slot.shard_id,
+                    address: slot.address,
+                    key: slot.key,
+                    expected: slot.final_value,
+                    actual,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}